@@ -1,13 +1,34 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use anyhow::{Ok, Result};
 use crossterm::event::{KeyCode, KeyModifiers};
-use tokio::sync::mpsc::{channel, unbounded_channel, Receiver};
+use tokio::sync::mpsc::{channel, unbounded_channel, Receiver, Sender, UnboundedReceiver};
 
 use crate::cli::args::{AppMode, Args, ProtocolType};
-use crate::protocols::{common, Message, ProtocolHandler};
+use crate::config::templates::load_templates;
+use crate::protocols::proxy::{ProxyCommand, ProxyDirection, ProxyEvent};
+use crate::protocols::{common, sockopt::SocketOption, LinkState, Message, ProtocolHandler};
+use crate::capture::har::HarExport;
+use crate::capture::history::MessageHistory;
+use crate::capture::pcap::{PcapExport, Transport};
+use crate::capture::replay::{spawn_replayer, Replayer};
+use crate::repeater::RepeaterSet;
+use crate::script::{spawn_script_host, ScriptHost};
+use crate::transfer::{parse_file_reference, spawn_file_transfer, TransferProgress};
 use crate::ui::layout::{AppLayout, LayoutType};
-use crate::ui::widgets::{input_dialog::InputDialog, message_view::MessageView, status_bar::StatusBar};
+use crate::utils::data_format::bytes_to_hex;
+use crate::utils::template::{ParsedFrame, Template};
+use crate::ui::widgets::{
+    input_dialog::{FormatType, InputDialog},
+    message_view::MessageView,
+    repeater_panel::RepeaterPanel,
+    rule_panel::RulePanel,
+    socket_panel::SocketPanel,
+    fault_panel::FaultPanel,
+    status_bar::StatusBar,
+};
 // use crate
 
 /// 应用程序状态
@@ -27,7 +48,13 @@ pub struct Stats {
     pub sent_bytes: usize,
     pub received_bytes: usize,
     pub connected: bool,
+    /// 启用自动重连时的客户端连接状态
+    pub link: Option<LinkState>,
     pub last_activity: Instant,
+    /// 最近一次文件发送的进度
+    pub transfer: Option<TransferProgress>,
+    /// 周期发送的次数
+    pub repeated_sends: usize,
 }
 
 impl Default for Stats {
@@ -36,7 +63,10 @@ impl Default for Stats {
             sent_bytes: 0,
             received_bytes: 0,
             connected: false,
+            link: None,
             last_activity: Instant::now(),
+            transfer: None,
+            repeated_sends: 0,
         }
     }
 }
@@ -65,9 +95,142 @@ pub struct App {
     pub protocol_handler: Box<dyn ProtocolHandler + Send + Sync>,
     /// 服务端到UI的消息接收通道
     pub server_to_ui_rx: Option<Receiver<Message>>,
+    /// 二进制结构模板
+    pub templates: Vec<Template>,
+    /// 周期发送任务
+    pub repeaters: RepeaterSet,
+    /// 周期发送任务面板
+    pub repeater_panel: RepeaterPanel,
+    /// 自动回复规则面板
+    pub rule_panel: RulePanel,
+    /// 套接字选项面板
+    pub socket_panel: SocketPanel,
+    /// 故障注入面板
+    pub fault_panel: FaultPanel,
+    /// 会话消息历史 (用于导出抓包文件)
+    pub history: MessageHistory,
+    /// 代理模式下数据注入的方向
+    inject_direction: ProxyDirection,
+    /// 代理扣留的数据 (连接 ID, 方向, 数据)，按扣留顺序排列
+    held: VecDeque<(String, ProxyDirection, bytes::Bytes)>,
+    /// 正在编辑扣留数据的连接
+    editing_held: Option<String>,
+    /// 文件发送进度接收通道
+    transfer_rx: Receiver<TransferProgress>,
+    /// 文件发送进度发送通道 (传给后台发送任务)
+    transfer_tx: Sender<TransferProgress>,
+    /// 输入发送的消息写出的通知，写出的数据记入消息历史
+    write_ack: common::WriteAck,
+    /// 输入发送的消息实际写出的连接和数据
+    written_rx: UnboundedReceiver<common::Written>,
     pub args: Args,
 }
 
+/// 按命令行参数创建并启动协议处理器
+///
+/// 指定了记录文件时开始记录；启用了回放或脚本时，处理器发往 `server_to_ui_tx` 的消息先依次经过回放器和脚本宿主。
+pub async fn start_session(
+    args: &Args,
+    server_to_ui_tx: Sender<Message>,
+) -> Result<Box<dyn ProtocolHandler + Send + Sync>> {
+    if let Some(path) = &args.log {
+        args.options.capture.start(path, args.log_format)?;
+    }
+
+    // 显示主机名解析出的全部地址
+    for resolved in &args.resolved {
+        let event = common::MessageType::Event(common::SessionEvent::Resolved(resolved.clone()));
+        let _ = server_to_ui_tx.try_send(Message::new_received(event, None));
+    }
+
+    // 从UI一侧向处理器一侧依次创建各中间层的输入通道
+    let mut layers = Vec::new();
+    let mut downstream = server_to_ui_tx;
+    if let Some(path) = &args.script {
+        let (tx, rx) = channel::<Message>(1000);
+        layers.push((SessionLayer::Script(Box::new(ScriptHost::load(path)?)), rx, downstream));
+        downstream = tx;
+    }
+    if let Some(replay) = &args.replay {
+        let (tx, rx) = channel::<Message>(1000);
+        layers.push((
+            SessionLayer::Replay(Replayer::new(replay.recording.clone(), replay.options.clone(), args.options.framing.clone())),
+            rx,
+            downstream,
+        ));
+        downstream = tx;
+    }
+
+    let handler = common::create_protocol_handler(
+        args.protocol.name(),
+        args.mode == AppMode::Server,
+        Some(downstream),
+        args.local_addrs.clone(),
+        args.remote_addrs.clone(),
+        args.unix_path.as_deref(),
+        args.options.clone(),
+    )
+    .await?;
+
+    if let Some(to_handler) = handler.get_ui_to_server_sender() {
+        for (layer, rx, tx) in layers {
+            match layer {
+                SessionLayer::Script(host) => spawn_script_host(*host, rx, tx, to_handler.clone()),
+                SessionLayer::Replay(replayer) => spawn_replayer(replayer, rx, tx, to_handler.clone()),
+            }
+        }
+    }
+    Ok(handler)
+}
+
+/// 将会话消息历史导出为 pcapng 文件，返回写入的报文数
+pub fn export_pcap(args: &Args, history: &MessageHistory, path: &Path) -> Result<usize> {
+    let transport = match args.protocol {
+        ProtocolType::Udp | ProtocolType::Http3 => Transport::Udp,
+        ProtocolType::Unix | ProtocolType::UnixDatagram => anyhow::bail!("pcap export needs an IP session, not a Unix socket"),
+        _ => Transport::Tcp,
+    };
+    let export = PcapExport::new(
+        transport,
+        args.local_addr(),
+        args.mode == AppMode::Client,
+        args.options.clone(),
+    );
+    export.write(path, history.iter())
+}
+
+/// 将会话消息历史中的 HTTP 请求和响应导出为 HAR 文件，返回写入的条目数
+pub fn export_har(args: &Args, history: &MessageHistory, path: &Path) -> Result<usize> {
+    let export = HarExport::new(args.local_addr(), args.mode == AppMode::Client, args.options.clone());
+    export.write(path, history.iter())
+}
+
+/// 按 `--pcap`/`--har` 导出会话，返回每项导出的结果说明
+pub fn export_on_exit(args: &Args, history: &MessageHistory) -> Vec<String> {
+    let mut reports = Vec::new();
+    if let Some(path) = &args.pcap {
+        reports.push(match export_pcap(args, history, path) {
+            core::result::Result::Ok(count) => format!("Exported {} packets to {}", count, path.display()),
+            Err(e) => format!("{:#}", e),
+        });
+    }
+    if let Some(path) = &args.har {
+        reports.push(match export_har(args, history, path) {
+            core::result::Result::Ok(count) => format!("Exported {} HTTP entries to {}", count, path.display()),
+            Err(e) => format!("{:#}", e),
+        });
+    }
+    reports
+}
+
+/// 处理器与UI之间的消息处理层
+enum SessionLayer {
+    /// 自动化脚本
+    Script(Box<ScriptHost>),
+    /// 会话回放
+    Replay(Replayer),
+}
+
 impl App {
     pub async fn new(args: Args) -> Result<Self> {
         // 根据参数确定布局方式
@@ -112,12 +275,25 @@ impl App {
                 AppMode::Server => ("HTTP/3 Server Send", "HTTP/3 Server Receive"),
                 AppMode::Client => ("HTTP/3 Client Send", "HTTP/3 Client Receive"),
             },
+            ProtocolType::Unix => match args.mode {
+                AppMode::Server => ("Unix Server Send", "Unix Server Receive"),
+                AppMode::Client => ("Unix Client Send", "Unix Client Receive"),
+            },
+            ProtocolType::UnixDatagram => match args.mode {
+                AppMode::Server => ("Unix Datagram Server Send", "Unix Datagram Server Receive"),
+                AppMode::Client => ("Unix Datagram Client Send", "Unix Datagram Client Receive"),
+            },
+            ProtocolType::TcpProxy => ("TCP Proxy: upstream -> client", "TCP Proxy: client -> upstream"),
         };
 
-        let handler =
-            common::create_protocol_handler("tcp", true, Some(server_to_ui_tx), args.local_addr, None).await?;
+        let templates = load_templates(&args.template_files)?;
 
-        let app = Self {
+        let handler = start_session(&args, server_to_ui_tx).await?;
+
+        let (transfer_tx, transfer_rx) = channel::<TransferProgress>(100);
+        let (write_ack, written_rx) = common::WriteAck::new();
+
+        let mut app = Self {
             should_quit: false,
             input_mode: InputMode::Normal,
             layout: AppLayout::new(layout_type),
@@ -129,38 +305,127 @@ impl App {
             // ui_to_server_tx,
             protocol_handler: handler,
             server_to_ui_rx: Some(server_to_ui_rx),
+            templates,
+            repeaters: RepeaterSet::default(),
+            repeater_panel: RepeaterPanel::default(),
+            rule_panel: RulePanel {
+                visible: !args.options.auto_reply.is_empty(),
+                selected: 0,
+            },
+            socket_panel: SocketPanel::default(),
+            fault_panel: FaultPanel::default(),
+            history: MessageHistory::default(),
+            inject_direction: ProxyDirection::ToUpstream,
+            held: VecDeque::new(),
+            editing_held: None,
+            transfer_rx,
+            transfer_tx,
+            write_ack,
+            written_rx,
             args,
         };
 
+        // 启动参数指定了文件时立即发送
+        if let Some(path) = app.args.send_file.clone() {
+            app.send_file(path);
+        }
+
         Ok(app)
     }
 
     pub fn receive_message(&mut self) {
         // 从 Option 中取出接收器的所有权
+        // 输入发送的消息按实际写出的连接和数据记入历史
+        while let core::result::Result::Ok(written) = self.written_rx.try_recv() {
+            self.history.push_written(written);
+        }
+
         if let Some(server_to_ui_rx) = self.server_to_ui_rx.as_mut() {
             // 处理接收到的消息
-            match server_to_ui_rx.try_recv() {
+            let received = server_to_ui_rx.try_recv();
+            if let core::result::Result::Ok(message) = &received {
+                self.history.push(message);
+            }
+            match received {
+                // 脚本或回放发送的数据显示在发送区
+                // 代理发往客户端的数据按连接对显示
+                core::result::Result::Ok(message) if message.direction == common::MessageDirection::Sent => {
+                    if let Some(data) = message.content.payload() {
+                        let label = match (&self.args.protocol, &message.connection_info) {
+                            (ProtocolType::TcpProxy, Some(info)) => info.connection_id.clone(),
+                            _ => "Auto".to_string(),
+                        };
+                        self.stats.sent_bytes += data.len();
+                        self.stats.last_activity = Instant::now();
+                        self.send_view.add_message(format!(
+                            "[{}] [{}] {}",
+                            message.timestamp.format("%H:%M:%S"),
+                            label,
+                            bytes_to_hex(&data)
+                        ));
+                    }
+                }
                 core::result::Result::Ok(message) => match message.content {
                     common::MessageType::Text(txt) => {
-                        self.add_received_message(txt, None);
+                        let parsed = self.parse_with_templates(txt.as_bytes());
+                        let from = self.proxy_label(message.connection_info.as_ref());
+                        self.add_received_message(txt, from);
+                        self.add_parsed_frame(parsed);
                     }
                     common::MessageType::ClientConnected => {
+                        self.stats.connected = true;
                         self.receive_view
                             .add_connection(&message.connection_info.unwrap().connection_id);
                     }
                     common::MessageType::ClientDisconnected => {
-                        self.receive_view
-                            .close_connection_by_title(&message.connection_info.unwrap().connection_id);
+                        self.stats.connected = false;
+                        let id = message.connection_info.unwrap().connection_id;
+                        self.held.retain(|(held_id, ..)| *held_id != id);
+                        self.receive_view.close_connection_by_title(&id);
                     }
                     common::MessageType::Binary(data) => {
                         // 将二进制数据显示为十六进制
                         let hex_str: String = data.iter().map(|b| format!("{:02x}", b)).collect();
-                        self.add_received_message(format!("[Binary] {}", hex_str), None);
+                        let parsed = self.parse_with_templates(&data);
+                        let from = self.proxy_label(message.connection_info.as_ref());
+                        self.add_received_message(format!("[Binary] {}", hex_str), from);
+                        self.add_parsed_frame(parsed);
+                    }
+                    common::MessageType::Raw(data) => {
+                        // 原始数据 (不完整的帧等) 不做模板解析，按内容显示为文本或十六进制
+                        let text = match std::str::from_utf8(&data) {
+                            core::result::Result::Ok(text) => text.to_string(),
+                            Err(_) => format!("[Binary] {}", data.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+                        };
+                        let from = self.proxy_label(message.connection_info.as_ref());
+                        self.add_received_message(text, from);
                     }
                     common::MessageType::Hex(hex_str) => {
                         // 十六进制消息直接显示
                         self.add_received_message(format!("[Hex] {}", hex_str), None);
                     }
+                    common::MessageType::Event(common::SessionEvent::AutoReply(hit)) => {
+                        // 自动回复显示在发送区
+                        self.stats.sent_bytes += hit.reply.len();
+                        self.stats.last_activity = Instant::now();
+                        self.send_view.add_message(format!(
+                            "[{}] [Auto:{}] {}",
+                            chrono::Local::now().format("%H:%M:%S"),
+                            hit.rule,
+                            bytes_to_hex(&hit.reply)
+                        ));
+                    }
+                    common::MessageType::Event(event) => {
+                        if let common::SessionEvent::Link(state) = event {
+                            self.stats.link = Some(state);
+                        }
+                        if let (common::SessionEvent::Proxy(proxy_event), Some(info)) = (&event, &message.connection_info) {
+                            self.track_held(&info.connection_id, proxy_event);
+                        }
+                        // 会话事件 (如校验失败) 以告警形式显示，不计入收到的数据
+                        self.add_event_message(&event);
+                    }
+                    common::MessageType::Control(_) | common::MessageType::Proxy(_) => {}
                 },
                 core::result::Result::Err(_) => {
                     // 没有消息可接收，继续执行
@@ -188,8 +453,70 @@ impl App {
             // 输入模式 (I)
             (KeyCode::Char('i'), KeyModifiers::NONE) => {
                 self.input_mode = InputMode::Editing;
-                self.input_dialog = Some(InputDialog::new());
+                let mut dialog = InputDialog::new();
+                dialog.set_templates(self.templates.clone());
+                if self.args.protocol == ProtocolType::TcpProxy {
+                    dialog.proxy_direction = Some(self.inject_direction);
+                }
+                self.input_dialog = Some(dialog);
+            }
+
+            // 代理: 暂停/恢复客户端到上游 (U)、上游到客户端 (D) 的转发，编辑最早扣留的数据 (E)
+            (KeyCode::Char('u'), KeyModifiers::NONE) if self.args.protocol == ProtocolType::TcpProxy => {
+                self.send_proxy_command(ProxyCommand::TogglePause(ProxyDirection::ToUpstream), self.selected_target());
+            }
+            (KeyCode::Char('d'), KeyModifiers::NONE) if self.args.protocol == ProtocolType::TcpProxy => {
+                self.send_proxy_command(ProxyCommand::TogglePause(ProxyDirection::ToClient), self.selected_target());
+            }
+            (KeyCode::Char('e'), KeyModifiers::NONE) if self.args.protocol == ProtocolType::TcpProxy => self.edit_held(),
+
+            // 周期发送任务: 选择下一个 (N)、暂停/恢复 (P)、取消 (X)
+            (KeyCode::Char('n'), KeyModifiers::NONE) => self.repeaters.select_next(),
+            (KeyCode::Char('p'), KeyModifiers::NONE) => self.repeaters.toggle_pause_selected(),
+            (KeyCode::Char('x'), KeyModifiers::NONE) => self.repeaters.cancel_selected(),
+
+            // 自动回复规则: 显示/隐藏面板 (A)、选择下一条 (R)、启用/禁用 (空格)
+            (KeyCode::Char('a'), KeyModifiers::NONE) => self.rule_panel.visible = !self.rule_panel.visible,
+            (KeyCode::Char('r'), KeyModifiers::NONE) if self.rule_panel.visible => {
+                self.rule_panel.select_next(&self.args.options.auto_reply);
+            }
+            (KeyCode::Char(' '), KeyModifiers::NONE) if self.rule_panel.visible => {
+                self.args.options.auto_reply.toggle(self.rule_panel.selected);
+            }
+
+            // 套接字选项: 显示/隐藏面板 (O)、选择下一项 (J)、增大/减小 (+/-)
+            (KeyCode::Char('o'), KeyModifiers::NONE) => {
+                self.socket_panel.visible = !self.socket_panel.visible;
+                self.fault_panel.visible = false;
             }
+            (KeyCode::Char('j'), KeyModifiers::NONE) if self.socket_panel.visible => self.socket_panel.select_next(),
+            (KeyCode::Char(c @ ('+' | '=' | '-')), _) if self.socket_panel.visible => {
+                if let Some(option) = self.socket_panel.adjust(&self.args.options.socket, c != '-') {
+                    self.set_socket_option(option);
+                }
+            }
+
+            // 故障注入: 显示/隐藏面板 (M)，与套接字选项面板共用 J 和 +/-；作用于选中的连接，未选中时作用于整个会话
+            (KeyCode::Char('m'), KeyModifiers::NONE) => {
+                self.fault_panel.visible = !self.fault_panel.visible;
+                self.socket_panel.visible = false;
+            }
+            (KeyCode::Char('j'), KeyModifiers::NONE) if self.fault_panel.visible => self.fault_panel.select_next(),
+            (KeyCode::Char(c @ ('+' | '=' | '-')), _) if self.fault_panel.visible => self.adjust_fault(c != '-'),
+
+            // 连接控制: 切换选中的连接 (Tab/Shift+Tab)，半关闭 (F)、关闭/踢出 (C)、RST 中止 (K)
+            (KeyCode::Tab, KeyModifiers::NONE) => self.receive_view.next_tab(),
+            (KeyCode::BackTab, _) => self.receive_view.prev_tab(),
+            (KeyCode::Char('f'), KeyModifiers::NONE) => self.control_selected(common::ConnectionControl::Shutdown),
+            (KeyCode::Char('c'), KeyModifiers::NONE) => self.control_selected(common::ConnectionControl::Close),
+            (KeyCode::Char('k'), KeyModifiers::NONE) => self.control_selected(common::ConnectionControl::Abort),
+
+            // 开启/停止会话记录 (L)
+            (KeyCode::Char('l'), KeyModifiers::NONE) => self.toggle_capture(),
+
+            // 导出 pcapng 抓包文件 (W)、HAR 文件 (H)
+            (KeyCode::Char('w'), KeyModifiers::NONE) => self.export_pcap(),
+            (KeyCode::Char('h'), KeyModifiers::NONE) => self.export_har(),
             _ => {}
         }
         Ok(())
@@ -202,21 +529,95 @@ impl App {
                 KeyCode::Esc => {
                     self.input_mode = InputMode::Normal;
                     self.input_dialog = None;
+                    self.editing_held = None;
+                }
+                KeyCode::Tab => {
+                    // 切换 String/Hex 发送格式
+                    dialog.toggle_format();
+                }
+                KeyCode::Char('r') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    // 在消息内容和重复发送设置之间切换
+                    dialog.cycle_focus();
+                }
+                KeyCode::Char('d') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    // 切换代理数据注入的方向
+                    dialog.toggle_direction();
                 }
                 KeyCode::Enter => {
-                    // 获取输入内容并发送
-                    if let Some(input) = dialog.submit() {
-                        // 处理输入的内容，实际发送逻辑将由具体协议实现
-                        self.send_message(input);
+                    // 获取输入内容并按所选格式发送
+                    // @path 表示发送文件内容
+                    if let Some(path) = dialog.submit().as_deref().and_then(parse_file_reference) {
+                        self.send_file(path);
+                        self.input_mode = InputMode::Normal;
+                        self.input_dialog = None;
+                        return Ok(());
+                    }
+
+                    let message = match dialog.format_type {
+                        FormatType::String => dialog.submit().map(common::MessageType::Text),
+                        FormatType::Hex => dialog.submit().map(common::MessageType::Hex),
+                        FormatType::Template => match dialog.encode_template() {
+                            Some(core::result::Result::Ok(bytes)) => Some(common::MessageType::Binary(bytes.into())),
+                            Some(Err(e)) => {
+                                // 字段有误时保留对话框，便于修改
+                                self.send_view.add_message(format!("[!] Template error: {}", e));
+                                return Ok(());
+                            }
+                            None => None,
+                        },
+                    };
+                    // 编辑扣留的数据: 用输入的数据替换并放行，输入为空时丢弃
+                    if let (Some(id), Some(direction)) = (self.editing_held.clone(), dialog.proxy_direction) {
+                        let replacement = match message.as_ref().map(common::MessageType::payload) {
+                            Some(None) => {
+                                self.send_view.add_message("[!] Invalid hex input".to_string());
+                                return Ok(());
+                            }
+                            Some(payload) => payload,
+                            None => None,
+                        };
+                        self.send_proxy_command(ProxyCommand::Edit(direction, replacement), Some(id));
+                        self.editing_held = None;
+                        self.input_mode = InputMode::Normal;
+                        self.input_dialog = None;
+                        return Ok(());
+                    }
+                    if let Some(direction) = dialog.proxy_direction {
+                        self.inject_direction = direction;
+                    }
+
+                    let repeat = match dialog.repeat_spec() {
+                        core::result::Result::Ok(repeat) => repeat,
+                        Err(e) => {
+                            self.send_view.add_message(format!("[!] {}", e));
+                            return Ok(());
+                        }
+                    };
+                    match (message, repeat) {
+                        (Some(message), Some(repeat)) if message.payload().is_some() => {
+                            let id = self.repeaters.add(message, repeat.interval, repeat.count);
+                            self.send_view.add_message(format!(
+                                "[Repeat #{}] every {} ms, {}",
+                                id,
+                                repeat.interval.as_millis(),
+                                repeat.count.map(|n| format!("{} times", n)).unwrap_or_else(|| "forever".to_string())
+                            ));
+                        }
+                        (Some(message), _) => self.send_message(message),
+                        (None, _) => {}
                     }
                     self.input_mode = InputMode::Normal;
                     self.input_dialog = None;
                 }
+                KeyCode::Up => dialog.prev_field(),
+                KeyCode::Down => dialog.next_field(),
+                KeyCode::Left if matches!(dialog.format_type, FormatType::Template) => dialog.prev_template(),
+                KeyCode::Right if matches!(dialog.format_type, FormatType::Template) => dialog.next_template(),
                 KeyCode::Char(c) => {
-                    dialog.input.push(c);
+                    dialog.active_input_mut().push(c);
                 }
                 KeyCode::Backspace => {
-                    dialog.input.pop();
+                    dialog.active_input_mut().pop();
                 }
                 _ => {}
             }
@@ -242,28 +643,178 @@ impl App {
         let _ = self.protocol_handler.send_message(message_type, target).await;
     }
 
-    fn send_message(&mut self, message: String) {
+    fn send_message(&mut self, message_type: common::MessageType) {
+        self.send_message_with_label(message_type, "");
+    }
+
+    /// 修改套接字选项并应用到所有连接
+    fn set_socket_option(&mut self, option: SocketOption) {
+        self.args.options.socket.update(option);
+        self.send_view.add_message(format!("[!] Set {}", option));
+        if let Some(tx) = self.protocol_handler.get_ui_to_server_sender() {
+            let msg = common::Message::new_sent(
+                common::MessageType::Control(common::ConnectionControl::SetOption(option)),
+                None,
+            );
+            tokio::spawn(async move {
+                let _ = tx.send(msg).await;
+            });
+        }
+    }
+
+    /// 调整选中连接 (未选中时为整个会话) 的故障注入设置
+    fn adjust_fault(&mut self, up: bool) {
+        let target = self.selected_target();
+        let faults = &self.args.options.faults;
+        faults.update(target.as_deref(), |config| self.fault_panel.adjust(config, up));
+        let config = faults.get(target.as_deref());
+        self.send_view.add_message(format!("[!] Faults [{}]: {}", target.as_deref().unwrap_or("session"), config));
+    }
+
+    /// 接收区选中的连接，未选中时为 None
+    fn selected_target(&self) -> Option<String> {
+        self.receive_view.selected_connection().map(str::to_string)
+    }
+
+    /// 连接 ID 对应的连接信息
+    fn connection_info(id: String) -> common::ConnectionInfo {
+        match id.parse() {
+            core::result::Result::Ok(remote_addr) => common::ConnectionInfo {
+                remote_addr,
+                connection_id: id,
+                local_addr: None,
+            },
+            Err(_) => common::ConnectionInfo::non_ip(id),
+        }
+    }
+
+    /// 向接收区选中的连接发送控制命令；服务器未选中连接时不执行，避免误关闭所有客户端
+    fn control_selected(&mut self, control: common::ConnectionControl) {
+        let target = self.selected_target().map(Self::connection_info);
+        let server = self.args.mode == AppMode::Server;
+        if server && target.is_none() {
+            self.send_view.add_message("[!] Select a connection with Tab first".to_string());
+            return;
+        }
+
+        let action = match control {
+            common::ConnectionControl::Shutdown => "Half-close",
+            common::ConnectionControl::Abort => "Abort (RST)",
+            _ if server => "Kick",
+            _ => "Close",
+        };
+        let name = target.as_ref().map(|info| info.connection_id.as_str()).unwrap_or("connection");
+        self.send_view.add_message(format!("[!] {} {}", action, name));
+        if let Some(tx) = self.protocol_handler.get_ui_to_server_sender() {
+            let msg = common::Message::new_sent(common::MessageType::Control(control), target);
+            tokio::spawn(async move {
+                let _ = tx.send(msg).await;
+            });
+        }
+    }
+
+    /// 向代理的指定连接对 (None 为所有连接对) 发送代理命令
+    fn send_proxy_command(&mut self, command: ProxyCommand, target: Option<String>) {
+        if let Some(tx) = self.protocol_handler.get_ui_to_server_sender() {
+            let msg = common::Message::new_sent(common::MessageType::Proxy(command), target.map(Self::connection_info));
+            tokio::spawn(async move {
+                let _ = tx.send(msg).await;
+            });
+        }
+    }
+
+    /// 打开对话框编辑选中连接对 (未选中时为任一连接对) 最早扣留的数据
+    fn edit_held(&mut self) {
+        let selected = self.selected_target();
+        let Some((id, direction, data)) = self
+            .held
+            .iter()
+            .find(|(id, ..)| selected.as_ref().is_none_or(|selected| selected == id))
+            .cloned()
+        else {
+            self.send_view.add_message("[!] No held proxy data (pause with U/D first)".to_string());
+            return;
+        };
+        self.input_mode = InputMode::Editing;
+        self.input_dialog = Some(InputDialog::for_held(direction, bytes_to_hex(&data)));
+        self.editing_held = Some(id);
+    }
+
+    /// 按代理事件更新扣留的数据
+    fn track_held(&mut self, id: &str, event: &ProxyEvent) {
+        match event {
+            ProxyEvent::Held { direction, data, .. } => self.held.push_back((id.to_string(), *direction, data.clone())),
+            ProxyEvent::Edited { direction, .. } | ProxyEvent::Dropped { direction, .. } => {
+                if let Some(index) = self.held.iter().position(|(held_id, held_direction, _)| held_id == id && held_direction == direction) {
+                    self.held.remove(index);
+                }
+            }
+            ProxyEvent::Resumed { direction, .. } => {
+                self.held.retain(|(held_id, held_direction, _)| held_id != id || held_direction != direction);
+            }
+            _ => {}
+        }
+    }
+
+    /// 代理模式下接收区消息的连接对标签
+    fn proxy_label(&self, info: Option<&common::ConnectionInfo>) -> Option<String> {
+        info.filter(|_| self.args.protocol == ProtocolType::TcpProxy)
+            .map(|info| info.connection_id.clone())
+    }
+
+    /// 发送消息，发送区显示时加上标签前缀 (如周期发送任务编号)
+    fn send_message_with_label(&mut self, message_type: common::MessageType, label: &str) {
+        // 代理转发的数据 (含注入的数据) 由代理按连接对上报显示，这里只按方向注入到选中的连接对
+        if self.args.protocol == ProtocolType::TcpProxy {
+            match self.args.options.outgoing_frame(&message_type) {
+                core::result::Result::Ok(Some(data)) => {
+                    let command = ProxyCommand::Inject(self.inject_direction, data);
+                    self.send_proxy_command(command, self.selected_target());
+                }
+                core::result::Result::Ok(None) => self.send_view.add_message("[!] Invalid hex input".to_string()),
+                Err(e) => self.send_view.add_message(format!("[!] {}", e)),
+            }
+            return;
+        }
+
         // 创建一个本地任务来执行异步发送
         // 注意：这里我们不在同步方法中等待结果，而是让消息在后台发送
-        let message_type = common::MessageType::Text(message.clone());
-        let target = None;
-        
+        let (display, len) = match &message_type {
+            common::MessageType::Text(text) => (text.clone(), text.len()),
+            common::MessageType::Hex(hex_str) => match message_type.payload() {
+                Some(bytes) => (format!("[Hex] {}", hex_str), bytes.len()),
+                None => {
+                    self.send_view.add_message(format!("[!] Invalid hex input: {}", hex_str));
+                    return;
+                }
+            },
+            common::MessageType::Binary(bytes) => (format!("[Binary] {}", bytes_to_hex(bytes)), bytes.len()),
+            other => (format!("{:?}", other), 0),
+        };
+
         // 更新统计数据和 UI
-        self.stats.sent_bytes += message.len();
+        self.stats.sent_bytes += len;
         self.stats.last_activity = Instant::now();
         self.send_view
-            .add_message(format!("[{}] {}", chrono::Local::now().format("%H:%M:%S"), message));
+            .add_message(format!("[{}] {}{}", chrono::Local::now().format("%H:%M:%S"), label, display));
         
         // 尝试获取发送器并发送消息
         // 注意：由于不能直接在同步方法中调用 async 方法，
         // 我们在这里只是记录消息，实际的发送应该通过其他机制处理
         // 或者使用 spawn 来在后台执行
         if let Some(tx) = self.protocol_handler.get_ui_to_server_sender() {
+            // 写出后才记入历史，未连接、被拒绝的消息不会出现在导出的抓包中；
+            // 代理注入的数据由处理器发往UI，已随其他转发的数据记入历史
+            let ack = match self.args.protocol {
+                ProtocolType::TcpProxy => common::WriteAck::default(),
+                _ => self.write_ack.clone(),
+            };
             let msg = common::Message {
                 content: message_type,
                 direction: common::MessageDirection::Sent,
                 timestamp: chrono::Local::now(),
                 connection_info: None,
+                ack,
             };
             // 使用 tokio::spawn 在后台发送，不阻塞当前线程
             tokio::spawn(async move {
@@ -272,13 +823,108 @@ impl App {
         }
     }
 
+    /// 在后台发送文件内容，按启动参数中的分块选项分块
+    fn send_file(&mut self, path: PathBuf) {
+        let Some(tx) = self.protocol_handler.get_ui_to_server_sender() else {
+            self.send_view.add_message(format!("[!] Cannot send {}: not connected", path.display()));
+            return;
+        };
+
+        self.send_view.add_message(format!(
+            "[{}] [File] {}",
+            chrono::Local::now().format("%H:%M:%S"),
+            path.display()
+        ));
+        spawn_file_transfer(path, self.args.chunking.clone(), self.args.options.clone(), tx, self.transfer_tx.clone());
+    }
+
+    /// 开启或停止会话记录，未指定 `--log` 时记录到当前目录下带时间戳的文件
+    fn toggle_capture(&mut self) {
+        let capture = &self.args.options.capture;
+        if let Some(path) = capture.path() {
+            capture.stop();
+            self.receive_view.add_message(format!("[log] Stopped logging to {}", path.display()));
+            return;
+        }
+
+        let path = self
+            .args
+            .log
+            .clone()
+            .unwrap_or_else(|| self.args.log_format.default_path());
+        match capture.start(&path, self.args.log_format) {
+            core::result::Result::Ok(()) => {
+                self.receive_view.add_message(format!("[log] Logging to {}", path.display()));
+            }
+            Err(e) => self.receive_view.add_message(format!("[!] {:#}", e)),
+        }
+    }
+
+    /// 将会话导出为 pcapng 文件，未指定 `--pcap` 时导出到当前目录下带时间戳的文件
+    pub fn export_pcap(&mut self) {
+        let path = self.args.pcap.clone().unwrap_or_else(PcapExport::default_path);
+        match export_pcap(&self.args, &self.history, &path) {
+            core::result::Result::Ok(count) => {
+                self.receive_view
+                    .add_message(format!("[pcap] Exported {} packets to {}", count, path.display()));
+            }
+            Err(e) => self.receive_view.add_message(format!("[!] {:#}", e)),
+        }
+    }
+
+    /// 将会话中的 HTTP 请求和响应导出为 HAR 文件，未指定 `--har` 时导出到当前目录下带时间戳的文件
+    pub fn export_har(&mut self) {
+        let path = self.args.har.clone().unwrap_or_else(HarExport::default_path);
+        match export_har(&self.args, &self.history, &path) {
+            core::result::Result::Ok(count) => {
+                self.receive_view
+                    .add_message(format!("[har] Exported {} HTTP entries to {}", count, path.display()));
+            }
+            Err(e) => self.receive_view.add_message(format!("[!] {:#}", e)),
+        }
+    }
+
+    /// 定时任务: 发送到期的周期消息
+    pub fn on_tick(&mut self) {
+        for (id, message) in self.repeaters.take_due(Instant::now()) {
+            self.stats.repeated_sends += 1;
+            self.send_message_with_label(message, &format!("[R#{}] ", id));
+        }
+    }
+
+    /// 处理后台文件发送的进度更新
+    pub fn poll_transfers(&mut self) {
+        while let core::result::Result::Ok(progress) = self.transfer_rx.try_recv() {
+            self.stats.sent_bytes += progress.chunk;
+            self.stats.last_activity = Instant::now();
+
+            if progress.done {
+                let result = match &progress.error {
+                    Some(e) => format!("[!] File {} failed: {}", progress.path, e),
+                    None => format!("[File] {} sent ({} bytes)", progress.path, progress.sent),
+                };
+                self.send_view.add_message(result);
+            }
+            self.stats.transfer = Some(progress);
+        }
+    }
+
     /// 添加接收到的消息
     pub fn add_received_message(&mut self, message: String, from: Option<String>) {
         // 更新统计数据
         self.stats.received_bytes += message.len();
         self.stats.last_activity = Instant::now();
 
-        // 添加消息到接收视图
+        self.show_received_line(message, from);
+    }
+
+    /// 在接收区显示会话事件，不更新收到数据的统计
+    fn add_event_message(&mut self, event: &common::SessionEvent) {
+        self.show_received_line(format!("[!] {}", event), None);
+    }
+
+    /// 添加一行到接收视图
+    fn show_received_line(&mut self, message: String, from: Option<String>) {
         let prefix = if let Some(addr) = from {
             format!("[{}] [{}]", chrono::Local::now().format("%H:%M:%S"), addr)
         } else {
@@ -288,6 +934,18 @@ impl App {
         self.receive_view.add_message(format!("{} {}", prefix, message));
     }
 
+    /// 按已加载的模板解析收到的帧，返回第一个匹配的结果
+    fn parse_with_templates(&self, data: &[u8]) -> Option<ParsedFrame> {
+        self.templates.iter().find_map(|template| template.parse(data))
+    }
+
+    /// 在接收区显示模板解析结果
+    fn add_parsed_frame(&mut self, parsed: Option<ParsedFrame>) {
+        if let Some(parsed) = parsed {
+            self.receive_view.add_message(format!("    -> {}", parsed));
+        }
+    }
+
     /// 更新连接状态
     pub fn set_connected(&mut self, connected: bool) {
         self.stats.connected = connected;
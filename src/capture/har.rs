@@ -0,0 +1,467 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Local, SecondsFormat};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{BufWriter, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use crate::capture::history::{stream_events, StreamEvent};
+use crate::protocols::{Message, SessionOptions};
+
+/// 解析请求或响应头时允许的最大头部字段数
+const MAX_HEADERS: usize = 100;
+
+/// HTTP 消息的起始行
+#[derive(Debug, Clone, PartialEq)]
+enum StartLine {
+    Request { method: String, target: String },
+    Response { status: u16, reason: String },
+}
+
+/// 从数据流中解析出的一个 HTTP/1.x 请求或响应
+#[derive(Debug, Clone)]
+struct HttpMessage {
+    start: StartLine,
+    version: String,
+    headers: Vec<(String, String)>,
+    /// 消息体 (已去掉分块编码)
+    body: Vec<u8>,
+    headers_size: usize,
+    /// 线路上的消息体长度
+    body_size: usize,
+    /// 首字节和末字节的时间
+    started: DateTime<Local>,
+    finished: DateTime<Local>,
+}
+
+impl HttpMessage {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn method(&self) -> Option<&str> {
+        match &self.start {
+            StartLine::Request { method, .. } => Some(method),
+            StartLine::Response { .. } => None,
+        }
+    }
+}
+
+/// 解析分块编码的消息体，返回解码后的数据和在线路上占用的长度，数据不完整时返回 None
+fn decode_chunked(data: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let line_end = |from: usize| data[from..].windows(2).position(|w| w == b"\r\n").map(|p| from + p);
+    let mut body = Vec::new();
+    let mut pos = 0;
+    loop {
+        let end = line_end(pos)?;
+        let size = std::str::from_utf8(&data[pos..end]).ok()?.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        pos = end + 2;
+        if size == 0 {
+            // 跳过尾部字段直到空行
+            loop {
+                let end = line_end(pos)?;
+                let empty = end == pos;
+                pos = end + 2;
+                if empty {
+                    return Some((body, pos));
+                }
+            }
+        }
+        // 块大小溢出视为格式错误
+        let chunk_end = pos.checked_add(size)?;
+        if data.len() < chunk_end.checked_add(2)? {
+            return None;
+        }
+        body.extend_from_slice(&data[pos..chunk_end]);
+        pos = chunk_end + 2;
+    }
+}
+
+/// 单方向的 HTTP/1.x 数据流
+#[derive(Default)]
+struct HttpStream {
+    buffer: Vec<u8>,
+    /// 缓冲区中第一个字节到达的时间
+    started: Option<DateTime<Local>>,
+}
+
+impl HttpStream {
+    fn push(&mut self, data: &[u8], timestamp: DateTime<Local>) {
+        if self.buffer.is_empty() {
+            self.started = Some(timestamp);
+        }
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// 从缓冲区取出一个完整的消息
+    ///
+    /// `method` 为响应对应的请求方法；`closed` 为 true 表示连接已关闭，没有长度的响应体到此结束。
+    fn take(&mut self, request: bool, method: Option<&str>, now: DateTime<Local>, closed: bool) -> Option<HttpMessage> {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let parsed = if request {
+            let mut req = httparse::Request::new(&mut headers);
+            match req.parse(&self.buffer) {
+                Ok(httparse::Status::Complete(len)) => {
+                    let start = StartLine::Request {
+                        method: req.method.unwrap_or_default().to_string(),
+                        target: req.path.unwrap_or_default().to_string(),
+                    };
+                    Ok(Some((len, start, req.version.unwrap_or(1), owned_headers(req.headers))))
+                }
+                Ok(httparse::Status::Partial) => Ok(None),
+                Err(e) => Err(e),
+            }
+        } else {
+            let mut resp = httparse::Response::new(&mut headers);
+            match resp.parse(&self.buffer) {
+                Ok(httparse::Status::Complete(len)) => {
+                    let start = StartLine::Response {
+                        status: resp.code.unwrap_or_default(),
+                        reason: resp.reason.unwrap_or_default().to_string(),
+                    };
+                    Ok(Some((len, start, resp.version.unwrap_or(1), owned_headers(resp.headers))))
+                }
+                Ok(httparse::Status::Partial) => Ok(None),
+                Err(e) => Err(e),
+            }
+        };
+        let (head, start, version, headers) = match parsed {
+            Ok(Some(parsed)) => parsed,
+            Ok(None) => return None,
+            // 不是 HTTP 数据，丢弃
+            Err(_) => {
+                self.buffer.clear();
+                return None;
+            }
+        };
+
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        let rest = &self.buffer[head..];
+        let no_body = match &start {
+            StartLine::Response { status, .. } => *status < 200 || *status == 204 || *status == 304 || method == Some("HEAD"),
+            StartLine::Request { .. } => false,
+        };
+        let (body, body_size) = if no_body {
+            (Vec::new(), 0)
+        } else if header("transfer-encoding").is_some_and(|value| value.to_ascii_lowercase().contains("chunked")) {
+            decode_chunked(rest)?
+        } else if let Some(length) = header("content-length").and_then(|value| value.trim().parse::<usize>().ok()) {
+            (rest.get(..length)?.to_vec(), length)
+        } else if request {
+            (Vec::new(), 0)
+        } else if closed {
+            (rest.to_vec(), rest.len())
+        } else {
+            return None;
+        };
+
+        let message = HttpMessage {
+            start,
+            version: format!("HTTP/1.{}", version),
+            headers,
+            body,
+            headers_size: head,
+            body_size,
+            started: self.started.unwrap_or(now),
+            finished: now,
+        };
+        self.buffer.drain(..head + body_size);
+        self.started = (!self.buffer.is_empty()).then_some(now);
+        Some(message)
+    }
+}
+
+fn owned_headers(headers: &[httparse::Header]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|header| (header.name.to_string(), String::from_utf8_lossy(header.value).into_owned()))
+        .collect()
+}
+
+/// 单个连接上的请求和响应
+struct Exchange {
+    server: SocketAddr,
+    requests: HttpStream,
+    responses: HttpStream,
+    /// 等待响应的请求
+    pending: VecDeque<HttpMessage>,
+}
+
+impl Exchange {
+    fn new(server: SocketAddr) -> Self {
+        Self {
+            server,
+            requests: HttpStream::default(),
+            responses: HttpStream::default(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// 取出已完整的请求和响应，配对的结果放入 `entries`
+    fn collect(&mut self, now: DateTime<Local>, closed: bool, entries: &mut Vec<(HttpMessage, HttpMessage, SocketAddr)>) {
+        while let Some(request) = self.requests.take(true, None, now, closed) {
+            self.pending.push_back(request);
+        }
+        loop {
+            let method = self.pending.front().and_then(|request| request.method().map(str::to_string));
+            let Some(response) = self.responses.take(false, method.as_deref(), now, closed) else {
+                break;
+            };
+            // 100 Continue 等中间响应不结束请求
+            if matches!(response.start, StartLine::Response { status: 100..=199, .. }) {
+                continue;
+            }
+            if let Some(request) = self.pending.pop_front() {
+                entries.push((request, response, self.server));
+            }
+        }
+    }
+}
+
+/// 两个时间之间的毫秒数
+fn millis(from: DateTime<Local>, to: DateTime<Local>) -> f64 {
+    ((to - from).num_microseconds().unwrap_or_default() as f64 / 1000.0).max(0.0)
+}
+
+fn headers_json(headers: &[(String, String)]) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect()
+}
+
+/// 消息体的文本形式，不是 UTF-8 时使用 base64 编码
+fn body_json(mime_type: &str, body: &[u8]) -> Value {
+    match std::str::from_utf8(body) {
+        Ok(text) => json!({ "mimeType": mime_type, "text": text }),
+        Err(_) => json!({ "mimeType": mime_type, "text": STANDARD.encode(body), "encoding": "base64" }),
+    }
+}
+
+/// 生成一个 HAR 条目
+fn har_entry(request: &HttpMessage, response: &HttpMessage, server: SocketAddr) -> Value {
+    let (method, target) = match &request.start {
+        StartLine::Request { method, target } => (method.as_str(), target.as_str()),
+        StartLine::Response { .. } => ("", ""),
+    };
+    let (status, reason) = match &response.start {
+        StartLine::Response { status, reason } => (*status, reason.as_str()),
+        StartLine::Request { .. } => (0, ""),
+    };
+    let url = if target.starts_with("http://") || target.starts_with("https://") {
+        target.to_string()
+    } else {
+        let host = request.header("host").map(str::to_string).unwrap_or_else(|| server.to_string());
+        format!("http://{}{}", host, target)
+    };
+    let query: Vec<Value> = target
+        .split_once('?')
+        .map(|(_, query)| query)
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            json!({ "name": name, "value": value })
+        })
+        .collect();
+
+    let send = millis(request.started, request.finished);
+    let wait = millis(request.finished, response.started);
+    let receive = millis(response.started, response.finished);
+
+    let mut har_request = json!({
+        "method": method,
+        "url": url,
+        "httpVersion": request.version,
+        "cookies": [],
+        "headers": headers_json(&request.headers),
+        "queryString": query,
+        "headersSize": request.headers_size,
+        "bodySize": request.body_size,
+    });
+    if !request.body.is_empty() {
+        har_request["postData"] = body_json(request.header("content-type").unwrap_or_default(), &request.body);
+    }
+    let mut content = body_json(response.header("content-type").unwrap_or_default(), &response.body);
+    content["size"] = json!(response.body.len());
+
+    json!({
+        "startedDateTime": request.started.to_rfc3339_opts(SecondsFormat::Millis, false),
+        "time": send + wait + receive,
+        "request": har_request,
+        "response": {
+            "status": status,
+            "statusText": reason,
+            "httpVersion": response.version,
+            "cookies": [],
+            "headers": headers_json(&response.headers),
+            "content": content,
+            "redirectURL": response.header("location").unwrap_or_default(),
+            "headersSize": response.headers_size,
+            "bodySize": response.body_size,
+        },
+        "cache": {},
+        "timings": {
+            "blocked": -1,
+            "dns": -1,
+            "connect": -1,
+            "ssl": -1,
+            "send": send,
+            "wait": wait,
+            "receive": receive,
+        },
+        "serverIPAddress": server.ip().to_string(),
+    })
+}
+
+/// HAR 1.2 导出: 从消息历史中每个连接的数据流解析 HTTP/1.x 请求和响应并配对
+///
+/// 客户端会话中本端发出的是请求，服务器会话中对端发来的是请求。
+pub struct HarExport {
+    /// 本端地址 (服务器模式下为监听地址)
+    pub local_addr: SocketAddr,
+    /// 本端是否为客户端
+    pub active_open: bool,
+    /// 会话选项，用于还原线路上的数据
+    pub options: SessionOptions,
+}
+
+impl HarExport {
+    pub fn new(local_addr: SocketAddr, active_open: bool, options: SessionOptions) -> Self {
+        Self {
+            local_addr,
+            active_open,
+            options,
+        }
+    }
+
+    /// 默认导出路径: 当前目录下带时间戳的文件
+    pub fn default_path() -> PathBuf {
+        PathBuf::from(format!("nt-{}.har", Local::now().format("%Y%m%d-%H%M%S")))
+    }
+
+    /// 按消息历史生成 HAR 条目，按请求开始时间排列
+    pub fn entries<'a>(&self, history: impl IntoIterator<Item = &'a Message>) -> Vec<Value> {
+        let mut exchanges: HashMap<String, Exchange> = HashMap::new();
+        let mut pairs = Vec::new();
+
+        for (timestamp, info, event) in stream_events(history, &self.options) {
+            match event {
+                StreamEvent::Open => {
+                    let server = if self.active_open { info.remote_addr } else { info.local_addr.unwrap_or(self.local_addr) };
+                    exchanges.insert(info.connection_id.clone(), Exchange::new(server));
+                }
+                StreamEvent::Data { outbound, payload } => {
+                    let Some(exchange) = exchanges.get_mut(&info.connection_id) else {
+                        continue;
+                    };
+                    if outbound == self.active_open {
+                        exchange.requests.push(&payload, timestamp);
+                    } else {
+                        exchange.responses.push(&payload, timestamp);
+                    }
+                    exchange.collect(timestamp, false, &mut pairs);
+                }
+                StreamEvent::Close => {
+                    if let Some(mut exchange) = exchanges.remove(&info.connection_id) {
+                        exchange.collect(timestamp, true, &mut pairs);
+                    }
+                }
+            }
+        }
+
+        pairs.sort_by_key(|(request, _, _)| request.started);
+        pairs
+            .iter()
+            .map(|(request, response, server)| har_entry(request, response, *server))
+            .collect()
+    }
+
+    /// 将消息历史导出为 HAR 文件，返回写入的条目数
+    pub fn write<'a>(&self, path: &Path, history: impl IntoIterator<Item = &'a Message>) -> Result<usize> {
+        let entries = self.entries(history);
+        let count = entries.len();
+        let har = json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "nt", "version": env!("CARGO_PKG_VERSION") },
+                "pages": [],
+                "entries": entries,
+            }
+        });
+
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &har)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writer.flush())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::{ConnectionInfo, MessageType};
+    use bytes::Bytes;
+
+    #[test]
+    fn test_decode_chunked() {
+        assert_eq!(
+            decode_chunked(b"5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\nX-Trailer: 1\r\n\r\nnext"),
+            Some((b"hello world".to_vec(), 46))
+        );
+        assert_eq!(decode_chunked(b"5\r\nhel"), None);
+        assert_eq!(decode_chunked(b"ffffffffffffffff\r\nhello\r\n0\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_client_entries() {
+        let export = HarExport::new("0.0.0.0:0".parse().unwrap(), true, SessionOptions::default());
+        let info = Some(ConnectionInfo {
+            remote_addr: "93.184.216.34:80".parse().unwrap(),
+            connection_id: "93.184.216.34:80".to_string(),
+            local_addr: None,
+        });
+        let binary = Bytes::from_static(b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: 2\r\n\r\n\x89P");
+        let history = vec![
+            Message::new_received(MessageType::ClientConnected, info.clone()),
+            Message::new_sent(
+                MessageType::Text("GET /a?x=1&y HTTP/1.1\r\nHost: example.com\r\n\r\nHEAD /b HTTP/1.1\r\n\r\n".to_string()),
+                None,
+            ),
+            Message::new_received(MessageType::Binary(binary), info.clone()),
+            // HEAD 响应带 Content-Length 但没有消息体
+            Message::new_received(
+                MessageType::Binary(Bytes::from_static(b"HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\n")),
+                info.clone(),
+            ),
+        ];
+
+        let entries = export.entries(&history);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["request"]["url"], "http://example.com/a?x=1&y");
+        assert_eq!(entries[0]["request"]["queryString"][1], json!({ "name": "y", "value": "" }));
+        assert_eq!(entries[0]["response"]["content"]["text"], "iVA=");
+        assert_eq!(entries[0]["response"]["content"]["encoding"], "base64");
+        assert_eq!(entries[1]["request"]["url"], "http://93.184.216.34:80/b");
+        assert_eq!(entries[1]["response"]["status"], 404);
+        assert_eq!(entries[1]["response"]["bodySize"], 0);
+    }
+}
@@ -0,0 +1,204 @@
+use bytes::Bytes;
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+
+use crate::protocols::common::Written;
+use crate::protocols::{ConnectionInfo, Message, MessageDirection, MessageType, SessionEvent, SessionOptions};
+
+/// 历史中保留的最大消息数
+const HISTORY_LIMIT: usize = 100_000;
+
+/// 会话消息历史: 保存收发的数据、连接事件和自动回复，供导出抓包文件使用
+#[derive(Debug, Default)]
+pub struct MessageHistory {
+    messages: VecDeque<Message>,
+}
+
+impl MessageHistory {
+    /// 记录一条消息，其他会话事件不影响流量，不保存
+    pub fn push(&mut self, message: &Message) {
+        let keep = match &message.content {
+            MessageType::Event(event) => matches!(event, SessionEvent::AutoReply(_)),
+            MessageType::Control(_) | MessageType::Proxy(_) => false,
+            _ => true,
+        };
+        if !keep {
+            return;
+        }
+
+        if self.messages.len() >= HISTORY_LIMIT {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message.clone());
+    }
+
+    /// 记录发送的消息经某个连接实际写出的数据
+    pub fn push_written(&mut self, written: Written) {
+        self.push(&Message {
+            timestamp: written.timestamp,
+            ..Message::new_sent(MessageType::Raw(written.frame), Some(written.connection_info))
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Message> {
+        self.messages.iter()
+    }
+}
+
+/// 单个连接上的流量事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    Open,
+    /// 线路上的数据，`outbound` 为 true 表示本端发出
+    Data { outbound: bool, payload: Bytes },
+    Close,
+}
+
+/// 还原消息在线路上的数据 (发送时追加的校验值和分帧，接收时解码去掉的分帧)
+fn wire_payload(options: &SessionOptions, direction: MessageDirection, content: &MessageType) -> Option<Bytes> {
+    match (direction, content) {
+        (MessageDirection::Sent, content) => options.outgoing_frame(content).ok().flatten(),
+        // 未解码的数据 (不完整的帧、数据报等) 已是线路上的数据
+        (MessageDirection::Received, MessageType::Raw(data)) => Some(data.clone()),
+        (MessageDirection::Received, content) => options.framing.encode(&content.payload()?).ok(),
+    }
+}
+
+/// 按实际发送时间 (自动回复按规则延迟发送) 排列历史中每个连接上的流量
+///
+/// 未指定连接的发送数据展开到当时已建立的每个连接；历史中缺少连接事件时 (如超出历史长度)
+/// 在该连接的首个数据前补上建立事件。
+pub fn stream_events<'a>(
+    history: impl IntoIterator<Item = &'a Message>,
+    options: &SessionOptions,
+) -> Vec<(DateTime<Local>, &'a ConnectionInfo, StreamEvent)> {
+    let mut events: Vec<(DateTime<Local>, Option<&ConnectionInfo>, StreamEvent)> = Vec::new();
+    for message in history {
+        let connection = message.connection_info.as_ref();
+        let (timestamp, event) = match &message.content {
+            MessageType::ClientConnected => (message.timestamp, StreamEvent::Open),
+            MessageType::ClientDisconnected => (message.timestamp, StreamEvent::Close),
+            MessageType::Event(SessionEvent::AutoReply(hit)) => {
+                let reply = MessageType::Binary(hit.reply.clone());
+                let Some(payload) = wire_payload(options, MessageDirection::Sent, &reply) else {
+                    continue;
+                };
+                let delay = chrono::Duration::from_std(hit.delay).unwrap_or_default();
+                (message.timestamp + delay, StreamEvent::Data { outbound: true, payload })
+            }
+            content => match wire_payload(options, message.direction, content) {
+                Some(payload) => (
+                    message.timestamp,
+                    StreamEvent::Data {
+                        outbound: message.direction == MessageDirection::Sent,
+                        payload,
+                    },
+                ),
+                None => continue,
+            },
+        };
+        events.push((timestamp, connection, event));
+    }
+    events.sort_by_key(|(timestamp, _, _)| *timestamp);
+
+    // 按建立顺序排列的已建立连接
+    let mut open: Vec<&ConnectionInfo> = Vec::new();
+    let mut streams = Vec::new();
+    for (timestamp, connection, event) in events {
+        let targets = match (connection, &event) {
+            (Some(info), _) => vec![info],
+            (None, StreamEvent::Data { outbound: true, .. }) => open.clone(),
+            (None, _) => continue,
+        };
+
+        for info in targets {
+            let is_open = open.iter().any(|other| other.connection_id == info.connection_id);
+            match &event {
+                StreamEvent::Open if is_open => continue,
+                StreamEvent::Open => open.push(info),
+                StreamEvent::Data { .. } if !is_open => {
+                    open.push(info);
+                    streams.push((timestamp, info, StreamEvent::Open));
+                }
+                StreamEvent::Data { .. } => {}
+                StreamEvent::Close if !is_open => continue,
+                StreamEvent::Close => open.retain(|other| other.connection_id != info.connection_id),
+            }
+            streams.push((timestamp, info, event.clone()));
+        }
+    }
+    streams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_received_wire_payload() {
+        let options = SessionOptions {
+            framing: crate::protocols::framing::FramingConfig {
+                mode: "len:2".parse().unwrap(),
+                idle_gap: None,
+            },
+            ..Default::default()
+        };
+        let info = Some(ConnectionInfo {
+            remote_addr: "127.0.0.1:50000".parse().unwrap(),
+            connection_id: "127.0.0.1:50000".to_string(),
+            local_addr: None,
+        });
+        let history = vec![
+            // 解码后的帧还原长度前缀，未解码的不完整帧原样保留
+            Message::new_received(MessageType::Text("ab".to_string()), info.clone()),
+            Message::new_received(MessageType::Raw(Bytes::from_static(&[0x00, 0x05, b'x'])), info.clone()),
+        ];
+
+        let payloads: Vec<Bytes> = stream_events(&history, &options)
+            .into_iter()
+            .filter_map(|(_, _, event)| match event {
+                StreamEvent::Data { payload, .. } => Some(payload),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![Bytes::from_static(&[0x00, 0x02, b'a', b'b']), Bytes::from_static(&[0x00, 0x05, b'x'])]
+        );
+    }
+
+    #[test]
+    fn test_written_payload() {
+        let options = SessionOptions {
+            framing: crate::protocols::framing::FramingConfig {
+                mode: "len:2".parse().unwrap(),
+                idle_gap: None,
+            },
+            ..Default::default()
+        };
+        let connection = |id: &str| ConnectionInfo {
+            remote_addr: id.parse().unwrap(),
+            connection_id: id.to_string(),
+            local_addr: None,
+        };
+        let mut history = MessageHistory::default();
+        for id in ["127.0.0.1:1", "127.0.0.1:2"] {
+            history.push(&Message::new_received(MessageType::ClientConnected, Some(connection(id))));
+        }
+        // 只记录实际写出的连接和数据，已分帧的数据不再封装
+        history.push_written(Written {
+            connection_info: connection("127.0.0.1:2"),
+            frame: Bytes::from_static(&[0x00, 0x02, b'h', b'i']),
+            timestamp: Local::now(),
+        });
+
+        let data: Vec<(&str, Bytes)> = stream_events(history.iter(), &options)
+            .into_iter()
+            .filter_map(|(_, info, event)| match event {
+                StreamEvent::Data { outbound: true, payload } => Some((info.connection_id.as_str(), payload)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(data, vec![("127.0.0.1:2", Bytes::from_static(&[0x00, 0x02, b'h', b'i']))]);
+    }
+}
@@ -0,0 +1,406 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Local};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncWriteExt, BufWriter},
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+
+use crate::protocols::{ConnectionInfo, MessageDirection};
+use crate::utils::data_format::{bytes_to_hex, hex_to_bytes};
+
+/// 缓冲数据写入磁盘的周期
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 会话记录格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// 便于阅读的文本，每条记录一行
+    #[default]
+    Text,
+    /// JSON Lines，每条记录一个 JSON 对象
+    Jsonl,
+    /// 按连接和方向分别保存原始字节，路径为目录
+    Raw,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(LogFormat::Text),
+            "jsonl" | "json" => Ok(LogFormat::Jsonl),
+            "raw" | "bin" => Ok(LogFormat::Raw),
+            other => Err(format!("Unknown log format: {}", other)),
+        }
+    }
+}
+
+impl LogFormat {
+    /// 运行时开启记录且未指定路径时使用的默认路径
+    pub fn default_path(&self) -> PathBuf {
+        let stamp = Local::now().format("%Y%m%d-%H%M%S");
+        match self {
+            LogFormat::Text => PathBuf::from(format!("nt-{}.log", stamp)),
+            LogFormat::Jsonl => PathBuf::from(format!("nt-{}.jsonl", stamp)),
+            LogFormat::Raw => PathBuf::from(format!("nt-{}", stamp)),
+        }
+    }
+}
+
+/// 记录的事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogEvent {
+    /// 收到或发送的数据，两个方向都是线路上的原始字节 (含分帧)
+    Data(Bytes),
+    /// 连接建立
+    Connected,
+    /// 连接断开
+    Disconnected,
+    /// 其他会话事件 (校验失败、自动回复等)
+    Note(String),
+}
+
+/// 一条会话记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    /// 时间戳
+    pub timestamp: DateTime<Local>,
+    /// 方向
+    pub direction: MessageDirection,
+    /// 连接 ID，广播发送时为 None
+    pub connection: Option<String>,
+    /// 事件
+    pub event: LogEvent,
+}
+
+impl LogRecord {
+    pub fn new(direction: MessageDirection, connection: Option<&ConnectionInfo>, event: LogEvent) -> Self {
+        Self {
+            timestamp: Local::now(),
+            direction,
+            connection: connection.map(|info| info.connection_id.clone()),
+            event,
+        }
+    }
+
+    fn direction_name(&self) -> &'static str {
+        match self.direction {
+            MessageDirection::Received => "in",
+            MessageDirection::Sent => "out",
+        }
+    }
+
+    /// 转换为 JSON Lines 格式的一行
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::json!({
+            "time": self.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Micros, false),
+            "dir": self.direction_name(),
+            "conn": self.connection,
+        });
+        match &self.event {
+            LogEvent::Data(data) => {
+                value["type"] = "data".into();
+                value["len"] = data.len().into();
+                value["hex"] = bytes_to_hex(data).into();
+                if let Ok(text) = std::str::from_utf8(data) {
+                    value["text"] = text.into();
+                }
+            }
+            LogEvent::Connected => value["type"] = "connected".into(),
+            LogEvent::Disconnected => value["type"] = "disconnected".into(),
+            LogEvent::Note(note) => {
+                value["type"] = "note".into();
+                value["text"] = note.as_str().into();
+            }
+        }
+        value
+    }
+
+    /// 解析 JSON Lines 格式的一行，格式不正确时返回 None
+    pub fn from_json(value: &serde_json::Value) -> Option<Self> {
+        let timestamp = DateTime::parse_from_rfc3339(value["time"].as_str()?).ok()?.into();
+        let direction = match value["dir"].as_str()? {
+            "in" => MessageDirection::Received,
+            "out" => MessageDirection::Sent,
+            _ => return None,
+        };
+        let event = match value["type"].as_str()? {
+            "data" => LogEvent::Data(Bytes::from(hex_to_bytes(value["hex"].as_str()?).ok()?)),
+            "connected" => LogEvent::Connected,
+            "disconnected" => LogEvent::Disconnected,
+            "note" => LogEvent::Note(value["text"].as_str().unwrap_or_default().to_string()),
+            _ => return None,
+        };
+        Some(Self {
+            timestamp,
+            direction,
+            connection: value["conn"].as_str().map(str::to_string),
+            event,
+        })
+    }
+}
+
+impl fmt::Display for LogRecord {
+    /// 文本格式: `时间 方向 连接 内容`，数据同时显示十六进制和可打印字符
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arrow = match self.direction {
+            MessageDirection::Received => "<-",
+            MessageDirection::Sent => "->",
+        };
+        write!(
+            f,
+            "{} {} {} ",
+            self.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+            arrow,
+            self.connection.as_deref().unwrap_or("*")
+        )?;
+        match &self.event {
+            LogEvent::Data(data) => {
+                let printable: String = data
+                    .iter()
+                    .map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' })
+                    .collect();
+                write!(f, "[{} bytes] {} |{}|", data.len(), bytes_to_hex(data), printable)
+            }
+            LogEvent::Connected => write!(f, "connected"),
+            LogEvent::Disconnected => write!(f, "disconnected"),
+            LogEvent::Note(note) => write!(f, "{}", note),
+        }
+    }
+}
+
+/// 后台写入任务的句柄，丢弃后写入任务刷新缓冲并退出
+#[derive(Debug)]
+struct SessionLogger {
+    tx: UnboundedSender<LogRecord>,
+    path: PathBuf,
+    /// 后台写入任务
+    writer: JoinHandle<()>,
+}
+
+impl SessionLogger {
+    /// 打开记录文件 (追加写入) 并启动后台写入任务，打开失败时立即返回错误
+    fn start(path: &Path, format: LogFormat) -> Result<Self> {
+        let sink = match format {
+            LogFormat::Raw => {
+                std::fs::create_dir_all(path)
+                    .with_context(|| format!("Failed to create dump directory {}", path.display()))?;
+                LogSink::Raw {
+                    dir: path.to_path_buf(),
+                    files: HashMap::new(),
+                }
+            }
+            _ => {
+                let file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open log file {}", path.display()))?;
+                LogSink::File {
+                    writer: BufWriter::new(File::from_std(file)),
+                    json: format == LogFormat::Jsonl,
+                }
+            }
+        };
+
+        let (tx, rx) = unbounded_channel();
+        let writer = tokio::spawn(write_records(rx, sink));
+        Ok(Self {
+            tx,
+            path: path.to_path_buf(),
+            writer,
+        })
+    }
+}
+
+async fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Failed to open log file {}", path.display()))
+}
+
+/// 写入目标
+enum LogSink {
+    /// 文本或 JSON Lines 文件
+    File { writer: BufWriter<File>, json: bool },
+    /// 原始字节目录，每个连接和方向一个文件
+    Raw {
+        dir: PathBuf,
+        files: HashMap<String, BufWriter<File>>,
+    },
+}
+
+impl LogSink {
+    async fn write(&mut self, record: &LogRecord) -> Result<()> {
+        match self {
+            LogSink::File { writer, json } => {
+                let line = if *json {
+                    record.to_json().to_string()
+                } else {
+                    record.to_string()
+                };
+                writer.write_all(line.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+            }
+            LogSink::Raw { dir, files } => {
+                let LogEvent::Data(data) = &record.event else {
+                    return Ok(());
+                };
+                // 连接 ID 中的冒号等字符不适合作为文件名
+                let connection: String = record
+                    .connection
+                    .as_deref()
+                    .unwrap_or("broadcast")
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+                    .collect();
+                let name = format!("{}.{}.bin", connection, record.direction_name());
+                if !files.contains_key(&name) {
+                    let file = open_append(&dir.join(&name)).await?;
+                    files.insert(name.clone(), BufWriter::new(file));
+                }
+                if let Some(writer) = files.get_mut(&name) {
+                    writer.write_all(data).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        match self {
+            LogSink::File { writer, .. } => writer.flush().await?,
+            LogSink::Raw { files, .. } => {
+                for writer in files.values_mut() {
+                    writer.flush().await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 后台写入任务: 缓冲写入并定期刷新，通道关闭后刷新剩余数据
+async fn write_records(mut rx: UnboundedReceiver<LogRecord>, mut sink: LogSink) {
+    let mut flush_timer = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            record = rx.recv() => match record {
+                Some(record) => {
+                    if sink.write(&record).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            _ = flush_timer.tick() => {
+                if sink.flush().await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = sink.flush().await;
+}
+
+/// 会话记录开关，在UI和各协议处理器之间共享，运行时可随时开启或停止
+#[derive(Debug, Clone, Default)]
+pub struct SessionCapture {
+    logger: Arc<RwLock<Option<SessionLogger>>>,
+}
+
+impl SessionCapture {
+    /// 开始记录到指定路径，已在记录时先停止之前的记录
+    pub fn start(&self, path: &Path, format: LogFormat) -> Result<()> {
+        let logger = SessionLogger::start(path, format)?;
+        if let Ok(mut current) = self.logger.write() {
+            *current = Some(logger);
+        }
+        Ok(())
+    }
+
+    /// 停止记录，缓冲的数据会在后台写入完成
+    pub fn stop(&self) {
+        if let Ok(mut current) = self.logger.write() {
+            *current = None;
+        }
+    }
+
+    /// 停止记录并等待缓冲的数据全部写入，程序退出前调用
+    pub async fn finish(&self) {
+        let logger = self.logger.write().ok().and_then(|mut current| current.take());
+        if let Some(SessionLogger { tx, writer, .. }) = logger {
+            drop(tx);
+            let _ = writer.await;
+        }
+    }
+
+    /// 当前记录文件路径，未在记录时返回 None
+    pub fn path(&self) -> Option<PathBuf> {
+        self.logger
+            .read()
+            .ok()
+            .and_then(|logger| logger.as_ref().map(|l| l.path.clone()))
+    }
+
+    /// 记录一条事件，未开启记录时忽略
+    pub fn record(&self, direction: MessageDirection, connection: Option<&ConnectionInfo>, event: LogEvent) {
+        if let Ok(logger) = self.logger.read() {
+            if let Some(logger) = logger.as_ref() {
+                let _ = logger.tx.send(LogRecord::new(direction, connection, event));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(event: LogEvent) -> LogRecord {
+        LogRecord {
+            timestamp: "2024-05-01T12:00:00.250+08:00".parse::<DateTime<chrono::FixedOffset>>().unwrap().into(),
+            direction: MessageDirection::Received,
+            connection: Some("127.0.0.1:9000".to_string()),
+            event,
+        }
+    }
+
+    #[test]
+    fn test_json_record() {
+        let value = record(LogEvent::Data(Bytes::from_static(b"hi\x00"))).to_json();
+        assert_eq!(value["dir"], "in");
+        assert_eq!(value["conn"], "127.0.0.1:9000");
+        assert_eq!(value["type"], "data");
+        assert_eq!(value["len"], 3);
+        assert_eq!(value["hex"], "68 69 00");
+
+        let value = record(LogEvent::Connected).to_json();
+        assert_eq!(value["type"], "connected");
+        assert!(value.get("hex").is_none());
+
+        let original = record(LogEvent::Data(Bytes::from_static(&[0x01, 0xFF])));
+        assert_eq!(LogRecord::from_json(&original.to_json()), Some(original));
+    }
+
+    #[test]
+    fn test_text_record() {
+        let line = record(LogEvent::Data(Bytes::from_static(b"ok\n"))).to_string();
+        assert!(line.ends_with("<- 127.0.0.1:9000 [3 bytes] 6F 6B 0A |ok.|"));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod har;
+pub mod history;
+pub mod logger;
+pub mod pcap;
+pub mod pcap_import;
+pub mod replay;
@@ -0,0 +1,431 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+use crate::capture::history::{stream_events, StreamEvent};
+use crate::protocols::{ConnectionInfo, Message, SessionOptions};
+
+/// 合成 TCP 段的最大负载，超出时拆分为多个段
+const MAX_SEGMENT: usize = 1460;
+/// 合成 UDP 报文的最大负载
+const MAX_DATAGRAM: usize = 65_507;
+
+/// 本端和对端使用的合成 MAC 地址
+const LOCAL_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+const REMOTE_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x02];
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+/// 合成报文使用的传输层协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+/// 合成的一个以太网帧
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub timestamp: DateTime<Local>,
+    pub data: Vec<u8>,
+}
+
+/// 单个连接的合成状态
+struct Flow {
+    local: SocketAddr,
+    remote: SocketAddr,
+    /// 本端下一个发送序号
+    local_seq: u32,
+    /// 对端下一个发送序号
+    remote_seq: u32,
+}
+
+impl Flow {
+    /// 生成一个 TCP 段或 UDP 报文，并推进发送方的序号
+    fn packet(&mut self, transport: Transport, outbound: bool, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let (src, dst, seq, ack) = if outbound {
+            (self.local, self.remote, self.local_seq, self.remote_seq)
+        } else {
+            (self.remote, self.local, self.remote_seq, self.local_seq)
+        };
+        let advance = payload.len() as u32 + u32::from(flags & (TCP_SYN | TCP_FIN) != 0);
+        if outbound {
+            self.local_seq = self.local_seq.wrapping_add(advance);
+        } else {
+            self.remote_seq = self.remote_seq.wrapping_add(advance);
+        }
+
+        let (protocol, mut segment, checksum_at) = match transport {
+            Transport::Tcp => {
+                let mut segment = Vec::with_capacity(20 + payload.len());
+                segment.extend_from_slice(&src.port().to_be_bytes());
+                segment.extend_from_slice(&dst.port().to_be_bytes());
+                segment.extend_from_slice(&seq.to_be_bytes());
+                let ack = if flags & TCP_ACK != 0 { ack } else { 0 };
+                segment.extend_from_slice(&ack.to_be_bytes());
+                segment.extend_from_slice(&[5 << 4, flags, 0xFF, 0xFF, 0, 0, 0, 0]);
+                (IPPROTO_TCP, segment, 16)
+            }
+            Transport::Udp => {
+                let mut segment = Vec::with_capacity(8 + payload.len());
+                segment.extend_from_slice(&src.port().to_be_bytes());
+                segment.extend_from_slice(&dst.port().to_be_bytes());
+                segment.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+                segment.extend_from_slice(&[0, 0]);
+                (IPPROTO_UDP, segment, 6)
+            }
+        };
+        segment.extend_from_slice(payload);
+
+        let checksum = match transport_checksum(src.ip(), dst.ip(), protocol, &segment) {
+            // UDP 校验和为 0 表示未计算
+            0 if transport == Transport::Udp => 0xFFFF,
+            checksum => checksum,
+        };
+        segment[checksum_at..checksum_at + 2].copy_from_slice(&checksum.to_be_bytes());
+
+        let (src_mac, dst_mac) = if outbound { (LOCAL_MAC, REMOTE_MAC) } else { (REMOTE_MAC, LOCAL_MAC) };
+        ethernet_frame(src_mac, dst_mac, src.ip(), dst.ip(), protocol, &segment)
+    }
+}
+
+/// pcapng 导出: 按消息历史为每个连接合成以太网/IP/TCP或UDP报文
+///
+/// 数据按线路上的形式 (含分帧和追加的校验值) 放入报文，TCP 连接补上握手和挥手，
+/// 序号按连接连续递增，以便 Wireshark 重组数据流并交给 HTTP、WebSocket 等解析器。
+pub struct PcapExport {
+    /// 传输层协议
+    pub transport: Transport,
+    /// 本端地址 (服务器模式下为监听地址)
+    pub local_addr: SocketAddr,
+    /// 本端是否为主动发起连接的一方
+    pub active_open: bool,
+    /// 会话选项，用于还原线路上的数据
+    pub options: SessionOptions,
+}
+
+impl PcapExport {
+    pub fn new(transport: Transport, local_addr: SocketAddr, active_open: bool, options: SessionOptions) -> Self {
+        Self {
+            transport,
+            local_addr,
+            active_open,
+            options,
+        }
+    }
+
+    /// 默认导出路径: 当前目录下带时间戳的文件
+    pub fn default_path() -> PathBuf {
+        PathBuf::from(format!("nt-{}.pcapng", Local::now().format("%Y%m%d-%H%M%S")))
+    }
+
+    /// 本端在指定连接中使用的地址: 优先使用连接记录的本端地址，
+    /// 否则使用监听地址，地址未指定时按对端地址补全
+    fn local_endpoint(&self, info: &ConnectionInfo, index: usize) -> SocketAddr {
+        let local = info.local_addr.unwrap_or(self.local_addr);
+        let remote = info.remote_addr;
+        let ip = match (local.ip(), remote.ip()) {
+            (ip, remote) if ip.is_unspecified() && remote.is_loopback() => remote,
+            (IpAddr::V4(ip), IpAddr::V6(_)) => IpAddr::V6(ip.to_ipv6_mapped()),
+            (IpAddr::V6(ip), IpAddr::V4(_)) => IpAddr::V4(ip.to_ipv4_mapped().unwrap_or(Ipv4Addr::UNSPECIFIED)),
+            (ip, _) => ip,
+        };
+        // 客户端未绑定端口时使用合成的临时端口
+        let port = match local.port() {
+            0 => 49152 + (index % 16384) as u16,
+            port => port,
+        };
+        SocketAddr::new(ip, port)
+    }
+
+    /// 按消息历史合成报文
+    pub fn packets<'a>(&self, history: impl IntoIterator<Item = &'a Message>) -> Vec<Packet> {
+        let mut flows: HashMap<String, Flow> = HashMap::new();
+        let mut opened = 0;
+        let mut packets = Vec::new();
+
+        for (timestamp, info, event) in stream_events(history, &self.options) {
+            let mut emit = |data: Vec<u8>| packets.push(Packet { timestamp, data });
+            match event {
+                StreamEvent::Open => {
+                    let isn = (opened as u32).wrapping_mul(0x9E37_79B9).wrapping_add(0x1000_0000);
+                    let mut flow = Flow {
+                        local: self.local_endpoint(info, opened),
+                        remote: info.remote_addr,
+                        local_seq: isn,
+                        remote_seq: isn.rotate_left(16),
+                    };
+                    opened += 1;
+                    if self.transport == Transport::Tcp {
+                        let active = self.active_open;
+                        emit(flow.packet(Transport::Tcp, active, TCP_SYN, &[]));
+                        emit(flow.packet(Transport::Tcp, !active, TCP_SYN | TCP_ACK, &[]));
+                        emit(flow.packet(Transport::Tcp, active, TCP_ACK, &[]));
+                    }
+                    flows.insert(info.connection_id.clone(), flow);
+                }
+                StreamEvent::Data { outbound, payload } => {
+                    let Some(flow) = flows.get_mut(&info.connection_id) else {
+                        continue;
+                    };
+                    let limit = match self.transport {
+                        Transport::Tcp => MAX_SEGMENT,
+                        Transport::Udp => MAX_DATAGRAM,
+                    };
+                    for chunk in payload.chunks(limit) {
+                        emit(flow.packet(self.transport, outbound, TCP_PSH | TCP_ACK, chunk));
+                    }
+                }
+                StreamEvent::Close => {
+                    // 断开事件由对端关闭触发，由对端先发送 FIN
+                    if let (Some(mut flow), Transport::Tcp) = (flows.remove(&info.connection_id), self.transport) {
+                        emit(flow.packet(Transport::Tcp, false, TCP_FIN | TCP_ACK, &[]));
+                        emit(flow.packet(Transport::Tcp, true, TCP_FIN | TCP_ACK, &[]));
+                        emit(flow.packet(Transport::Tcp, false, TCP_ACK, &[]));
+                    }
+                }
+            }
+        }
+        packets
+    }
+
+    /// 将消息历史导出为 pcapng 文件，返回写入的报文数
+    pub fn write<'a>(&self, path: &Path, history: impl IntoIterator<Item = &'a Message>) -> Result<usize> {
+        let packets = self.packets(history);
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        write_pcapng(&mut writer, &packets)
+            .and_then(|()| writer.flush())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(packets.len())
+    }
+}
+
+/// 计算 TCP/UDP 校验和 (含 IP 伪首部)
+fn transport_checksum(src: IpAddr, dst: IpAddr, protocol: u8, segment: &[u8]) -> u16 {
+    let mut pseudo = Vec::with_capacity(40);
+    match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            pseudo.extend_from_slice(&src.octets());
+            pseudo.extend_from_slice(&dst.octets());
+            pseudo.extend_from_slice(&[0, protocol]);
+            pseudo.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+        }
+        _ => {
+            pseudo.extend_from_slice(&ipv6_octets(src));
+            pseudo.extend_from_slice(&ipv6_octets(dst));
+            pseudo.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+            pseudo.extend_from_slice(&[0, 0, 0, protocol]);
+        }
+    }
+    internet_checksum(&[&pseudo, segment])
+}
+
+/// 互联网校验和 (各部分长度除最后一部分外均为偶数)
+fn internet_checksum(parts: &[&[u8]]) -> u16 {
+    let mut sum: u32 = 0;
+    for part in parts {
+        for pair in part.chunks(2) {
+            sum += u32::from(u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]));
+        }
+    }
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn ipv6_octets(ip: IpAddr) -> [u8; 16] {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip) => ip.octets(),
+    }
+}
+
+/// 为传输层数据加上 IP 首部和以太网首部
+fn ethernet_frame(src_mac: [u8; 6], dst_mac: [u8; 6], src: IpAddr, dst: IpAddr, protocol: u8, segment: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(14 + 40 + segment.len());
+    frame.extend_from_slice(&dst_mac);
+    frame.extend_from_slice(&src_mac);
+
+    match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            frame.extend_from_slice(&[0x08, 0x00]);
+            let mut header = Vec::with_capacity(20);
+            header.extend_from_slice(&[0x45, 0]);
+            header.extend_from_slice(&((20 + segment.len()) as u16).to_be_bytes());
+            // 标识为 0，设置 DF 标志，TTL 64
+            header.extend_from_slice(&[0, 0, 0x40, 0, 64, protocol, 0, 0]);
+            header.extend_from_slice(&src.octets());
+            header.extend_from_slice(&dst.octets());
+            let checksum = internet_checksum(&[&header]);
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+            frame.extend_from_slice(&header);
+        }
+        _ => {
+            frame.extend_from_slice(&[0x86, 0xDD]);
+            frame.extend_from_slice(&[0x60, 0, 0, 0]);
+            frame.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+            frame.extend_from_slice(&[protocol, 64]);
+            frame.extend_from_slice(&ipv6_octets(src));
+            frame.extend_from_slice(&ipv6_octets(dst));
+        }
+    }
+    frame.extend_from_slice(segment);
+    frame
+}
+
+/// 写入一个 pcapng 块，块内容按 4 字节对齐
+fn write_block<W: Write>(out: &mut W, block_type: u32, body: &[u8]) -> std::io::Result<()> {
+    let padding = (4 - body.len() % 4) % 4;
+    let total = (12 + body.len() + padding) as u32;
+    out.write_all(&block_type.to_le_bytes())?;
+    out.write_all(&total.to_le_bytes())?;
+    out.write_all(body)?;
+    out.write_all(&[0; 3][..padding])?;
+    out.write_all(&total.to_le_bytes())
+}
+
+/// 写入 pcapng 文件: 节头块、一个以太网接口描述块，每个报文一个增强报文块 (微秒时间戳)
+fn write_pcapng<W: Write>(out: &mut W, packets: &[Packet]) -> std::io::Result<()> {
+    let mut header = Vec::with_capacity(16);
+    header.extend_from_slice(&0x1A2B_3C4Du32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes());
+    // 节长度未知
+    header.extend_from_slice(&(-1i64).to_le_bytes());
+    write_block(out, 0x0A0D_0D0A, &header)?;
+
+    // 链路类型 1 (以太网)，不限制抓包长度
+    let mut interface = Vec::with_capacity(8);
+    interface.extend_from_slice(&1u16.to_le_bytes());
+    interface.extend_from_slice(&0u16.to_le_bytes());
+    interface.extend_from_slice(&0u32.to_le_bytes());
+    write_block(out, 1, &interface)?;
+
+    for packet in packets {
+        let micros = packet.timestamp.timestamp_micros() as u64;
+        let mut block = Vec::with_capacity(20 + packet.data.len());
+        block.extend_from_slice(&0u32.to_le_bytes());
+        block.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        block.extend_from_slice(&(micros as u32).to_le_bytes());
+        block.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        block.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        block.extend_from_slice(&packet.data);
+        write_block(out, 6, &block)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::MessageType;
+    use bytes::Bytes;
+
+    fn connection(addr: &str) -> ConnectionInfo {
+        ConnectionInfo {
+            remote_addr: addr.parse().unwrap(),
+            connection_id: addr.to_string(),
+            local_addr: None,
+        }
+    }
+
+    /// 从以太网帧中取出 TCP 序号、确认号和标志
+    fn tcp_fields(frame: &[u8]) -> (u32, u32, u8) {
+        let tcp = &frame[14 + 20..];
+        (
+            u32::from_be_bytes(tcp[4..8].try_into().unwrap()),
+            u32::from_be_bytes(tcp[8..12].try_into().unwrap()),
+            tcp[13],
+        )
+    }
+
+    #[test]
+    fn test_tcp_sequence_numbers() {
+        let export = PcapExport::new(Transport::Tcp, "0.0.0.0:8080".parse().unwrap(), false, SessionOptions::default());
+        let info = Some(connection("127.0.0.1:50000"));
+        let history = vec![
+            Message::new_received(MessageType::ClientConnected, info.clone()),
+            Message::new_received(MessageType::Binary(Bytes::from_static(b"hello")), info.clone()),
+            Message::new_sent(MessageType::Text("hi".to_string()), None),
+            Message::new_received(MessageType::ClientDisconnected, info.clone()),
+        ];
+
+        let packets = export.packets(&history);
+        assert_eq!(packets.len(), 3 + 2 + 3);
+
+        let (client_isn, _, flags) = tcp_fields(&packets[0].data);
+        assert_eq!(flags, TCP_SYN);
+        let (server_isn, ack, _) = tcp_fields(&packets[1].data);
+        assert_eq!(ack, client_isn.wrapping_add(1));
+
+        let (seq, ack, flags) = tcp_fields(&packets[3].data);
+        assert_eq!((seq, ack, flags), (client_isn.wrapping_add(1), server_isn.wrapping_add(1), TCP_PSH | TCP_ACK));
+        // 未指定连接的发送数据广播到已建立的连接
+        let (seq, ack, _) = tcp_fields(&packets[4].data);
+        assert_eq!((seq, ack), (server_isn.wrapping_add(1), client_isn.wrapping_add(6)));
+        assert_eq!(&packets[4].data[14 + 20 + 20..], b"hi");
+
+        // 监听地址未指定时本端地址按对端补全，端口为监听端口
+        let ip = &packets[0].data[14..34];
+        assert_eq!(&ip[16..20], &[127, 0, 0, 1]);
+        assert_eq!(&packets[0].data[36..38], &8080u16.to_be_bytes());
+        assert_eq!(internet_checksum(&[ip]), 0);
+    }
+
+    #[test]
+    fn test_pcapng_blocks() {
+        let export = PcapExport::new(Transport::Udp, "127.0.0.1:9000".parse().unwrap(), true, SessionOptions::default());
+        let history = vec![Message::new_sent(
+            MessageType::Binary(Bytes::from_static(b"abc")),
+            Some(connection("127.0.0.1:9001")),
+        )];
+
+        let mut output = Vec::new();
+        write_pcapng(&mut output, &export.packets(&history)).unwrap();
+
+        // 节头块 28 字节，接口描述块 20 字节，报文块 32 + 14 + 20 + 8 + 3 字节并按 4 字节对齐
+        assert_eq!(&output[..4], &[0x0A, 0x0D, 0x0D, 0x0A]);
+        assert_eq!(output[28], 1);
+        assert_eq!(output[48], 6);
+        assert_eq!(output.len(), 28 + 20 + 32 + 48);
+    }
+
+    #[test]
+    fn test_local_addr_per_connection() {
+        let export = PcapExport::new(Transport::Udp, "0.0.0.0:7000".parse().unwrap(), false, SessionOptions::default());
+        // 多地址监听时每个连接使用各自所属的监听地址
+        let info = |remote: &str, local: &str| ConnectionInfo {
+            local_addr: Some(local.parse().unwrap()),
+            ..connection(remote)
+        };
+        let connections = [
+            info("10.0.0.2:5000", "10.0.0.1:7000"),
+            info("192.168.1.2:5000", "192.168.1.1:7001"),
+            connection("127.0.0.1:5000"),
+        ];
+
+        let locals: Vec<SocketAddr> = connections.iter().map(|info| export.local_endpoint(info, 0)).collect();
+        assert_eq!(
+            locals,
+            vec![
+                "10.0.0.1:7000".parse::<SocketAddr>().unwrap(),
+                "192.168.1.1:7001".parse().unwrap(),
+                "127.0.0.1:7000".parse().unwrap(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,471 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Local};
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::capture::logger::{LogEvent, LogRecord};
+use crate::capture::pcap::Transport;
+use crate::protocols::MessageDirection;
+
+const TCP_SYN: u8 = 0x02;
+const TCP_ACK: u8 = 0x10;
+
+/// 按端点选择抓包中的流: `IP:端口` 或只有端口
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowSelector {
+    pub ip: Option<IpAddr>,
+    pub port: u16,
+}
+
+impl FlowSelector {
+    fn matches(&self, addr: &SocketAddr) -> bool {
+        addr.port() == self.port && self.ip.is_none_or(|ip| ip == addr.ip())
+    }
+}
+
+impl FromStr for FlowSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(port) = s.parse::<u16>() {
+            return Ok(Self { ip: None, port });
+        }
+        s.parse::<SocketAddr>()
+            .map(|addr| Self {
+                ip: Some(addr.ip()),
+                port: addr.port(),
+            })
+            .map_err(|_| format!("Invalid flow (expected IP:PORT or PORT): {}", s))
+    }
+}
+
+/// 从抓包中提取的一个 TCP 连接或 UDP 会话
+#[derive(Debug, Clone)]
+pub struct PcapFlow {
+    pub transport: Transport,
+    pub server: SocketAddr,
+    /// 按时间排列的数据，客户端发出的为 Sent，服务端发出的为 Received (TCP 已重组)
+    pub records: Vec<LogRecord>,
+}
+
+impl PcapFlow {
+    /// 读取 pcap/pcapng 文件，提取匹配的第一个有数据的流 (未指定时为文件中第一个有数据的流)
+    pub fn load(path: &Path, selector: Option<FlowSelector>) -> Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let segments = read_capture(&data).with_context(|| format!("Failed to parse {}", path.display()))?;
+        extract_flow(&segments, selector).ok_or_else(|| match selector {
+            Some(selector) => anyhow::anyhow!(
+                "No TCP/UDP flow with data matching {}{} in {}",
+                selector.ip.map(|ip| format!("{} port ", ip)).unwrap_or_default(),
+                selector.port,
+                path.display()
+            ),
+            None => anyhow::anyhow!("No TCP/UDP flow with data in {}", path.display()),
+        })
+    }
+}
+
+/// 抓包中解析出的一个 TCP 段或 UDP 报文
+#[derive(Debug, Clone)]
+struct Segment {
+    timestamp: DateTime<Local>,
+    transport: Transport,
+    src: SocketAddr,
+    dst: SocketAddr,
+    seq: u32,
+    flags: u8,
+    payload: Bytes,
+}
+
+fn read_u16(data: &[u8], offset: usize, little: bool) -> Option<u16> {
+    let bytes: [u8; 2] = data.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if little { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+}
+
+fn read_u32(data: &[u8], offset: usize, little: bool) -> Option<u32> {
+    let bytes: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if little { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+}
+
+fn timestamp(units: u64, per_second: u64) -> DateTime<Local> {
+    let secs = (units / per_second) as i64;
+    let nanos = ((units % per_second) * 1_000_000_000 / per_second) as u32;
+    DateTime::from_timestamp(secs, nanos).unwrap_or_default().with_timezone(&Local)
+}
+
+/// 解析 pcap 或 pcapng 文件中的全部 TCP/UDP 报文
+fn read_capture(data: &[u8]) -> Result<Vec<Segment>> {
+    let magic = read_u32(data, 0, true).ok_or_else(|| anyhow::anyhow!("File too short"))?;
+    let mut segments = Vec::new();
+    let mut push = |timestamp, link_type, frame: &[u8]| {
+        if let Some(segment) = parse_frame(timestamp, link_type, frame) {
+            segments.push(segment);
+        }
+    };
+
+    match magic {
+        0x0A0D_0D0A => {
+            // pcapng: 每个节开头的字节序标记决定该节的字节序
+            let mut little = true;
+            let mut interfaces: Vec<(u32, u64)> = Vec::new();
+            let mut offset = 0;
+            while offset + 12 <= data.len() {
+                if read_u32(data, offset, true) == Some(0x0A0D_0D0A) {
+                    little = read_u32(data, offset + 8, true) == Some(0x1A2B_3C4D);
+                    interfaces.clear();
+                }
+                let block_type = read_u32(data, offset, little).unwrap_or_default();
+                let length = read_u32(data, offset + 4, little).unwrap_or_default() as usize;
+                if length < 12 || offset + length > data.len() {
+                    anyhow::bail!("Truncated pcapng block at offset {}", offset);
+                }
+                let body = &data[offset + 8..offset + length - 4];
+                match block_type {
+                    // 接口描述块
+                    1 => {
+                        let link_type = u32::from(read_u16(body, 0, little).unwrap_or_default());
+                        interfaces.push((link_type, interface_resolution(body, little)));
+                    }
+                    // 增强报文块
+                    6 => {
+                        let interface = read_u32(body, 0, little).unwrap_or_default() as usize;
+                        let high = u64::from(read_u32(body, 4, little).unwrap_or_default());
+                        let low = u64::from(read_u32(body, 8, little).unwrap_or_default());
+                        let captured = read_u32(body, 12, little).unwrap_or_default() as usize;
+                        if let (Some(&(link_type, resolution)), Some(frame)) =
+                            (interfaces.get(interface), body.get(20..20 + captured))
+                        {
+                            push(timestamp(high << 32 | low, resolution), link_type, frame);
+                        }
+                    }
+                    // 简单报文块 (无时间戳)
+                    3 => {
+                        if let (Some(&(link_type, _)), Some(frame)) = (interfaces.first(), body.get(4..)) {
+                            push(timestamp(0, 1), link_type, frame);
+                        }
+                    }
+                    _ => {}
+                }
+                offset += length;
+            }
+        }
+        0xA1B2_C3D4 | 0xD4C3_B2A1 | 0xA1B2_3C4D | 0x4D3C_B2A1 => {
+            // 经典 pcap: 魔数区分字节序和微秒/纳秒精度
+            let little = matches!(magic, 0xA1B2_C3D4 | 0xA1B2_3C4D);
+            let per_second = if matches!(magic, 0xA1B2_3C4D | 0x4D3C_B2A1) { 1_000_000_000 } else { 1_000_000 };
+            let link_type = read_u32(data, 20, little).ok_or_else(|| anyhow::anyhow!("Truncated pcap header"))?;
+            let mut offset = 24;
+            while offset + 16 <= data.len() {
+                let secs = u64::from(read_u32(data, offset, little).unwrap_or_default());
+                let fraction = u64::from(read_u32(data, offset + 4, little).unwrap_or_default());
+                let captured = read_u32(data, offset + 8, little).unwrap_or_default() as usize;
+                let Some(frame) = data.get(offset + 16..offset + 16 + captured) else {
+                    anyhow::bail!("Truncated pcap record at offset {}", offset);
+                };
+                push(timestamp(secs * per_second + fraction, per_second), link_type & 0xFFFF, frame);
+                offset += 16 + captured;
+            }
+        }
+        _ => anyhow::bail!("Not a pcap or pcapng file"),
+    }
+    Ok(segments)
+}
+
+/// 接口描述块中的时间戳精度 (if_tsresol 选项，默认微秒)，返回每秒的单位数
+fn interface_resolution(body: &[u8], little: bool) -> u64 {
+    let mut offset = 8;
+    while let (Some(code), Some(length)) = (read_u16(body, offset, little), read_u16(body, offset + 2, little)) {
+        let length = length as usize;
+        match (code, body.get(offset + 4)) {
+            (0, _) => break,
+            (9, Some(&value)) => {
+                return match value & 0x80 {
+                    0 => 10u64.saturating_pow(u32::from(value)),
+                    _ => 1u64 << (value & 0x7F).min(63),
+                };
+            }
+            _ => offset += 4 + length.div_ceil(4) * 4,
+        }
+    }
+    1_000_000
+}
+
+/// 去掉链路层首部，解析 IP 报文
+fn parse_frame(timestamp: DateTime<Local>, link_type: u32, frame: &[u8]) -> Option<Segment> {
+    let packet = match link_type {
+        // 以太网，跳过 VLAN 标签
+        1 => {
+            let mut offset = 12;
+            while matches!(read_u16(frame, offset, false)?, 0x8100 | 0x88A8) {
+                offset += 4;
+            }
+            frame.get(offset + 2..)?
+        }
+        // BSD 回环 (NULL/LOOP)
+        0 | 108 => frame.get(4..)?,
+        // 原始 IP
+        12 | 101 | 228 | 229 => frame,
+        // Linux cooked capture v1/v2
+        113 => frame.get(16..)?,
+        276 => frame.get(20..)?,
+        _ => return None,
+    };
+    parse_ip(timestamp, packet)
+}
+
+fn parse_ip(timestamp: DateTime<Local>, packet: &[u8]) -> Option<Segment> {
+    let (src, dst, mut protocol, mut payload) = match packet.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(packet[0] & 0x0F) * 4;
+            let total_len = usize::from(read_u16(packet, 2, false)?).min(packet.len());
+            // 只处理首个分片
+            if read_u16(packet, 6, false)? & 0x1FFF != 0 {
+                return None;
+            }
+            let src: [u8; 4] = packet.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = packet.get(16..20)?.try_into().ok()?;
+            (
+                IpAddr::V4(Ipv4Addr::from(src)),
+                IpAddr::V4(Ipv4Addr::from(dst)),
+                packet[9],
+                packet.get(header_len..total_len)?,
+            )
+        }
+        6 => {
+            let payload_len = usize::from(read_u16(packet, 4, false)?);
+            let src: [u8; 16] = packet.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = packet.get(24..40)?.try_into().ok()?;
+            let end = (40 + payload_len).min(packet.len());
+            (
+                IpAddr::V6(Ipv6Addr::from(src)),
+                IpAddr::V6(Ipv6Addr::from(dst)),
+                packet[6],
+                packet.get(40..end)?,
+            )
+        }
+        _ => return None,
+    };
+
+    // 跳过 IPv6 扩展首部
+    loop {
+        let header_len = match protocol {
+            0 | 43 | 60 => (usize::from(*payload.get(1)?) + 1) * 8,
+            44 if read_u16(payload, 2, false)? & 0xFFF8 != 0 => return None,
+            44 => 8,
+            51 => (usize::from(*payload.get(1)?) + 2) * 4,
+            _ => break,
+        };
+        protocol = *payload.first()?;
+        payload = payload.get(header_len..)?;
+    }
+
+    let src_port = read_u16(payload, 0, false)?;
+    let dst_port = read_u16(payload, 2, false)?;
+    let (transport, seq, flags, data) = match protocol {
+        6 => {
+            // 数据偏移小于最小首部长度的报文不合法
+            let header_len = usize::from(payload.get(12)? >> 4) * 4;
+            if header_len < 20 {
+                return None;
+            }
+            (Transport::Tcp, read_u32(payload, 4, false)?, *payload.get(13)?, payload.get(header_len..)?)
+        }
+        17 => {
+            let length = usize::from(read_u16(payload, 4, false)?).clamp(8, payload.len());
+            (Transport::Udp, 0, 0, payload.get(8..length)?)
+        }
+        _ => return None,
+    };
+
+    Some(Segment {
+        timestamp,
+        transport,
+        src: SocketAddr::new(src, src_port),
+        dst: SocketAddr::new(dst, dst_port),
+        seq,
+        flags,
+        payload: Bytes::copy_from_slice(data),
+    })
+}
+
+/// TCP 单方向的重组状态
+#[derive(Default)]
+struct Reassembler {
+    /// 下一个期望的序号
+    next: Option<u32>,
+    /// 乱序到达、等待前面数据的段
+    pending: Vec<(u32, Bytes)>,
+}
+
+impl Reassembler {
+    /// 放入一个段，返回按序可交付的数据 (去掉重传的部分)
+    fn push(&mut self, seq: u32, flags: u8, payload: Bytes) -> Option<Bytes> {
+        if flags & TCP_SYN != 0 {
+            self.next = Some(seq.wrapping_add(1));
+            self.pending.clear();
+            return None;
+        }
+        let next = *self.next.get_or_insert(seq);
+        if payload.is_empty() {
+            return None;
+        }
+
+        let ahead = seq.wrapping_sub(next) as i32;
+        if ahead > 0 {
+            self.pending.push((seq, payload));
+            return None;
+        }
+        let mut delivered = Vec::new();
+        self.deliver(seq, &payload, &mut delivered);
+        // 补齐后依次交付之前乱序到达的段
+        while let Some(index) = self
+            .pending
+            .iter()
+            .position(|(seq, _)| seq.wrapping_sub(self.next.unwrap_or_default()) as i32 <= 0)
+        {
+            let (seq, payload) = self.pending.swap_remove(index);
+            self.deliver(seq, &payload, &mut delivered);
+        }
+        (!delivered.is_empty()).then(|| Bytes::from(delivered))
+    }
+
+    fn deliver(&mut self, seq: u32, payload: &[u8], output: &mut Vec<u8>) {
+        let next = self.next.unwrap_or(seq);
+        let overlap = next.wrapping_sub(seq) as usize;
+        if overlap < payload.len() {
+            output.extend_from_slice(&payload[overlap..]);
+            self.next = Some(seq.wrapping_add(payload.len() as u32));
+        }
+    }
+}
+
+/// 判断会话中的客户端和服务端
+fn client_and_server(
+    first: &Segment,
+    segments: &[&Segment],
+    selector: Option<FlowSelector>,
+) -> (SocketAddr, SocketAddr) {
+    // 发送 SYN 的一方为客户端
+    if let Some(syn) = segments.iter().find(|s| s.flags & (TCP_SYN | TCP_ACK) == TCP_SYN) {
+        return (syn.src, syn.dst);
+    }
+    match selector {
+        Some(selector) if selector.matches(&first.src) => (first.dst, first.src),
+        Some(_) => (first.src, first.dst),
+        // 没有握手时认为端口较小的一方为服务端
+        None if first.transport == Transport::Tcp && first.src.port() < first.dst.port() => (first.dst, first.src),
+        None => (first.src, first.dst),
+    }
+}
+
+fn extract_flow(segments: &[Segment], selector: Option<FlowSelector>) -> Option<PcapFlow> {
+    let same_conversation = |a: &Segment, b: &Segment| {
+        a.transport == b.transport && ((a.src == b.src && a.dst == b.dst) || (a.src == b.dst && a.dst == b.src))
+    };
+
+    // 第一个匹配且有数据的会话
+    let first = segments.iter().find(|s| {
+        !s.payload.is_empty() && selector.is_none_or(|selector| selector.matches(&s.src) || selector.matches(&s.dst))
+    })?;
+    let conversation: Vec<&Segment> = segments.iter().filter(|s| same_conversation(s, first)).collect();
+    let (client, server) = client_and_server(first, &conversation, selector);
+    let connection = format!("{}-{}", client, server);
+
+    let mut upstream = Reassembler::default();
+    let mut downstream = Reassembler::default();
+    let mut records = Vec::new();
+    for segment in conversation {
+        let from_client = segment.src == client;
+        let data = match segment.transport {
+            Transport::Udp => Some(segment.payload.clone()).filter(|data| !data.is_empty()),
+            Transport::Tcp if from_client => upstream.push(segment.seq, segment.flags, segment.payload.clone()),
+            Transport::Tcp => downstream.push(segment.seq, segment.flags, segment.payload.clone()),
+        };
+        if let Some(data) = data {
+            records.push(LogRecord {
+                timestamp: segment.timestamp,
+                direction: if from_client { MessageDirection::Sent } else { MessageDirection::Received },
+                connection: Some(connection.clone()),
+                event: LogEvent::Data(data),
+            });
+        }
+    }
+
+    Some(PcapFlow {
+        transport: first.transport,
+        server,
+        records,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::pcap::PcapExport;
+    use crate::protocols::{ConnectionInfo, Message, MessageType, SessionOptions};
+
+    #[test]
+    fn test_reassembly() {
+        let mut stream = Reassembler::default();
+        assert_eq!(stream.push(99, TCP_SYN, Bytes::new()), None);
+        assert_eq!(stream.push(100, TCP_ACK, Bytes::from_static(b"abc")), Some(Bytes::from_static(b"abc")));
+        // 乱序到达的段在补齐后一起交付
+        assert_eq!(stream.push(106, TCP_ACK, Bytes::from_static(b"ghi")), None);
+        assert_eq!(stream.push(103, TCP_ACK, Bytes::from_static(b"def")), Some(Bytes::from_static(b"defghi")));
+        // 重传的部分被丢弃
+        assert_eq!(stream.push(107, TCP_ACK, Bytes::from_static(b"hijk")), Some(Bytes::from_static(b"jk")));
+        assert_eq!(stream.push(100, TCP_ACK, Bytes::from_static(b"abc")), None);
+    }
+
+    #[test]
+    fn test_malformed_tcp_header() {
+        let packet = |tcp: &[u8]| {
+            let mut packet = vec![0x45, 0, 0, (20 + tcp.len()) as u8, 0, 0, 0, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2];
+            packet.extend_from_slice(tcp);
+            packet
+        };
+        let mut tcp = vec![0u8; 20];
+        tcp[12] = 0x50;
+        assert!(parse_ip(Local::now(), &packet(&tcp)).is_some());
+        // 首部被截断或数据偏移小于 5
+        assert!(parse_ip(Local::now(), &packet(&tcp[..13])).is_none());
+        tcp[12] = 0x10;
+        assert!(parse_ip(Local::now(), &packet(&tcp)).is_none());
+    }
+
+    #[test]
+    fn test_extract_exported_flow() {
+        let export = PcapExport::new(Transport::Tcp, "10.0.0.3:502".parse().unwrap(), false, SessionOptions::default());
+        let info = Some(ConnectionInfo {
+            remote_addr: "10.0.0.9:40000".parse().unwrap(),
+            connection_id: "client".to_string(),
+            local_addr: None,
+        });
+        let history = vec![
+            Message::new_received(MessageType::ClientConnected, info.clone()),
+            Message::new_received(MessageType::Binary(Bytes::from_static(b"request")), info.clone()),
+            Message::new_sent(MessageType::Binary(Bytes::from_static(b"response")), info.clone()),
+        ];
+        let segments: Vec<Segment> = export
+            .packets(&history)
+            .iter()
+            .filter_map(|packet| parse_frame(packet.timestamp, 1, &packet.data))
+            .collect();
+
+        let flow = extract_flow(&segments, Some("502".parse().unwrap())).unwrap();
+        assert_eq!(flow.server, "10.0.0.3:502".parse().unwrap());
+        assert_eq!(flow.records[0].connection.as_deref(), Some("10.0.0.9:40000-10.0.0.3:502"));
+        let data: Vec<(MessageDirection, LogEvent)> = flow.records.into_iter().map(|r| (r.direction, r.event)).collect();
+        assert_eq!(
+            data,
+            vec![
+                (MessageDirection::Sent, LogEvent::Data(Bytes::from_static(b"request"))),
+                (MessageDirection::Received, LogEvent::Data(Bytes::from_static(b"response"))),
+            ]
+        );
+    }
+}
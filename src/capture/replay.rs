@@ -0,0 +1,463 @@
+use anyhow::{Context, Result};
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Local};
+use std::{collections::HashMap, path::Path, str::FromStr, sync::Arc, time::Duration};
+use tokio::{
+    sync::mpsc::{unbounded_channel, Receiver, Sender, UnboundedReceiver, UnboundedSender},
+    time::Instant,
+};
+
+use crate::capture::logger::{LogEvent, LogRecord};
+use crate::protocols::framing::{Frame, FramingConfig};
+use crate::protocols::{ConnectionInfo, Message, MessageDirection, MessageType, SessionEvent};
+use crate::utils::data_format::bytes_to_hex;
+
+/// 差异报告中每段显示的最大字节数
+const DIFF_CONTEXT: usize = 8;
+
+/// 回放步骤
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayStep {
+    /// 距上一步的间隔后发送数据
+    Send { delay: Duration, data: Bytes },
+    /// 距上一步的间隔后期望对端发来数据
+    Expect { delay: Duration, data: Bytes },
+}
+
+/// 从会话记录中提取的单个连接的收发序列
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub steps: Vec<ReplayStep>,
+}
+
+impl Recording {
+    /// 从记录中提取指定连接 (默认为第一个有数据的连接) 的收发序列
+    ///
+    /// 记录中发送的数据在回放时发送，收到的数据作为期望的输入；
+    /// `invert` 为 true 时对调两者，例如用客户端的记录模拟服务器。两个方向记录的都是线路上的原始数据。
+    pub fn from_records(records: &[LogRecord], connection: Option<&str>, invert: bool) -> Self {
+        let connection = connection.map(str::to_string).or_else(|| {
+            records
+                .iter()
+                .find(|r| matches!(r.event, LogEvent::Data(_)) && r.connection.is_some())
+                .and_then(|r| r.connection.clone())
+        });
+
+        let mut steps = Vec::new();
+        let mut last_time: Option<DateTime<Local>> = None;
+        for record in records {
+            let LogEvent::Data(data) = &record.event else {
+                continue;
+            };
+            // 广播发送的数据属于所有连接
+            if record.connection.is_some() && record.connection != connection {
+                continue;
+            }
+
+            let delay = last_time
+                .map(|last| (record.timestamp - last).to_std().unwrap_or_default())
+                .unwrap_or_default();
+            last_time = Some(record.timestamp);
+
+            let send = (record.direction == MessageDirection::Sent) != invert;
+            steps.push(if send {
+                ReplayStep::Send { delay, data: data.clone() }
+            } else {
+                ReplayStep::Expect { delay, data: data.clone() }
+            });
+        }
+        Self { steps }
+    }
+
+    /// 加载 JSON Lines 格式的会话记录 (`--log-format jsonl`)
+    pub fn load(path: &Path, connection: Option<&str>, invert: bool) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read recording {}", path.display()))?;
+        let mut records = Vec::new();
+        for (index, line) in source.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let value: serde_json::Value = serde_json::from_str(line)
+                .with_context(|| format!("{}:{}: invalid JSON", path.display(), index + 1))?;
+            let record = LogRecord::from_json(&value)
+                .ok_or_else(|| anyhow::anyhow!("{}:{}: invalid record", path.display(), index + 1))?;
+            records.push(record);
+        }
+
+        Self::from_records(&records, connection, invert).ensure_sends(path)
+    }
+
+    /// 检查记录中有需要发送的数据
+    pub fn ensure_sends(self, path: &Path) -> Result<Self> {
+        if !self.steps.iter().any(|step| matches!(step, ReplayStep::Send { .. })) {
+            anyhow::bail!("Recording {} has nothing to send", path.display());
+        }
+        Ok(self)
+    }
+}
+
+/// 回放抓包中的哪一端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplaySide {
+    /// 连接目标，发送客户端的数据
+    #[default]
+    Client,
+    /// 监听端口，向连接上来的客户端发送服务端的数据
+    Server,
+}
+
+impl FromStr for ReplaySide {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "client" => Ok(ReplaySide::Client),
+            "server" => Ok(ReplaySide::Server),
+            other => Err(format!("Unknown replay side: {}", other)),
+        }
+    }
+}
+
+/// 回放选项
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// 速度倍数，2.0 表示以两倍速回放
+    pub speed: f64,
+    /// 是否等待对端发来期望的数据后再继续
+    pub wait_for_input: bool,
+    /// 等待期望数据的超时
+    pub expect_timeout: Duration,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            wait_for_input: false,
+            expect_timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ReplayOptions {
+    /// 按速度倍数缩放记录中的间隔
+    fn scale(&self, delay: Duration) -> Duration {
+        delay.div_f64(self.speed.max(0.001))
+    }
+}
+
+/// 比较期望数据和实际收到的数据，相同时返回 None，否则返回第一处差异
+pub fn diff_bytes(expected: &[u8], actual: &[u8]) -> Option<(usize, String)> {
+    if expected == actual {
+        return None;
+    }
+    match expected.iter().zip(actual).position(|(e, a)| e != a) {
+        Some(offset) => Some((offset, format!("differs at byte {}", offset))),
+        None => {
+            let offset = expected.len().min(actual.len());
+            Some((offset, format!("expected {} bytes, got {}", expected.len(), actual.len())))
+        }
+    }
+}
+
+/// 期望数据和实际数据并列显示的对比报告，从第一处差异所在处开始显示
+pub fn compare_report(expected: &[u8], actual: &[u8]) -> (bool, String) {
+    let diff = diff_bytes(expected, actual);
+    let start = diff.as_ref().map(|(offset, _)| offset - offset % DIFF_CONTEXT).unwrap_or(0);
+    let window = |data: &[u8]| {
+        let begin = start.min(data.len());
+        let end = (start + DIFF_CONTEXT).min(data.len());
+        let mut hex = bytes_to_hex(&data[begin..end]);
+        if begin > 0 {
+            hex.insert_str(0, ".. ");
+        }
+        if data.len() > end {
+            hex.push_str(" ..");
+        }
+        hex
+    };
+
+    let columns = format!("expected [{}] | actual [{}]", window(expected), window(actual));
+    match diff {
+        None => (true, format!("{} match", columns)),
+        Some((_, diff)) => (false, format!("{} {}", columns, diff)),
+    }
+}
+
+/// 会话回放器: 对每个新连接 (客户端模式下为连接到服务器后) 按记录发送数据并比对对端的响应
+pub struct Replayer {
+    recording: Arc<Recording>,
+    options: ReplayOptions,
+    /// 会话的分帧配置，收到的帧重新封装为线路上的数据后再与记录比对
+    framing: FramingConfig,
+}
+
+impl Replayer {
+    pub fn new(recording: Recording, options: ReplayOptions, framing: FramingConfig) -> Self {
+        Self {
+            recording: Arc::new(recording),
+            options,
+            framing,
+        }
+    }
+}
+
+fn replay_event(connection: &ConnectionInfo, text: String) -> Message {
+    Message::new_received(MessageType::Event(SessionEvent::Replay(text)), Some(connection.clone()))
+}
+
+/// 在后台运行回放器，转发协议处理器发往UI的消息并将收到的数据交给对应连接的回放任务
+pub fn spawn_replayer(
+    replayer: Replayer,
+    mut from_handler: Receiver<Message>,
+    to_ui: Sender<Message>,
+    to_handler: Sender<Message>,
+) {
+    tokio::spawn(async move {
+        let mut inputs: HashMap<String, UnboundedSender<Bytes>> = HashMap::new();
+        while let Some(message) = from_handler.recv().await {
+            if let Some(info) = message.connection_info.clone() {
+                match &message.content {
+                    MessageType::ClientConnected => {
+                        let (input_tx, input_rx) = unbounded_channel();
+                        inputs.insert(info.connection_id.clone(), input_tx);
+                        tokio::spawn(run_replay(
+                            Arc::clone(&replayer.recording),
+                            replayer.options.clone(),
+                            info,
+                            input_rx,
+                            to_ui.clone(),
+                            to_handler.clone(),
+                        ));
+                    }
+                    MessageType::ClientDisconnected => {
+                        inputs.remove(&info.connection_id);
+                    }
+                    content if message.direction == MessageDirection::Received => {
+                        // 记录中是线路上的数据，解码后的帧需要重新封装才能比对
+                        let data = match content {
+                            MessageType::Raw(data) => Some(data.clone()),
+                            content => content.payload().map(|payload| Frame::Payload(payload).wire(&replayer.framing)),
+                        };
+                        if let (Some(input), Some(data)) = (inputs.get(&info.connection_id), data) {
+                            let _ = input.send(data);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if to_ui.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// 单个连接的回放任务
+async fn run_replay(
+    recording: Arc<Recording>,
+    options: ReplayOptions,
+    connection: ConnectionInfo,
+    mut input: UnboundedReceiver<Bytes>,
+    to_ui: Sender<Message>,
+    to_handler: Sender<Message>,
+) {
+    let mut received = BytesMut::new();
+    let mut expected_all = BytesMut::new();
+    let mut matched = 0;
+    let mut expects = 0;
+
+    let _ = to_ui
+        .send(replay_event(&connection, format!("started ({} steps)", recording.steps.len())))
+        .await;
+
+    for (index, step) in recording.steps.iter().enumerate() {
+        match step {
+            ReplayStep::Send { delay, data } => {
+                tokio::time::sleep(options.scale(*delay)).await;
+                // 记录中是线路上的帧，原样发送，不再追加校验值和分帧
+                let message = Message::new_sent(MessageType::Raw(data.clone()), Some(connection.clone()));
+                if to_handler.send(message.clone()).await.is_err() {
+                    break;
+                }
+                let _ = to_ui.send(message).await;
+            }
+            ReplayStep::Expect { delay, data } if options.wait_for_input => {
+                expects += 1;
+                // 等待期望长度的数据到达或超时，多出的数据留给下一步
+                let deadline = Instant::now() + options.scale(*delay) + options.expect_timeout;
+                while received.len() < data.len() {
+                    match tokio::time::timeout_at(deadline, input.recv()).await {
+                        Ok(Some(chunk)) => received.extend_from_slice(&chunk),
+                        Ok(None) | Err(_) => break,
+                    }
+                }
+                let actual = received.split_to(data.len().min(received.len())).freeze();
+                let (same, report) = compare_report(data, &actual);
+                matched += usize::from(same);
+                let _ = to_ui
+                    .send(replay_event(&connection, format!("step {}: {}", index + 1, report)))
+                    .await;
+            }
+            ReplayStep::Expect { delay, data } => {
+                tokio::time::sleep(options.scale(*delay)).await;
+                expected_all.extend_from_slice(data);
+            }
+        }
+    }
+
+    let summary = if options.wait_for_input {
+        format!("finished: {}/{} expected responses matched", matched, expects)
+    } else {
+        // 不等待输入时，在最后一步后收集对端数据，与记录中收到的全部数据整体比对
+        let deadline = Instant::now() + options.expect_timeout;
+        while received.len() < expected_all.len() {
+            match tokio::time::timeout_at(deadline, input.recv()).await {
+                Ok(Some(chunk)) => received.extend_from_slice(&chunk),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        match compare_report(&expected_all, &received) {
+            (true, _) => format!("finished: peer sent the recorded {} bytes", expected_all.len()),
+            (false, report) => format!("finished: {}", report),
+        }
+    };
+    let _ = to_ui.send(replay_event(&connection, summary)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn record(ms: i64, direction: MessageDirection, connection: &str, data: &'static [u8]) -> LogRecord {
+        LogRecord {
+            timestamp: Local.timestamp_millis_opt(1_700_000_000_000 + ms).unwrap(),
+            direction,
+            connection: Some(connection.to_string()),
+            event: LogEvent::Data(Bytes::from_static(data)),
+        }
+    }
+
+    #[test]
+    fn test_steps_from_records() {
+        let records = vec![
+            record(0, MessageDirection::Sent, "a", b"req1"),
+            record(50, MessageDirection::Received, "b", b"other"),
+            record(100, MessageDirection::Received, "a", b"resp1"),
+            record(300, MessageDirection::Sent, "a", b"req2"),
+        ];
+
+        let recording = Recording::from_records(&records, None, false);
+        assert_eq!(
+            recording.steps,
+            vec![
+                ReplayStep::Send { delay: Duration::ZERO, data: Bytes::from_static(b"req1") },
+                ReplayStep::Expect { delay: Duration::from_millis(100), data: Bytes::from_static(b"resp1") },
+                ReplayStep::Send { delay: Duration::from_millis(200), data: Bytes::from_static(b"req2") },
+            ]
+        );
+
+        let inverted = Recording::from_records(&records, Some("b"), true);
+        assert_eq!(
+            inverted.steps,
+            vec![ReplayStep::Send { delay: Duration::ZERO, data: Bytes::from_static(b"other") }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_framed_records_unchanged() {
+        // 记录的是按 len:2 分帧后的帧，回放时不能再加一层长度前缀
+        let options = crate::protocols::SessionOptions {
+            framing: crate::protocols::framing::FramingConfig {
+                mode: "len:2".parse().unwrap(),
+                idle_gap: None,
+            },
+            ..Default::default()
+        };
+        let frame = options.outgoing_frame(&MessageType::Text("hi".to_string())).unwrap().unwrap();
+        assert_eq!(&frame[..], &[0x00, 0x02, b'h', b'i']);
+        let records = vec![LogRecord {
+            event: LogEvent::Data(frame.clone()),
+            ..record(0, MessageDirection::Sent, "a", b"")
+        }];
+
+        let (handler_tx, handler_rx) = tokio::sync::mpsc::channel(8);
+        let (ui_tx, _ui_rx) = tokio::sync::mpsc::channel(8);
+        let (to_handler, mut sent) = tokio::sync::mpsc::channel(8);
+        let replayer = Replayer::new(
+            Recording::from_records(&records, None, false),
+            ReplayOptions::default(),
+            options.framing.clone(),
+        );
+        spawn_replayer(replayer, handler_rx, ui_tx, to_handler);
+
+        let info = ConnectionInfo {
+            remote_addr: "127.0.0.1:9000".parse().unwrap(),
+            connection_id: "a".to_string(),
+            local_addr: None,
+        };
+        handler_tx.send(Message::new_received(MessageType::ClientConnected, Some(info))).await.unwrap();
+        let message = sent.recv().await.unwrap();
+        assert_eq!(options.outgoing_frame(&message.content), Ok(Some(frame)));
+    }
+
+    #[tokio::test]
+    async fn test_inverted_replay_framed() {
+        // 客户端的 len:2 记录反向回放为服务器: 发送记录中收到的帧，期望记录中发送的帧
+        let framing = crate::protocols::framing::FramingConfig {
+            mode: "len:2".parse().unwrap(),
+            idle_gap: None,
+        };
+        let records = vec![
+            record(0, MessageDirection::Sent, "a", &[0x00, 0x02, b'h', b'i']),
+            record(10, MessageDirection::Received, "a", &[0x00, 0x02, b'o', b'k']),
+        ];
+        let recording = Recording::from_records(&records, None, true);
+
+        let (handler_tx, handler_rx) = tokio::sync::mpsc::channel(8);
+        let (ui_tx, mut ui_rx) = tokio::sync::mpsc::channel(8);
+        let (to_handler, mut sent) = tokio::sync::mpsc::channel(8);
+        spawn_replayer(Replayer::new(recording, ReplayOptions::default(), framing), handler_rx, ui_tx, to_handler);
+
+        let info = ConnectionInfo {
+            remote_addr: "127.0.0.1:9000".parse().unwrap(),
+            connection_id: "a".to_string(),
+            local_addr: None,
+        };
+        handler_tx
+            .send(Message::new_received(MessageType::ClientConnected, Some(info.clone())))
+            .await
+            .unwrap();
+        let message = sent.recv().await.unwrap();
+        assert!(matches!(message.content, MessageType::Raw(data) if data[..] == [0x00, 0x02, b'o', b'k']));
+
+        // 对端的帧经解码器去掉长度字段后到达
+        let payload = MessageType::from_payload(Bytes::from_static(b"hi"));
+        handler_tx.send(Message::new_received(payload, Some(info))).await.unwrap();
+        let summary = loop {
+            match ui_rx.recv().await.unwrap().content {
+                MessageType::Event(SessionEvent::Replay(text)) if text.starts_with("finished") => break text,
+                _ => {}
+            }
+        };
+        assert_eq!(summary, "finished: peer sent the recorded 4 bytes");
+    }
+
+    #[test]
+    fn test_diff_bytes() {
+        assert_eq!(diff_bytes(b"abc", b"abc"), None);
+        assert_eq!(
+            compare_report(&[0x01, 0x02, 0x03], &[0x01, 0x09]),
+            (false, "expected [01 02 03] | actual [01 09] differs at byte 1".to_string())
+        );
+        assert_eq!(diff_bytes(b"ab", b"abc"), Some((2, "expected 2 bytes, got 3".to_string())));
+
+        let expected: Vec<u8> = (0..20).collect();
+        let mut actual = expected.clone();
+        actual[17] = 0xFF;
+        assert_eq!(
+            compare_report(&expected, &actual).1,
+            "expected [.. 10 11 12 13] | actual [.. 10 FF 12 13] differs at byte 17"
+        );
+    }
+}
@@ -1,5 +1,29 @@
+use anyhow::Result;
 use clap::{Parser, Subcommand, Args as ClapArgs};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::capture::logger::{LogFormat, SessionCapture};
+use crate::capture::pcap::Transport;
+use crate::capture::pcap_import::{FlowSelector, PcapFlow};
+use crate::capture::replay::{Recording, ReplayOptions, ReplaySide};
+use crate::config::rules::parse_rules_arg;
+use crate::headless::{HeadlessOptions, OutputFormat};
+use crate::protocols::autoreply::AutoReplyRules;
+use crate::protocols::fault::{FaultConfig, Faults};
+use crate::protocols::framing::{FramingConfig, FramingMode};
+use crate::protocols::multicast::{Multicast, UdpGroup};
+use crate::protocols::reconnect::{Backoff, OfflineSend, ReconnectPolicy};
+use crate::protocols::resolve::{self, HostPort, IpFamily, Resolved};
+use crate::protocols::sockopt::{Keepalive, SocketOptions};
+use crate::protocols::timeout::Timeouts;
+use crate::protocols::tunnel::{ProxyConfig, Tunnel};
+use crate::protocols::SessionOptions;
+use crate::transfer::ChunkOptions;
+use crate::utils::checksum::{ChecksumAlgorithm, ChecksumConfig, ChecksumMode, ChecksumRange};
+use crate::utils::data_format::Endian;
 
 /// 终端网络调试工具
 #[derive(Parser, Debug, Clone)]
@@ -9,10 +33,319 @@ pub struct Cli {
     #[arg(short, long)]
     pub vertical_layout: bool,
 
+    /// 二进制结构模板文件，可多次指定 (另外会加载配置目录 nt/templates 下的 .tpl 文件)
+    #[arg(long = "template", global = true, value_name = "FILE")]
+    pub templates: Vec<PathBuf>,
+
+    /// 启动后发送指定文件的内容 (也可在输入框中输入 @path 发送)
+    #[arg(long = "send-file", global = true, value_name = "FILE")]
+    pub send_file: Option<PathBuf>,
+
+    /// 文件分块发送时每块的字节数 (整个文件分帧后再分块，分帧和校验值不按块重复)
+    #[arg(long = "chunk-size", global = true, value_name = "BYTES")]
+    pub chunk_size: Option<usize>,
+
+    /// 文件分块发送时块之间的间隔 (毫秒)
+    #[arg(long = "chunk-delay", global = true, value_name = "MS", default_value_t = 0)]
+    pub chunk_delay: u64,
+
+    /// 将收发的数据和连接事件记录到文件 (运行时按 L 开启/停止)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub log: Option<PathBuf>,
+
+    /// 记录格式: text、jsonl、raw (raw 时路径为目录，按连接和方向保存原始字节)
+    #[arg(long = "log-format", global = true, value_name = "FORMAT", default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// 回放 JSON Lines 会话记录 (--log-format jsonl 记录): 按原有时间间隔发送记录中发送的数据，并比对对端的响应
+    #[arg(long, global = true, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// 回放速度倍数
+    #[arg(long = "replay-speed", global = true, value_name = "FACTOR", default_value_t = 1.0)]
+    pub replay_speed: f64,
+
+    /// 回放时等待对端发来记录中的数据后再继续
+    #[arg(long = "replay-wait", global = true)]
+    pub replay_wait: bool,
+
+    /// 等待期望数据的超时 (毫秒)
+    #[arg(long = "replay-timeout", global = true, value_name = "MS", default_value_t = 2000)]
+    pub replay_timeout: u64,
+
+    /// 回放记录中的指定连接，默认为第一个有数据的连接
+    #[arg(long = "replay-conn", global = true, value_name = "ID")]
+    pub replay_conn: Option<String>,
+
+    /// 对调记录中的收发方向 (如用客户端的记录模拟服务器)
+    #[arg(long = "replay-invert", global = true)]
+    pub replay_invert: bool,
+
+    /// 退出时将会话导出为 pcapng 文件 (运行时按 W 立即导出)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub pcap: Option<PathBuf>,
+
+    /// 退出时将会话中的 HTTP/1.x 请求和响应导出为 HAR 文件 (运行时按 H 立即导出)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub har: Option<PathBuf>,
+
+    /// 无界面模式: 收到的数据写到标准输出，标准输入逐行发送 (标准输出不是终端时自动启用)
+    #[arg(long, global = true)]
+    pub headless: bool,
+
+    /// 无界面模式的输出格式: raw、hex、json
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "raw")]
+    pub output: OutputFormat,
+
+    /// 无界面模式下标准输入结束后再等待的毫秒数，之后退出
+    #[arg(long = "eof-wait", global = true, value_name = "MS")]
+    pub eof_wait: Option<u64>,
+
+    /// Rhai 自动化脚本，可定义 on_connect/on_data/on_disconnect/on_tick 钩子
+    #[arg(long, global = true, value_name = "FILE")]
+    pub script: Option<PathBuf>,
+
+    /// 只使用 IPv4 地址 (主机名只解析 A 记录)
+    #[arg(short = '4', long = "ipv4", global = true, conflicts_with = "ipv6")]
+    pub ipv4: bool,
+
+    /// 只使用 IPv6 地址 (主机名只解析 AAAA 记录)
+    #[arg(short = '6', long = "ipv6", global = true)]
+    pub ipv6: bool,
+
+    /// 会话选项
+    #[command(flatten)]
+    pub session: SessionArgs,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+/// 会话选项 (适用于所有协议命令)
+#[derive(ClapArgs, Debug, Clone)]
+pub struct SessionArgs {
+    /// 流分帧方式: raw、line、delim:<hex>、len:<1|2|4|8>[be|le][,incl][,offset=N]、fixed:<n>
+    #[arg(long = "frame", global = true, default_value = "raw")]
+    pub frame: FramingMode,
+
+    /// 空闲间隔 (毫秒)，超过该时间无新数据时将缓冲数据作为一帧上报
+    #[arg(long = "frame-idle", global = true, value_name = "MS")]
+    pub frame_idle: Option<u64>,
+
+    /// 校验算法: sum8、xor、crc8、crc16-modbus、crc16-ccitt、crc32
+    #[arg(long, global = true, value_name = "ALG")]
+    pub checksum: Option<ChecksumAlgorithm>,
+
+    /// 校验字段字节序 (be/le)，默认使用算法惯用的字节序
+    #[arg(long = "checksum-endian", global = true, value_name = "ENDIAN")]
+    pub checksum_endian: Option<Endian>,
+
+    /// 参与校验的字节范围 START[:-N]，如 1:-1 表示跳过首字节和末尾 1 字节
+    #[arg(long = "checksum-range", global = true, value_name = "RANGE")]
+    pub checksum_range: Option<ChecksumRange>,
+
+    /// 校验方式: append (仅追加到发送数据)、verify (仅校验收到的帧)、both
+    #[arg(long = "checksum-mode", global = true, value_name = "MODE", default_value = "both", requires = "checksum")]
+    pub checksum_mode: ChecksumMode,
+
+    /// 自动回复规则文件 (JSON)，收到匹配的帧时自动发送回复
+    #[arg(long, global = true, value_name = "FILE", value_parser = parse_rules_arg)]
+    pub rules: Option<AutoReplyRules>,
+
+    /// TCP 客户端断开后自动重连，退避方式: fixed (固定间隔)、exp (指数增长)
+    #[arg(long, global = true, value_name = "BACKOFF")]
+    pub reconnect: Option<Backoff>,
+
+    /// 首次重连前的等待时间 (毫秒)
+    #[arg(long = "reconnect-delay", global = true, value_name = "MS", default_value_t = 1000)]
+    pub reconnect_delay: u64,
+
+    /// 指数退避的最大等待时间 (毫秒)
+    #[arg(long = "reconnect-max-delay", global = true, value_name = "MS", default_value_t = 30000)]
+    pub reconnect_max_delay: u64,
+
+    /// 等待时间的随机抖动比例 (0~1)
+    #[arg(long = "reconnect-jitter", global = true, value_name = "FRACTION", default_value_t = 0.1)]
+    pub reconnect_jitter: f64,
+
+    /// 连续重连失败的最大次数，默认不限
+    #[arg(long = "reconnect-attempts", global = true, value_name = "N")]
+    pub reconnect_attempts: Option<u32>,
+
+    /// 断开期间发送的消息: reject (丢弃)、queue (排队，重连后发送)
+    #[arg(long, global = true, value_name = "MODE", default_value = "reject")]
+    pub offline: OfflineSend,
+
+    /// 建立连接的超时 (毫秒)
+    #[arg(long = "connect-timeout", global = true, value_name = "MS")]
+    pub connect_timeout: Option<u64>,
+
+    /// 读空闲超时 (毫秒)，超过该时间未收到数据时提示；UDP 客户端为发送后等待响应的超时
+    #[arg(long = "idle-timeout", global = true, value_name = "MS")]
+    pub idle_timeout: Option<u64>,
+
+    /// 写入数据的超时 (毫秒)
+    #[arg(long = "write-timeout", global = true, value_name = "MS")]
+    pub write_timeout: Option<u64>,
+
+    /// 读空闲、写入或响应超时后关闭连接
+    #[arg(long = "timeout-close", global = true)]
+    pub timeout_close: bool,
+
+    /// 设置 TCP_NODELAY (关闭 Nagle 算法)
+    #[arg(long, global = true)]
+    pub nodelay: bool,
+
+    /// 开启 TCP 保活: IDLE[,INTERVAL[,COUNT]]，时间单位为秒
+    #[arg(long, global = true, value_name = "IDLE[,INTERVAL[,COUNT]]")]
+    pub keepalive: Option<Keepalive>,
+
+    /// 接收缓冲区大小 SO_RCVBUF (字节)
+    #[arg(long, global = true, value_name = "BYTES")]
+    pub rcvbuf: Option<usize>,
+
+    /// 发送缓冲区大小 SO_SNDBUF (字节)
+    #[arg(long, global = true, value_name = "BYTES")]
+    pub sndbuf: Option<usize>,
+
+    /// SO_LINGER 秒数，0 表示关闭连接时直接发送 RST
+    #[arg(long, global = true, value_name = "SECS")]
+    pub linger: Option<u64>,
+
+    /// 设置 SO_REUSEADDR
+    #[arg(long, global = true)]
+    pub reuseaddr: bool,
+
+    /// 设置 SO_REUSEPORT
+    #[arg(long, global = true)]
+    pub reuseport: bool,
+
+    /// 发送数据包的 IP_TTL (IPv6 为跳数限制)
+    #[arg(long, global = true, value_name = "N")]
+    pub ttl: Option<u32>,
+
+    /// IPv6 套接字的 IPV6_V6ONLY: true 仅 IPv6，false 同时接受 IPv4
+    #[arg(long = "v6only", global = true, value_name = "BOOL")]
+    pub only_v6: Option<bool>,
+
+    /// 故障注入: 每次发送前增加的延迟 (毫秒，运行时按 M 打开面板调整)
+    #[arg(long = "fault-latency", global = true, value_name = "MS", default_value_t = 0)]
+    pub fault_latency: u64,
+
+    /// 故障注入: 延迟的随机抖动 (毫秒)
+    #[arg(long = "fault-jitter", global = true, value_name = "MS", default_value_t = 0)]
+    pub fault_jitter: u64,
+
+    /// 故障注入: 发送带宽上限 (字节/秒)
+    #[arg(long = "fault-bandwidth", global = true, value_name = "BYTES_PER_SEC")]
+    pub fault_bandwidth: Option<u64>,
+
+    /// 故障注入: 随机丢弃发送数据报的概率 (0~1，仅 UDP)
+    #[arg(long = "fault-drop", global = true, value_name = "FRACTION", default_value_t = 0.0)]
+    pub fault_drop: f64,
+
+    /// 故障注入: 每个发送字节被随机篡改的概率 (0~1)
+    #[arg(long = "fault-corrupt", global = true, value_name = "FRACTION", default_value_t = 0.0)]
+    pub fault_corrupt: f64,
+
+    /// 故障注入: 将每次写入拆分为不超过该字节数的小段 (仅 TCP，写入期间临时开启 TCP_NODELAY)
+    #[arg(long = "fault-segment", global = true, value_name = "BYTES")]
+    pub fault_segment: Option<usize>,
+
+    /// 故障注入: 连接建立后经过该时间由本端关闭 (毫秒)
+    #[arg(long = "fault-close-after", global = true, value_name = "MS")]
+    pub fault_close_after: Option<u64>,
+
+    /// 故障注入: 本端关闭连接时发送 RST 而不是 FIN
+    #[arg(long = "fault-rst", global = true)]
+    pub fault_rst: bool,
+
+    /// TCP 客户端经由代理连接: http://、socks5:// 或 socks5h:// (由代理解析主机名)，可带 USER:PASS@；
+    /// 未指定时使用 ALL_PROXY 环境变量 (WebSocket、HTTP 客户端尚不支持代理)
+    #[arg(long, global = true, value_name = "URL", value_parser = ProxyConfig::parse)]
+    pub proxy: Option<ProxyConfig>,
+
+    /// 忽略 ALL_PROXY 环境变量，直接连接
+    #[arg(long = "no-proxy", global = true, conflicts_with = "proxy")]
+    pub no_proxy: bool,
+}
+
+impl SessionArgs {
+    /// 构建传递给协议处理器的会话选项
+    pub fn to_options(&self) -> SessionOptions {
+        SessionOptions {
+            framing: FramingConfig {
+                mode: self.frame.clone(),
+                idle_gap: self.frame_idle.map(Duration::from_millis),
+            },
+            checksum: self.checksum.map(|algorithm| {
+                let mut config = ChecksumConfig::new(algorithm);
+                config.endian = self.checksum_endian.unwrap_or(config.endian);
+                config.range = self.checksum_range.unwrap_or_default();
+                config.append = self.checksum_mode.appends();
+                config.verify = self.checksum_mode.verifies();
+                config
+            }),
+            auto_reply: self.rules.clone().unwrap_or_default(),
+            // 会话记录在启动会话时按 --log 开启
+            capture: SessionCapture::default(),
+            reconnect: self.reconnect.map(|backoff| ReconnectPolicy {
+                backoff,
+                initial_delay: Duration::from_millis(self.reconnect_delay),
+                max_delay: Duration::from_millis(self.reconnect_max_delay),
+                jitter: self.reconnect_jitter,
+                max_attempts: self.reconnect_attempts,
+            }),
+            offline: self.offline,
+            timeouts: Timeouts {
+                connect: self.connect_timeout.map(Duration::from_millis),
+                read_idle: self.idle_timeout.map(Duration::from_millis),
+                write: self.write_timeout.map(Duration::from_millis),
+                close_on_timeout: self.timeout_close,
+            },
+            socket: SocketOptions {
+                nodelay: self.nodelay.then_some(true),
+                keepalive: self.keepalive,
+                recv_buffer: self.rcvbuf,
+                send_buffer: self.sndbuf,
+                linger: self.linger.map(Duration::from_secs),
+                ttl: self.ttl,
+                reuse_addr: self.reuseaddr.then_some(true),
+                reuse_port: self.reuseport.then_some(true),
+                only_v6: self.only_v6,
+            },
+            // 组播/广播由 udp multicast/broadcast 命令设置
+            udp_group: None,
+            faults: Faults::new(FaultConfig {
+                latency: Duration::from_millis(self.fault_latency),
+                jitter: Duration::from_millis(self.fault_jitter),
+                bandwidth: self.fault_bandwidth,
+                drop: self.fault_drop.clamp(0.0, 1.0),
+                corrupt: self.fault_corrupt.clamp(0.0, 1.0),
+                segment: self.fault_segment,
+                close_after: self.fault_close_after.map(Duration::from_millis),
+                abrupt_close: self.fault_rst,
+            }),
+            // 代理隧道在解析远程地址时设置
+            tunnel: None,
+        }
+    }
+
+    /// 客户端使用的代理: --proxy，其次为 ALL_PROXY (或 all_proxy) 环境变量
+    pub fn proxy(&self) -> Result<Option<ProxyConfig>> {
+        if self.no_proxy {
+            return Ok(None);
+        }
+        if let Some(proxy) = &self.proxy {
+            return Ok(Some(proxy.clone()));
+        }
+        match std::env::var("ALL_PROXY").or_else(|_| std::env::var("all_proxy")) {
+            Ok(url) if !url.is_empty() => ProxyConfig::parse(&url).map(Some).map_err(|e| anyhow::anyhow!("ALL_PROXY: {}", e)),
+            _ => Ok(None),
+        }
+    }
+}
+
 /// 支持的协议命令
 #[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
@@ -40,6 +373,14 @@ pub enum Commands {
     #[command(alias = "udpc")]
     UdpClient(ClientArgs),
     
+    /// Unix 域套接字 (默认 SOCK_STREAM，--dgram 为 SOCK_DGRAM)
+    #[command(subcommand)]
+    Unix(UnixCommands),
+
+    /// 端口转发代理: 显示每个连接对两个方向的数据，可暂停、编辑或注入
+    #[command(subcommand)]
+    Proxy(ProxyCommands),
+
     /// WebSocket 协议
     #[command(alias = "ws", subcommand)]
     WebSocket(WebSocketCommands),
@@ -55,6 +396,9 @@ pub enum Commands {
     /// HTTP/3 协议
     #[command(subcommand)]
     Http3(HttpCommands),
+
+    /// 从 pcap/pcapng 抓包中提取一个 TCP/UDP 流，对在线目标回放其中一端的数据
+    Replay(PcapReplayArgs),
 }
 
 /// TCP 命令
@@ -79,6 +423,33 @@ pub enum UdpCommands {
     /// UDP 客户端模式
     #[command(alias = "c")]
     Client(ClientArgs),
+
+    /// 加入组播组，按来源显示收到的数据，未选中连接时发送到组
+    #[command(alias = "m")]
+    Multicast(MulticastArgs),
+
+    /// 开启 SO_BROADCAST 发送广播，按来源显示收到的响应
+    #[command(alias = "b")]
+    Broadcast(BroadcastArgs),
+}
+
+/// Unix 域套接字命令
+#[derive(Subcommand, Debug, Clone)]
+pub enum UnixCommands {
+    /// 监听 Unix 套接字
+    #[command(alias = "s")]
+    Server(UnixArgs),
+
+    /// 连接 Unix 套接字
+    #[command(alias = "c")]
+    Client(UnixArgs),
+}
+
+/// 代理命令
+#[derive(Subcommand, Debug, Clone)]
+pub enum ProxyCommands {
+    /// TCP 端口转发 (如 nt proxy tcp 8000 backend:80)
+    Tcp(ProxyArgs),
 }
 
 /// WebSocket 命令
@@ -108,20 +479,135 @@ pub enum HttpCommands {
 /// 服务器参数
 #[derive(ClapArgs, Debug, Clone)]
 pub struct ServerArgs {
-    /// 服务器地址 (如 127.0.0.1:8000)
-    /// 如果只提供端口号则绑定到 127.0.0.1
-    pub address: String,
+    /// 监听地址 (如 127.0.0.1:8000、localhost:8000、[::]:8000 或 [fe80::1%eth0]:8000)，可指定多个同时监听
+    /// 如果只提供端口号则绑定到 127.0.0.1；[::] 默认同时接受 IPv4 连接 (--v6only true 时仅 IPv6)
+    #[arg(value_name = "ADDRESS", num_args = 1.., required = true)]
+    pub addresses: Vec<String>,
+}
+
+impl ServerArgs {
+    /// 所有监听地址
+    pub fn listen(&self, family: IpFamily) -> Result<Vec<HostPort>> {
+        self.addresses.iter().map(|addr| parse_address(addr, family)).collect()
+    }
 }
 
 /// 客户端参数
 #[derive(ClapArgs, Debug, Clone)]
 pub struct ClientArgs {
-    /// 本地地址 (如 127.0.0.1:9000)
-    /// 如果只提供端口号则绑定到 127.0.0.1
+    /// [本地地址] 远程服务器地址 (如 192.168.1.1:8000、example.com:80 或 0.0.0.0:9000 192.168.1.1:8000)
+    /// 省略本地地址时由系统选择；本地地址只提供端口号时绑定所有地址，端口 0 表示临时端口
+    #[arg(value_name = "[LOCAL] REMOTE", num_args = 1..=2, required = true)]
+    pub addresses: Vec<String>,
+}
+
+impl ClientArgs {
+    /// 连接前绑定的本地地址，未指定时为 0.0.0.0:0
+    pub fn local(&self) -> Result<HostPort> {
+        let [local, _] = self.addresses.as_slice() else {
+            return Ok(HostPort::from(SocketAddr::from(([0, 0, 0, 0], 0))));
+        };
+        HostPort::parse(local, "0.0.0.0").map_err(|_| invalid_local(local))
+    }
+
+    /// 远程服务器地址，可以是主机名
+    pub fn remote(&self, family: IpFamily) -> Result<HostPort> {
+        parse_address(self.addresses.last().expect("clap requires the remote address"), family)
+    }
+}
+
+/// Unix 域套接字参数
+#[derive(ClapArgs, Debug, Clone)]
+pub struct UnixArgs {
+    /// 套接字路径 (如 /tmp/x.sock)，以 @ 开头表示 Linux 抽象命名空间 (如 @x)
+    pub path: String,
+
+    /// 使用数据报套接字 (SOCK_DGRAM)
+    #[arg(long)]
+    pub dgram: bool,
+}
+
+impl UnixArgs {
+    fn protocol(&self) -> ProtocolType {
+        if self.dgram {
+            ProtocolType::UnixDatagram
+        } else {
+            ProtocolType::Unix
+        }
+    }
+}
+
+/// 代理参数
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ProxyArgs {
+    /// 监听地址 (如 8000 或 0.0.0.0:8000)，如果只提供端口号则绑定到 127.0.0.1
+    pub listen: String,
+
+    /// 上游地址，可以是主机名 (如 backend:80)
+    pub upstream: String,
+}
+
+/// UDP 组播参数
+#[derive(ClapArgs, Debug, Clone)]
+pub struct MulticastArgs {
+    /// 组播组地址和端口 (如 239.1.2.3:5000 或 [ff02::1234%eth0]:5000)
+    pub group: String,
+
+    /// 加入组播组和发送组播的网络接口: 接口名、序号或 IPv4 地址
+    #[arg(long, value_name = "IFACE")]
+    pub iface: Option<String>,
+
+    /// 源特定组播 (SSM): 只接收该源地址发往组的数据
+    #[arg(long, value_name = "IP")]
+    pub source: Option<IpAddr>,
+
+    /// 发送组播的 TTL (IPv6 为跳数限制)
+    #[arg(long = "mcast-ttl", value_name = "N")]
+    pub ttl: Option<u32>,
+
+    /// 是否收到本机发往组的数据 (IP_MULTICAST_LOOP)
+    #[arg(long = "mcast-loop", value_name = "BOOL")]
+    pub loopback: Option<bool>,
+}
+
+impl MulticastArgs {
+    /// 组播配置
+    pub fn multicast(&self) -> Result<Multicast> {
+        Ok(Multicast {
+            group: Multicast::parse_group(&self.group)?,
+            iface: self.iface.clone(),
+            source: self.source,
+            ttl: self.ttl,
+            loopback: self.loopback,
+        })
+    }
+}
+
+/// UDP 广播参数
+#[derive(ClapArgs, Debug, Clone)]
+pub struct BroadcastArgs {
+    /// 广播地址和端口 (如 255.255.255.255:5000 或 192.168.1.255:5000)
+    pub target: String,
+
+    /// 本地地址，接收发往该端口的广播时需要指定端口
+    #[arg(long, default_value = "0.0.0.0:0")]
     pub local: String,
-    
-    /// 远程服务器地址 (如 192.168.1.1:8000)
-    pub remote: String,
+}
+
+impl BroadcastArgs {
+    /// 广播地址
+    pub fn target(&self) -> Result<SocketAddr> {
+        let target = HostPort::parse(&self.target, "255.255.255.255").map_err(anyhow::Error::msg)?;
+        match target.literal()? {
+            Some(addr @ SocketAddr::V4(_)) => Ok(addr),
+            _ => anyhow::bail!("Broadcast address must be an IPv4 address: {}", self.target),
+        }
+    }
+
+    /// 绑定的本地地址
+    pub fn local(&self) -> Result<HostPort> {
+        HostPort::parse(&self.local, "0.0.0.0").map_err(|_| invalid_local(&self.local))
+    }
 }
 
 /// HTTP 客户端参数
@@ -142,6 +628,29 @@ pub struct HttpClientArgs {
     pub headers: Vec<String>,
 }
 
+/// 抓包回放参数
+#[derive(ClapArgs, Debug, Clone)]
+pub struct PcapReplayArgs {
+    /// pcap 或 pcapng 文件
+    pub file: PathBuf,
+
+    /// 要回放的流，按端点选择 (如 10.0.0.3:502 或 502)，默认为第一个有数据的流
+    #[arg(long)]
+    pub flow: Option<FlowSelector>,
+
+    /// 回放哪一端: client (连接目标并发送客户端的数据) 或 server (监听并发送服务端的数据)
+    #[arg(long, default_value = "client")]
+    pub side: ReplaySide,
+
+    /// 目标地址 (client 端) 或监听地址 (server 端)，默认为抓包中服务端的地址/端口
+    #[arg(long)]
+    pub target: Option<String>,
+
+    /// 本地地址 (client 端)
+    #[arg(long, default_value = "0.0.0.0:0")]
+    pub local: String,
+}
+
 /// 命令行参数完整结构
 #[derive(Debug, Clone)]
 pub struct Args {
@@ -154,14 +663,69 @@ pub struct Args {
     /// 模式 (服务端或客户端)
     pub mode: AppMode,
     
-    /// 本地地址
-    pub local_addr: SocketAddr,
+    /// 本地地址: 客户端绑定的地址，或服务器的所有监听地址
+    pub local_addrs: Vec<SocketAddr>,
     
-    /// 远程地址 (仅客户端模式)
-    pub remote_addr: Option<SocketAddr>,
+    /// 远程地址 (客户端模式或代理的上游)，主机名解析出多个地址时按连接尝试顺序排列
+    pub remote_addrs: Vec<SocketAddr>,
+
+    /// Unix 套接字路径 (仅 unix 命令)
+    pub unix_path: Option<String>,
+
+    /// 由主机名解析出的地址，会话开始时显示
+    pub resolved: Vec<Resolved>,
     
     /// HTTP 特定参数 (仅HTTP协议)
     pub http_args: Option<HttpClientArgs>,
+
+    /// 会话选项
+    pub options: SessionOptions,
+
+    /// 额外的模板文件
+    pub template_files: Vec<PathBuf>,
+
+    /// 启动后发送的文件
+    pub send_file: Option<PathBuf>,
+
+    /// 文件分块发送选项
+    pub chunking: ChunkOptions,
+
+    /// 自动化脚本
+    pub script: Option<PathBuf>,
+
+    /// 会话记录文件
+    pub log: Option<PathBuf>,
+
+    /// 会话记录格式
+    pub log_format: LogFormat,
+
+    /// 会话回放
+    pub replay: Option<ReplayConfig>,
+
+    /// 退出时导出的 pcapng 文件
+    pub pcap: Option<PathBuf>,
+
+    /// 退出时导出的 HAR 文件
+    pub har: Option<PathBuf>,
+
+    /// 无界面模式选项，None 表示使用终端界面
+    pub headless: Option<HeadlessOptions>,
+}
+
+impl Args {
+    /// 首个本地地址 (客户端绑定的地址或第一个监听地址)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addrs[0]
+    }
+}
+
+/// 会话回放配置
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    /// 从会话记录或抓包中提取的收发序列
+    pub recording: Recording,
+    /// 回放选项
+    pub options: ReplayOptions,
 }
 
 /// 协议类型
@@ -173,6 +737,26 @@ pub enum ProtocolType {
     Http,
     Http2,
     Http3,
+    Unix,
+    UnixDatagram,
+    TcpProxy,
+}
+
+impl ProtocolType {
+    /// 协议处理器工厂使用的协议名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProtocolType::Tcp => "tcp",
+            ProtocolType::Udp => "udp",
+            ProtocolType::WebSocket => "websocket",
+            ProtocolType::Http => "http",
+            ProtocolType::Http2 => "http2",
+            ProtocolType::Http3 => "http3",
+            ProtocolType::Unix => "unix",
+            ProtocolType::UnixDatagram => "unixgram",
+            ProtocolType::TcpProxy => "tcp-proxy",
+        }
+    }
 }
 
 /// 应用模式
@@ -182,102 +766,256 @@ pub enum AppMode {
     Client,
 }
 
-/// 解析命令行参数
-pub fn parse_args() -> Args {
+/// 解析命令行参数，解析主机名，加载回放的会话记录或抓包
+pub async fn parse_args() -> Result<Args> {
     let cli = Cli::parse();
+
+    let pcap_replay = match &cli.command {
+        Commands::Replay(replay) => Some((replay, PcapFlow::load(&replay.file, replay.flow)?)),
+        _ => None,
+    };
     
+    let family = match (cli.ipv4, cli.ipv6) {
+        (true, _) => IpFamily::V4,
+        (_, true) => IpFamily::V6,
+        _ => IpFamily::Any,
+    };
+
+    let udp_group = match &cli.command {
+        Commands::Udp(UdpCommands::Multicast(args)) => Some(UdpGroup::Multicast(args.multicast()?)),
+        Commands::Udp(UdpCommands::Broadcast(args)) => Some(UdpGroup::Broadcast(args.target()?)),
+        _ => None,
+    };
+
     // 提取信息，转换成我们的Args结构
-    let (protocol, mode, local_addr, remote_addr, http_args) = match &cli.command {
+    let (protocol, mode, local, remote, http_args) = match &cli.command {
         Commands::Tcp(cmd) => match cmd {
             TcpCommands::Server(args) => {
-                (ProtocolType::Tcp, AppMode::Server, parse_address(&args.address), None, None)
+                (ProtocolType::Tcp, AppMode::Server, args.listen(family)?, None, None)
             }
             TcpCommands::Client(args) => {
-                (ProtocolType::Tcp, AppMode::Client, parse_address(&args.local), Some(parse_address(&args.remote)), None)
+                (ProtocolType::Tcp, AppMode::Client, vec![args.local()?], Some(args.remote(family)?), None)
             }
         },
         Commands::TcpServer(args) => {
-            (ProtocolType::Tcp, AppMode::Server, parse_address(&args.address), None, None)
+            (ProtocolType::Tcp, AppMode::Server, args.listen(family)?, None, None)
         },
         Commands::TcpClient(args) => {
-            (ProtocolType::Tcp, AppMode::Client, parse_address(&args.local), Some(parse_address(&args.remote)), None)
+            (ProtocolType::Tcp, AppMode::Client, vec![args.local()?], Some(args.remote(family)?), None)
         },
         Commands::Udp(cmd) => match cmd {
             UdpCommands::Server(args) => {
-                (ProtocolType::Udp, AppMode::Server, parse_address(&args.address), None, None)
+                (ProtocolType::Udp, AppMode::Server, args.listen(family)?, None, None)
             }
             UdpCommands::Client(args) => {
-                (ProtocolType::Udp, AppMode::Client, parse_address(&args.local), Some(parse_address(&args.remote)), None)
+                (ProtocolType::Udp, AppMode::Client, vec![args.local()?], Some(args.remote(family)?), None)
+            }
+            UdpCommands::Multicast(args) => {
+                let listen = HostPort::from(args.multicast()?.listen_addr());
+                (ProtocolType::Udp, AppMode::Server, vec![listen], None, None)
             }
+            UdpCommands::Broadcast(args) => (ProtocolType::Udp, AppMode::Server, vec![args.local()?], None, None),
         },
         Commands::UdpServer(args) => {
-            (ProtocolType::Udp, AppMode::Server, parse_address(&args.address), None, None)
+            (ProtocolType::Udp, AppMode::Server, args.listen(family)?, None, None)
         },
         Commands::UdpClient(args) => {
-            (ProtocolType::Udp, AppMode::Client, parse_address(&args.local), Some(parse_address(&args.remote)), None)
+            (ProtocolType::Udp, AppMode::Client, vec![args.local()?], Some(args.remote(family)?), None)
         },
+        // Unix 套接字不使用 IP 地址
+        Commands::Unix(UnixCommands::Server(args)) => (args.protocol(), AppMode::Server, vec![parse_dummy_addr()], None, None),
+        Commands::Unix(UnixCommands::Client(args)) => (args.protocol(), AppMode::Client, vec![parse_dummy_addr()], None, None),
+        Commands::Proxy(ProxyCommands::Tcp(args)) => {
+            let listen = parse_address(&args.listen, family)?;
+            (ProtocolType::TcpProxy, AppMode::Server, vec![listen], Some(parse_address(&args.upstream, family)?), None)
+        }
         Commands::WebSocket(cmd) => match cmd {
             WebSocketCommands::Server(args) => {
-                (ProtocolType::WebSocket, AppMode::Server, parse_address(&args.address), None, None)
+                (ProtocolType::WebSocket, AppMode::Server, args.listen(family)?, None, None)
             }
             WebSocketCommands::Client(args) => {
-                (ProtocolType::WebSocket, AppMode::Client, parse_address(&args.local), Some(parse_address(&args.remote)), None)
+                (ProtocolType::WebSocket, AppMode::Client, vec![args.local()?], Some(args.remote(family)?), None)
             }
         },
         Commands::Http(cmd) => match cmd {
             HttpCommands::Server(args) => {
-                (ProtocolType::Http, AppMode::Server, parse_address(&args.address), None, None)
+                (ProtocolType::Http, AppMode::Server, args.listen(family)?, None, None)
             }
             HttpCommands::HttpClient(args) => {
-                (ProtocolType::Http, AppMode::Client, parse_dummy_addr(), None, Some(args.clone()))
+                (ProtocolType::Http, AppMode::Client, vec![parse_dummy_addr()], None, Some(args.clone()))
             }
         },
         Commands::Http2(cmd) => match cmd {
             HttpCommands::Server(args) => {
-                (ProtocolType::Http2, AppMode::Server, parse_address(&args.address), None, None)
+                (ProtocolType::Http2, AppMode::Server, args.listen(family)?, None, None)
             }
             HttpCommands::HttpClient(args) => {
-                (ProtocolType::Http2, AppMode::Client, parse_dummy_addr(), None, Some(args.clone()))
+                (ProtocolType::Http2, AppMode::Client, vec![parse_dummy_addr()], None, Some(args.clone()))
             }
         },
         Commands::Http3(cmd) => match cmd {
             HttpCommands::Server(args) => {
-                (ProtocolType::Http3, AppMode::Server, parse_address(&args.address), None, None)
+                (ProtocolType::Http3, AppMode::Server, args.listen(family)?, None, None)
             }
             HttpCommands::HttpClient(args) => {
-                (ProtocolType::Http3, AppMode::Client, parse_dummy_addr(), None, Some(args.clone()))
+                (ProtocolType::Http3, AppMode::Client, vec![parse_dummy_addr()], None, Some(args.clone()))
             }
         },
+        Commands::Replay(_) => {
+            let (args, flow) = pcap_replay.as_ref().expect("pcap flow is loaded above");
+            let protocol = match flow.transport {
+                Transport::Tcp => ProtocolType::Tcp,
+                Transport::Udp => ProtocolType::Udp,
+            };
+            match args.side {
+                ReplaySide::Client => {
+                    let target = match args.target.as_deref() {
+                        Some(target) => parse_address(target, family)?,
+                        None => HostPort::from(flow.server),
+                    };
+                    let local = HostPort::parse(&args.local, "0.0.0.0").map_err(|_| invalid_local(&args.local))?;
+                    (protocol, AppMode::Client, vec![local], Some(target), None)
+                }
+                ReplaySide::Server => {
+                    let listen = args.target.clone().unwrap_or_else(|| format!("0.0.0.0:{}", flow.server.port()));
+                    (protocol, AppMode::Server, vec![parse_address(&listen, family)?], None, None)
+                }
+            }
+        }
+    };
+
+    // 解析主机名: 本地地址取首选地址，远程地址保留全部地址用于竞速连接
+    let mut resolved = Vec::new();
+    let mut local_addrs = Vec::new();
+    for local in &local {
+        // 未指定的本地地址 (0.0.0.0) 连接时会换成远程地址的地址族
+        let local_family = if local.ip().is_some_and(|ip| ip.is_unspecified()) { IpFamily::Any } else { family };
+        let local = resolve::resolve(local, local_family).await?;
+        local_addrs.push(local.first());
+        resolved.extend(Some(local).filter(|local| local.target.ip().is_none()));
+    }
+
+    // 经由代理时连接代理服务器，再由代理连接目标；目前只有 TCP 客户端支持，
+    // WebSocket 和 HTTP 客户端尚未实现隧道，指定代理时报错而不是绕过代理直连
+    let proxy = match (&mode, &protocol) {
+        (AppMode::Client, ProtocolType::Tcp) => cli.session.proxy()?,
+        (AppMode::Client, ProtocolType::WebSocket | ProtocolType::Http | ProtocolType::Http2) => {
+            if cli.session.proxy()?.is_some() {
+                anyhow::bail!(
+                    "--proxy/ALL_PROXY is not yet supported for WebSocket and HTTP clients; use --no-proxy to connect directly"
+                );
+            }
+            None
+        }
+        _ if cli.session.proxy.is_some() => anyhow::bail!("--proxy only applies to TCP clients"),
+        _ => None,
+    };
+    let (remote_addrs, tunnel) = match (&remote, proxy) {
+        (Some(remote), Some(proxy)) => {
+            // 代理解析主机名时不在本地解析目标
+            let target = match remote.literal()? {
+                Some(addr) => HostPort::from(addr),
+                None if proxy.remote_dns() => remote.clone(),
+                None => {
+                    let remote = resolve::resolve(remote, family).await?;
+                    let target = HostPort::from(remote.first());
+                    resolved.push(remote);
+                    target
+                }
+            };
+            let server = resolve::resolve(&proxy.server, family).await?;
+            let addrs = server.addrs.clone();
+            resolved.extend(Some(server).filter(|server| server.target.ip().is_none()));
+            (addrs, Some(Tunnel { proxy, target }))
+        }
+        (Some(remote), None) => {
+            let remote = resolve::resolve(remote, family).await?;
+            let addrs = remote.addrs.clone();
+            resolved.extend(Some(remote).filter(|remote| remote.target.ip().is_none()));
+            (addrs, None)
+        }
+        (None, _) => (Vec::new(), None),
+    };
+
+    let mut options = cli.session.to_options();
+    // 允许多个进程同时接收同一组播组
+    if matches!(udp_group, Some(UdpGroup::Multicast(_))) {
+        options.socket.reuse_addr = options.socket.reuse_addr.or(Some(true));
+    }
+    options.udp_group = udp_group;
+    options.tunnel = tunnel;
+    // 同时监听 0.0.0.0 和 [::] 的同一端口时，[::] 只接受 IPv6 连接，否则两者冲突
+    if options.socket.only_v6.is_none() && dual_stack_conflict(&local_addrs) {
+        options.socket.only_v6 = Some(true);
+    }
+
+    let replay_options = ReplayOptions {
+        speed: cli.replay_speed,
+        wait_for_input: cli.replay_wait,
+        expect_timeout: Duration::from_millis(cli.replay_timeout),
+    };
+    let replay = match (&pcap_replay, &cli.replay) {
+        (Some((args, flow)), _) => Some(Recording::from_records(&flow.records, None, args.side == ReplaySide::Server)
+            .ensure_sends(&args.file)?),
+        (None, Some(path)) => Some(Recording::load(path, cli.replay_conn.as_deref(), cli.replay_invert)?),
+        (None, None) => None,
     };
 
-    Args {
+    Ok(Args {
         vertical_layout: cli.vertical_layout,
         protocol,
         mode,
-        local_addr,
-        remote_addr,
+        local_addrs,
+        remote_addrs,
+        unix_path: match &cli.command {
+            Commands::Unix(UnixCommands::Server(args) | UnixCommands::Client(args)) => Some(args.path.clone()),
+            _ => None,
+        },
+        resolved,
         http_args,
-    }
+        options,
+        template_files: cli.templates.clone(),
+        send_file: cli.send_file.clone(),
+        chunking: ChunkOptions {
+            chunk_size: cli.chunk_size,
+            delay: Duration::from_millis(cli.chunk_delay),
+        },
+        script: cli.script.clone(),
+        log: cli.log.clone(),
+        log_format: cli.log_format,
+        replay: replay.map(|recording| ReplayConfig {
+            recording,
+            options: replay_options,
+        }),
+        pcap: cli.pcap.clone(),
+        har: cli.har.clone(),
+        headless: (cli.headless || !std::io::stdout().is_terminal()).then(|| HeadlessOptions {
+            output: cli.output,
+            eof_wait: cli.eof_wait.map(Duration::from_millis),
+        }),
+    })
 }
 
-/// 解析地址字符串，如果只提供端口则使用 127.0.0.1
-fn parse_address(addr_str: &str) -> SocketAddr {
-    // 检查是否只有端口号
-    if let Ok(port) = addr_str.parse::<u16>() {
-        format!("127.0.0.1:{}", port).parse().unwrap()
-    } else {
-        // 尝试直接解析完整地址
-        match addr_str.parse() {
-            Ok(addr) => addr,
-            Err(_) => {
-                // 如果解析失败，返回默认地址
-                "127.0.0.1:8000".parse().unwrap()
-            }
-        }
-    }
+/// 解析地址字符串 (主机名或 IP 加端口)，如果只提供端口则使用 127.0.0.1 (指定 -6 时为 ::1)
+fn parse_address(addr_str: &str, family: IpFamily) -> Result<HostPort> {
+    let loopback = if family == IpFamily::V6 { "::1" } else { "127.0.0.1" };
+    HostPort::parse(addr_str, loopback).map_err(anyhow::Error::msg)
+}
+
+/// 是否同时监听了同一端口的 IPv4 和 IPv6 未指定地址
+fn dual_stack_conflict(addrs: &[SocketAddr]) -> bool {
+    addrs.iter().filter(|v4| v4.is_ipv4() && v4.ip().is_unspecified()).any(|v4| {
+        addrs
+            .iter()
+            .any(|v6| v6.is_ipv6() && v6.ip().is_unspecified() && v6.port() == v4.port())
+    })
+}
+
+fn invalid_local(local: &str) -> anyhow::Error {
+    anyhow::anyhow!("Invalid local address: {} (expected HOST:PORT or PORT)", local)
 }
 
 /// 为HTTP客户端模式生成一个虚拟地址，因为HTTP客户端不需要绑定到特定地址
-fn parse_dummy_addr() -> SocketAddr {
-    "127.0.0.1:0".parse().unwrap()
+fn parse_dummy_addr() -> HostPort {
+    HostPort::from(SocketAddr::from(([127, 0, 0, 1], 0)))
 }
\ No newline at end of file
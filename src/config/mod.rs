@@ -1,2 +1,4 @@
 pub mod language;
+pub mod rules;
+pub mod templates;
 // pub mod tls;
\ No newline at end of file
@@ -0,0 +1,18 @@
+use anyhow::{Context, Result};
+use std::{fs, path::Path};
+
+use crate::protocols::autoreply::{parse_rules, AutoReplyRules};
+
+/// 从 JSON 文件加载自动回复规则
+pub fn load_rules_file(path: &Path) -> Result<AutoReplyRules> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read rules file {}", path.display()))?;
+    let rules = parse_rules(&source)
+        .map_err(|e| anyhow::anyhow!("Invalid rules file {}: {}", path.display(), e))?;
+    Ok(AutoReplyRules::new(rules))
+}
+
+/// 命令行参数解析器: 加载 `--rules` 指定的规则文件
+pub fn parse_rules_arg(path: &str) -> std::result::Result<AutoReplyRules, String> {
+    load_rules_file(Path::new(path)).map_err(|e| format!("{:#}", e))
+}
@@ -0,0 +1,47 @@
+use anyhow::{Context, Result};
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::utils::template::{parse_templates, Template};
+
+/// 模板文件扩展名
+const TEMPLATE_EXTENSION: &str = "tpl";
+
+/// 默认模板目录: <配置目录>/nt/templates
+pub fn default_template_dir() -> Option<PathBuf> {
+    dirs_next::config_dir().map(|dir| dir.join("nt").join("templates"))
+}
+
+/// 加载单个模板文件，文件名 (不含扩展名) 作为未使用 `struct` 块时的模板名
+pub fn load_template_file(path: &Path) -> Result<Vec<Template>> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read template file {}", path.display()))?;
+    let default_name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "template".to_string());
+    parse_templates(&source, &default_name)
+        .map_err(|e| anyhow::anyhow!("Invalid template file {}: {}", path.display(), e))
+}
+
+/// 加载默认模板目录下的所有 `.tpl` 文件以及额外指定的模板文件
+pub fn load_templates(extra_files: &[PathBuf]) -> Result<Vec<Template>> {
+    let mut files = Vec::new();
+
+    if let Some(dir) = default_template_dir() {
+        if let Ok(entries) = fs::read_dir(&dir) {
+            let mut defaults: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == TEMPLATE_EXTENSION))
+                .collect();
+            defaults.sort();
+            files.extend(defaults);
+        }
+    }
+    files.extend(extra_files.iter().cloned());
+
+    let mut templates = Vec::new();
+    for file in &files {
+        templates.extend(load_template_file(file)?);
+    }
+    Ok(templates)
+}
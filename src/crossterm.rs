@@ -8,11 +8,15 @@ use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScree
 use ratatui::backend::{Backend, CrosstermBackend};
 use ratatui::Terminal;
 
-use crate::app::App;
+use crate::app::{export_on_exit, App};
 use crate::cli::args::Args;
 use crate::ui::ui;
 
 pub async fn run(tick_rate: Duration, enhanced_graphics: bool, args: Args) -> Result<(), Box<dyn Error>> {
+    // create app before setting up the terminal, so startup errors (script, log file, bind)
+    // are reported on a normal terminal
+    let mut app = App::new(args).await?;
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -20,10 +24,10 @@ pub async fn run(tick_rate: Duration, enhanced_graphics: bool, args: Args) -> Re
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // create app and run it
-    let mut app = App::new(args).await.unwrap();
-
+    // run it
     let app_result = run_app(&mut terminal, &mut app, tick_rate).await;
+    app.args.options.capture.finish().await;
+    let exports = export_on_exit(&app.args, &app.history);
 
     // restore terminal
     disable_raw_mode()?;
@@ -33,6 +37,9 @@ pub async fn run(tick_rate: Duration, enhanced_graphics: bool, args: Args) -> Re
     if let Err(err) = app_result {
         println!("{err:?}");
     }
+    for report in exports {
+        println!("{report}");
+    }
 
     Ok(())
 }
@@ -51,6 +58,7 @@ async fn run_app<B: Backend>(
 
         // 接收服务端消息
         app.receive_message();
+        app.poll_transfers();
 
         // 绘制界面
         terminal.draw(|frame| ui::draw(frame, app))?;
@@ -74,6 +82,7 @@ async fn run_app<B: Backend>(
 
         if last_tick.elapsed() >= tick_rate {
             // 处理定时任务
+            app.on_tick();
             last_tick = Instant::now();
         }
     }
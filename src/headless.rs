@@ -0,0 +1,245 @@
+use anyhow::Result;
+use bytes::Bytes;
+use std::{str::FromStr, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc::{channel, Sender},
+};
+
+use crate::app::{export_on_exit, start_session};
+use crate::capture::history::MessageHistory;
+use crate::cli::args::{AppMode, Args, ProtocolType};
+use crate::protocols::common::WriteAck;
+use crate::protocols::{framing::FramingMode, LinkState, Message, MessageDirection, MessageType, SessionEvent};
+use crate::transfer::{spawn_file_transfer, TransferProgress};
+use crate::utils::data_format::bytes_to_hex;
+
+/// 无界面模式下收到数据的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// 原样输出字节
+    #[default]
+    Raw,
+    /// 每帧一行十六进制
+    Hex,
+    /// 每帧一行 JSON
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "raw" => Ok(OutputFormat::Raw),
+            "hex" => Ok(OutputFormat::Hex),
+            "json" | "jsonl" => Ok(OutputFormat::Json),
+            other => Err(format!("Unknown output format: {}", other)),
+        }
+    }
+}
+
+/// 无界面模式选项
+#[derive(Debug, Clone, Default)]
+pub struct HeadlessOptions {
+    /// 收到数据的输出格式
+    pub output: OutputFormat,
+    /// 标准输入结束后再等待多久退出，None 表示一直运行到连接断开或 Ctrl+C
+    pub eof_wait: Option<Duration>,
+}
+
+/// 无界面模式: 收到的数据写到标准输出，标准输入的每一行发送给对端，连接事件写到标准错误
+pub async fn run(args: Args, options: HeadlessOptions) -> Result<()> {
+    let (server_to_ui_tx, mut server_to_ui_rx) = channel::<Message>(1000);
+    let mut handler = start_session(&args, server_to_ui_tx).await?;
+    let ui_to_server_tx = handler
+        .get_ui_to_server_sender()
+        .ok_or_else(|| anyhow::anyhow!("Protocol handler is not running"))?;
+
+    if let Some(path) = args.send_file.clone() {
+        let (progress_tx, progress_rx) = channel::<TransferProgress>(100);
+        spawn_file_transfer(path, args.chunking.clone(), args.options.clone(), ui_to_server_tx.clone(), progress_tx);
+        spawn_progress_reporter(progress_rx);
+    }
+
+    // 导出抓包文件时标准输入发送的数据写出后也要进入消息历史 (代理注入的数据由处理器发来)
+    let mut history = MessageHistory::default();
+    let (write_ack, mut written_rx) = WriteAck::new();
+    let exporting = args.pcap.is_some() || args.har.is_some();
+    let ack = match exporting && args.protocol != ProtocolType::TcpProxy {
+        true => write_ack,
+        false => WriteAck::default(),
+    };
+    let (eof_tx, mut eof_rx) = channel::<()>(1);
+    spawn_stdin_reader(ui_to_server_tx, ack, eof_tx);
+
+    let delimiter = match &args.options.framing.mode {
+        FramingMode::Delimiter(delimiter) => Some(delimiter.clone()),
+        _ => None,
+    };
+    let mut stdout = tokio::io::stdout();
+    let mut exit_deadline: Option<tokio::time::Instant> = None;
+
+    loop {
+        let deadline = async move {
+            match exit_deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            message = server_to_ui_rx.recv() => {
+                let Some(message) = message else {
+                    break;
+                };
+                history.push(&message);
+                let connection = message
+                    .connection_info
+                    .as_ref()
+                    .map(|info| info.connection_id.clone())
+                    .unwrap_or_default();
+
+                match &message.content {
+                    MessageType::ClientConnected => match message.connection_info.as_ref().and_then(|info| info.family()) {
+                        Some(family) => eprintln!("[connected] {} ({})", connection, family),
+                        None => eprintln!("[connected] {}", connection),
+                    },
+                    MessageType::ClientDisconnected => {
+                        eprintln!("[disconnected] {}", connection);
+                        // 客户端模式下对端断开即结束 (启用自动重连时等待重连结束)
+                        if args.mode == AppMode::Client && args.options.reconnect.is_none() {
+                            break;
+                        }
+                    }
+                    MessageType::Event(SessionEvent::Link(LinkState::Disconnected)) => {
+                        eprintln!("[!] {}", SessionEvent::Link(LinkState::Disconnected));
+                        break;
+                    }
+                    MessageType::Event(event) => eprintln!("[!] {}", event),
+                    content => {
+                        let Some(data) = content.payload() else {
+                            continue;
+                        };
+                        if message.direction == MessageDirection::Sent {
+                            // 标准输入发送的数据不再回显
+                            if message.connection_info.is_none() {
+                                continue;
+                            }
+                            eprintln!("[sent] {} {}", connection, bytes_to_hex(&data));
+                            continue;
+                        }
+                        let output = format_output(options.output, &message, &connection, &data, delimiter.as_deref());
+                        stdout.write_all(&output).await?;
+                        stdout.flush().await?;
+                    }
+                }
+            }
+            Some(written) = written_rx.recv() => history.push_written(written),
+            Some(()) = eof_rx.recv() => {
+                if let Some(wait) = options.eof_wait {
+                    exit_deadline = Some(tokio::time::Instant::now() + wait);
+                }
+            }
+            _ = deadline => break,
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    handler.stop().await?;
+    args.options.capture.finish().await;
+    for report in export_on_exit(&args, &history) {
+        eprintln!("[export] {}", report);
+    }
+    Ok(())
+}
+
+/// 按输出格式生成一帧的输出内容
+fn format_output(
+    format: OutputFormat,
+    message: &Message,
+    connection: &str,
+    data: &Bytes,
+    delimiter: Option<&[u8]>,
+) -> Vec<u8> {
+    match format {
+        // 分帧时去掉的分隔符在输出时补回，保持原始字节流
+        OutputFormat::Raw => {
+            let mut output = data.to_vec();
+            output.extend_from_slice(delimiter.unwrap_or_default());
+            output
+        }
+        OutputFormat::Hex => format!("{}\n", bytes_to_hex(data)).into_bytes(),
+        OutputFormat::Json => {
+            let line = serde_json::json!({
+                "time": message.timestamp.to_rfc3339(),
+                "conn": connection,
+                "len": data.len(),
+                "text": std::str::from_utf8(data).ok(),
+                "hex": bytes_to_hex(data),
+            });
+            format!("{}\n", line).into_bytes()
+        }
+    }
+}
+
+/// 逐行读取标准输入并发送 (写出后通过 `ack` 通知)，读到 EOF 时通知主循环
+fn spawn_stdin_reader(ui_to_server_tx: Sender<Message>, ack: WriteAck, eof_tx: Sender<()>) {
+    tokio::spawn(async move {
+        let mut stdin = BufReader::new(tokio::io::stdin());
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match stdin.read_until(b'\n', &mut line).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let content = MessageType::from_payload(Bytes::copy_from_slice(&line));
+                    let message = Message {
+                        ack: ack.clone(),
+                        ..Message::new_sent(content, None)
+                    };
+                    if ui_to_server_tx.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let _ = eof_tx.send(()).await;
+    });
+}
+
+/// 将文件发送进度写到标准错误
+fn spawn_progress_reporter(mut progress_rx: tokio::sync::mpsc::Receiver<TransferProgress>) {
+    tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            if progress.done {
+                match &progress.error {
+                    Some(e) => eprintln!("[!] File {} failed: {}", progress.path, e),
+                    None => eprintln!("[file] {} sent ({} bytes)", progress.path, progress.sent),
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_output() {
+        let data = Bytes::from_static(b"hi");
+        let message = Message::new_received(MessageType::from_payload(data.clone()), None);
+        let output = |format| format_output(format, &message, "127.0.0.1:9000", &data, Some(b"\r\n"));
+
+        // 原样输出时补回分隔符
+        assert_eq!(output(OutputFormat::Raw), b"hi\r\n");
+        assert_eq!(output(OutputFormat::Hex), b"68 69\n");
+        let json: serde_json::Value = serde_json::from_slice(&output(OutputFormat::Json)).unwrap();
+        assert_eq!(json["conn"], "127.0.0.1:9000");
+        assert_eq!(json["len"], 2);
+        assert_eq!(json["text"], "hi");
+        assert_eq!(json["hex"], "68 69");
+        assert_eq!("JSON".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+    }
+}
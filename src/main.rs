@@ -1,10 +1,15 @@
+mod capture;
 mod cli;
 mod ui;
 mod config;
 mod crossterm;
+mod headless;
 mod utils;
 mod app;
 mod protocols;
+mod repeater;
+mod script;
+mod transfer;
 
 use std::time::Duration;
 
@@ -14,10 +19,17 @@ use cli::args::parse_args;
 #[tokio::main]
 async fn main() -> Result<()> {
     // 解析命令行参数
-    let args = parse_args();
+    let args = parse_args().await?;
 
-    // 运行主应用
-    let tick_rate = Duration::from_millis(100);
-    crossterm::run(tick_rate, true, args).await?;
+    // 无界面模式或运行主应用
+    match args.headless.clone() {
+        Some(options) => headless::run(args, options).await?,
+        None => {
+            let tick_rate = Duration::from_millis(100);
+            crossterm::run(tick_rate, true, args)
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,364 @@
+use bytes::Bytes;
+use regex::bytes::Regex;
+use serde::Deserialize;
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use crate::utils::data_format::hex_to_bytes;
+
+/// 帧匹配方式
+#[derive(Debug, Clone)]
+pub enum RuleMatcher {
+    /// 正则表达式 (按字节匹配，捕获组可用于回复)
+    Regex(Regex),
+    /// 十六进制模式，`??` 匹配任意单个字节并作为捕获组，结尾的 `*` 匹配剩余任意数据
+    HexPattern {
+        /// 每个字节的期望值，None 为通配
+        bytes: Vec<Option<u8>>,
+        /// 是否允许帧比模式长
+        open_ended: bool,
+    },
+    /// 前缀匹配，前缀之后的剩余数据作为捕获组 1
+    Prefix(Vec<u8>),
+}
+
+impl RuleMatcher {
+    /// 解析十六进制模式，如 `01 03 ?? ?? *`
+    pub fn hex_pattern(pattern: &str) -> Result<Self, String> {
+        let mut bytes = Vec::new();
+        let mut open_ended = false;
+        let tokens: Vec<&str> = pattern.split_whitespace().collect();
+        for (index, token) in tokens.iter().enumerate() {
+            match *token {
+                "*" if index == tokens.len() - 1 => open_ended = true,
+                "??" => bytes.push(None),
+                hex => bytes.extend(hex_to_bytes(hex)?.into_iter().map(Some)),
+            }
+        }
+        if bytes.is_empty() && !open_ended {
+            return Err("Hex pattern must not be empty".to_string());
+        }
+        Ok(RuleMatcher::HexPattern { bytes, open_ended })
+    }
+
+    /// 匹配帧，成功时返回捕获组 (下标 0 为整个匹配)
+    pub fn captures(&self, frame: &[u8]) -> Option<Vec<Bytes>> {
+        match self {
+            RuleMatcher::Regex(regex) => regex.captures(frame).map(|caps| {
+                caps.iter()
+                    .map(|group| group.map(|m| Bytes::copy_from_slice(m.as_bytes())).unwrap_or_default())
+                    .collect()
+            }),
+            RuleMatcher::HexPattern { bytes, open_ended } => {
+                let length_ok = if *open_ended {
+                    frame.len() >= bytes.len()
+                } else {
+                    frame.len() == bytes.len()
+                };
+                if !length_ok {
+                    return None;
+                }
+
+                let mut captures = vec![Bytes::copy_from_slice(frame)];
+                for (expected, actual) in bytes.iter().zip(frame) {
+                    match expected {
+                        Some(expected) if expected != actual => return None,
+                        Some(_) => {}
+                        None => captures.push(Bytes::copy_from_slice(&[*actual])),
+                    }
+                }
+                Some(captures)
+            }
+            RuleMatcher::Prefix(prefix) => frame.strip_prefix(prefix.as_slice()).map(|rest| {
+                vec![Bytes::copy_from_slice(frame), Bytes::copy_from_slice(rest)]
+            }),
+        }
+    }
+}
+
+/// 回复模板片段
+#[derive(Debug, Clone, PartialEq)]
+enum ReplyPart {
+    /// 原样输出的字节
+    Literal(Vec<u8>),
+    /// 插入第 N 个捕获组
+    Capture(usize),
+}
+
+/// 回复模板，`$0`-`$9` 引用匹配的捕获组
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplyTemplate {
+    parts: Vec<ReplyPart>,
+}
+
+impl ReplyTemplate {
+    /// 解析文本回复，`$N` 替换为捕获组，`$$` 表示 `$` 本身
+    pub fn text(template: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut literal = Vec::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek().copied()) {
+                ('$', Some('$')) => {
+                    chars.next();
+                    literal.push(b'$');
+                }
+                ('$', Some(d)) if d.is_ascii_digit() => {
+                    chars.next();
+                    if !literal.is_empty() {
+                        parts.push(ReplyPart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(ReplyPart::Capture(d as usize - '0' as usize));
+                }
+                _ => {
+                    let mut buf = [0u8; 4];
+                    literal.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(ReplyPart::Literal(literal));
+        }
+        Self { parts }
+    }
+
+    /// 解析十六进制回复，如 `01 03 02 $1 $2`
+    pub fn hex(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        for token in template.split_whitespace() {
+            match token.strip_prefix('$') {
+                Some(index) => {
+                    let index = index.parse().map_err(|_| format!("Invalid capture reference: {}", token))?;
+                    parts.push(ReplyPart::Capture(index));
+                }
+                None => parts.push(ReplyPart::Literal(hex_to_bytes(token)?)),
+            }
+        }
+        Ok(Self { parts })
+    }
+
+    /// 用捕获组生成回复内容，不存在的捕获组替换为空
+    pub fn render(&self, captures: &[Bytes]) -> Bytes {
+        let mut reply = Vec::new();
+        for part in &self.parts {
+            match part {
+                ReplyPart::Literal(bytes) => reply.extend_from_slice(bytes),
+                ReplyPart::Capture(index) => {
+                    if let Some(capture) = captures.get(*index) {
+                        reply.extend_from_slice(capture);
+                    }
+                }
+            }
+        }
+        Bytes::from(reply)
+    }
+}
+
+/// 自动回复规则
+#[derive(Debug, Clone)]
+pub struct AutoReplyRule {
+    /// 规则名称
+    pub name: String,
+    /// 匹配方式
+    pub matcher: RuleMatcher,
+    /// 回复模板
+    pub reply: ReplyTemplate,
+    /// 回复延迟
+    pub delay: Duration,
+    /// 是否启用
+    pub enabled: bool,
+}
+
+/// 规则命中结果
+#[derive(Debug, Clone)]
+pub struct AutoReplyHit {
+    /// 命中的规则名称
+    pub rule: String,
+    /// 回复内容
+    pub reply: Bytes,
+    /// 回复延迟
+    pub delay: Duration,
+}
+
+impl fmt::Display for AutoReplyHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "auto-reply '{}' ({} bytes", self.rule, self.reply.len())?;
+        if !self.delay.is_zero() {
+            write!(f, ", after {}ms", self.delay.as_millis())?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// 自动回复规则集，在UI和各协议处理器之间共享，UI中启用/禁用规则会立即生效
+#[derive(Debug, Clone, Default)]
+pub struct AutoReplyRules {
+    rules: Arc<RwLock<Vec<AutoReplyRule>>>,
+}
+
+impl AutoReplyRules {
+    pub fn new(rules: Vec<AutoReplyRule>) -> Self {
+        Self {
+            rules: Arc::new(RwLock::new(rules)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.read().map(|rules| rules.is_empty()).unwrap_or(true)
+    }
+
+    /// 按顺序匹配已启用的规则，返回所有命中的回复
+    pub fn evaluate(&self, frame: &[u8]) -> Vec<AutoReplyHit> {
+        let Ok(rules) = self.rules.read() else {
+            return Vec::new();
+        };
+        rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .filter_map(|rule| {
+                rule.matcher.captures(frame).map(|captures| AutoReplyHit {
+                    rule: rule.name.clone(),
+                    reply: rule.reply.render(&captures),
+                    delay: rule.delay,
+                })
+            })
+            .collect()
+    }
+
+    /// 启用或禁用指定规则
+    pub fn toggle(&self, index: usize) {
+        if let Ok(mut rules) = self.rules.write() {
+            if let Some(rule) = rules.get_mut(index) {
+                rule.enabled = !rule.enabled;
+            }
+        }
+    }
+
+    /// 规则 (名称, 是否启用) 列表，供界面显示
+    pub fn summaries(&self) -> Vec<(String, bool)> {
+        self.rules
+            .read()
+            .map(|rules| rules.iter().map(|rule| (rule.name.clone(), rule.enabled)).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// 规则文件中的匹配方式
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MatchConfig {
+    Regex(String),
+    Hex(String),
+    Prefix(String),
+}
+
+/// 规则文件中的回复内容
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReplyConfig {
+    Text(String),
+    Hex(String),
+}
+
+/// 规则文件中的单条规则
+#[derive(Deserialize)]
+struct RuleConfig {
+    name: Option<String>,
+    #[serde(rename = "match")]
+    matcher: MatchConfig,
+    reply: ReplyConfig,
+    #[serde(default)]
+    delay_ms: u64,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// 解析 JSON 规则列表，例如:
+///
+/// ```json
+/// [
+///   { "name": "ping", "match": { "regex": "^PING (\\w+)" }, "reply": { "text": "PONG $1\n" } },
+///   { "match": { "hex": "01 03 ?? ?? *" }, "reply": { "hex": "01 03 02 $1 $2" }, "delay_ms": 50 }
+/// ]
+/// ```
+pub fn parse_rules(source: &str) -> Result<Vec<AutoReplyRule>, String> {
+    let configs: Vec<RuleConfig> = serde_json::from_str(source).map_err(|e| e.to_string())?;
+    configs
+        .into_iter()
+        .enumerate()
+        .map(|(index, config)| {
+            let name = config.name.unwrap_or_else(|| format!("rule{}", index + 1));
+            let matcher = match config.matcher {
+                MatchConfig::Regex(pattern) => RuleMatcher::Regex(
+                    Regex::new(&pattern).map_err(|e| format!("Rule {}: invalid regex: {}", name, e))?,
+                ),
+                MatchConfig::Hex(pattern) => {
+                    RuleMatcher::hex_pattern(&pattern).map_err(|e| format!("Rule {}: {}", name, e))?
+                }
+                MatchConfig::Prefix(prefix) => RuleMatcher::Prefix(prefix.into_bytes()),
+            };
+            let reply = match config.reply {
+                ReplyConfig::Text(text) => ReplyTemplate::text(&text),
+                ReplyConfig::Hex(hex) => ReplyTemplate::hex(&hex).map_err(|e| format!("Rule {}: {}", name, e))?,
+            };
+            Ok(AutoReplyRule {
+                name,
+                matcher,
+                reply,
+                delay: Duration::from_millis(config.delay_ms),
+                enabled: config.enabled,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(source: &str) -> AutoReplyRules {
+        AutoReplyRules::new(parse_rules(source).unwrap())
+    }
+
+    #[test]
+    fn test_regex_capture() {
+        let rules = rules(r#"[{ "name": "ping", "match": { "regex": "^PING (\\w+)" }, "reply": { "text": "PONG $1 $$" } }]"#);
+        let hits = rules.evaluate(b"PING abc");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].rule, "ping");
+        assert_eq!(hits[0].reply, Bytes::from_static(b"PONG abc $"));
+        assert!(rules.evaluate(b"HELLO").is_empty());
+    }
+
+    #[test]
+    fn test_hex_pattern_and_prefix() {
+        let rules = rules(
+            r#"[
+                { "match": { "hex": "01 03 ?? ?? *" }, "reply": { "hex": "01 83 $1 $2" }, "delay_ms": 20 },
+                { "match": { "prefix": "GET " }, "reply": { "text": "OK $1" }, "enabled": false }
+            ]"#,
+        );
+        let hits = rules.evaluate(&[0x01, 0x03, 0xAA, 0xBB, 0xFF]);
+        assert_eq!(hits[0].reply, Bytes::from_static(&[0x01, 0x83, 0xAA, 0xBB]));
+        assert_eq!(hits[0].delay, Duration::from_millis(20));
+        assert!(rules.evaluate(&[0x01, 0x03, 0xAA]).is_empty());
+
+        assert!(rules.evaluate(b"GET /").is_empty());
+        rules.toggle(1);
+        assert_eq!(rules.evaluate(b"GET /")[0].reply, Bytes::from_static(b"OK /"));
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(parse_rules(r#"[{ "match": { "regex": "(" }, "reply": { "text": "" } }]"#).is_err());
+        assert!(parse_rules(r#"[{ "match": { "hex": "zz" }, "reply": { "text": "" } }]"#).is_err());
+        assert!(parse_rules(r#"[{ "match": { "hex": "01" }, "reply": { "hex": "$x" } }]"#).is_err());
+    }
+}
@@ -3,10 +3,24 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{DateTime, Local};
 use h2::server;
-use std::net::SocketAddr;
-use tokio::sync::mpsc::{Receiver, Sender};
+use std::{fmt, net::SocketAddr, time::Duration};
+use tokio::sync::mpsc::{unbounded_channel, Receiver, Sender, UnboundedReceiver, UnboundedSender};
 
+use crate::capture::logger::SessionCapture;
+use crate::protocols::autoreply::{AutoReplyHit, AutoReplyRules};
+use crate::protocols::fault::{FaultEvent, Faults};
+use crate::protocols::tunnel::{Tunnel, TunnelStep};
+use crate::protocols::framing::{FrameTooLong, FramingConfig};
+use crate::protocols::multicast::UdpGroup;
+use crate::protocols::proxy::{ProxyCommand, ProxyEvent, TcpProxyHandler};
+use crate::protocols::reconnect::{OfflineSend, ReconnectPolicy};
+use crate::protocols::resolve::{IpFamily, Resolved};
+use crate::protocols::sockopt::{SocketOption, SocketOptions};
+use crate::protocols::timeout::{TimeoutExpired, Timeouts};
 use crate::protocols::tcp::TcpServerHandler;
+use crate::protocols::udp::{UdpClientHandler, UdpServerHandler};
+use crate::utils::checksum::{ChecksumConfig, ChecksumMismatch};
+use crate::utils::data_format::hex_to_bytes;
 
 /// 传输消息类型
 #[derive(Debug, Clone)]
@@ -17,10 +31,183 @@ pub enum MessageType {
     Binary(Bytes),
     /// 十六进制消息
     Hex(String),
+    /// 线路上的原始数据: 发送时不追加校验值也不分帧 (如回放记录中的帧)
+    Raw(Bytes),
     /// 客户端连接消息
     ClientConnected,
     /// 客户端断开连接消息
     ClientDisconnected,
+    /// 会话事件 (校验失败等)
+    Event(SessionEvent),
+    /// 连接控制命令 (发往协议处理器)
+    Control(ConnectionControl),
+    /// 代理命令 (发往代理处理器)
+    Proxy(ProxyCommand),
+}
+
+/// 连接控制命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionControl {
+    /// 关闭连接 (服务器端即踢出客户端)
+    Close,
+    /// 半关闭: 关闭写入方向 (发送 FIN)，继续读取直到对端关闭
+    Shutdown,
+    /// 发送 RST 立即中止连接，丢弃未发送的数据
+    Abort,
+    /// 修改套接字选项
+    SetOption(SocketOption),
+}
+
+impl ConnectionControl {
+    /// 执行该命令需要设置的套接字选项 (中止连接需将 SO_LINGER 设为 0)
+    pub fn socket_option(&self) -> Option<SocketOption> {
+        match *self {
+            ConnectionControl::SetOption(option) => Some(option),
+            ConnectionControl::Abort => Some(SocketOption::Linger(Some(Duration::ZERO))),
+            ConnectionControl::Close | ConnectionControl::Shutdown => None,
+        }
+    }
+}
+
+/// 会话事件
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    /// 收到的帧校验失败
+    ChecksumMismatch(ChecksumMismatch),
+    /// 自动回复规则命中
+    AutoReply(AutoReplyHit),
+    /// 脚本输出的日志或错误
+    ScriptLog(String),
+    /// 会话回放进度和比对结果
+    Replay(String),
+    /// 客户端连接状态变化 (启用自动重连时)
+    Link(LinkState),
+    /// 连接或重连失败
+    ConnectFailed(String),
+    /// 连接、读空闲、写入或响应超时
+    Timeout(TimeoutExpired),
+    /// 套接字选项设置失败
+    OptionFailed(String),
+    /// 连接断开期间发送的消息被排队或丢弃
+    Unsent { bytes: usize, queued: bool },
+    /// 本端执行了关闭、半关闭或中止
+    LocalClose(ConnectionControl),
+    /// 写入方向已关闭，发送的消息被丢弃
+    WriteClosed { bytes: usize },
+    /// 主机名解析出的地址
+    Resolved(Resolved),
+    /// Unix 流套接字对端进程的凭据
+    PeerCredentials(PeerCredentials),
+    /// 代理连接对的转发状态
+    Proxy(ProxyEvent),
+    /// 故障注入丢弃、篡改了数据或关闭了连接
+    Fault(FaultEvent),
+    /// 经由代理建立隧道的握手步骤
+    Tunnel(TunnelStep),
+    /// 待发送的数据超出长度前缀分帧的长度字段范围，未发送
+    FrameTooLong(FrameTooLong),
+    /// 读写、接受连接等 I/O 操作失败
+    IoError(String),
+}
+
+/// 对端进程凭据 (SO_PEERCRED)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    /// 部分平台无法获取进程号
+    pub pid: Option<i32>,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl fmt::Display for PeerCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(pid) = self.pid {
+            write!(f, "pid={} ", pid)?;
+        }
+        write!(f, "uid={} gid={}", self.uid, self.gid)
+    }
+}
+
+/// 客户端连接状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    /// 正在首次连接
+    Connecting,
+    /// 已连接
+    Connected,
+    /// 等待 `delay` 后进行第 `attempt` 次重连
+    Reconnecting { attempt: u32, max_attempts: Option<u32>, delay: Duration },
+    /// 已断开，不再重连
+    Disconnected,
+}
+
+impl fmt::Display for LinkState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkState::Connecting => write!(f, "Connecting"),
+            LinkState::Connected => write!(f, "Connected"),
+            LinkState::Reconnecting { attempt, max_attempts: Some(max), .. } => {
+                write!(f, "Reconnecting ({}/{})", attempt, max)
+            }
+            LinkState::Reconnecting { attempt, .. } => write!(f, "Reconnecting ({})", attempt),
+            LinkState::Disconnected => write!(f, "Disconnected"),
+        }
+    }
+}
+
+impl fmt::Display for SessionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionEvent::ChecksumMismatch(mismatch) => write!(f, "{}", mismatch),
+            SessionEvent::AutoReply(hit) => write!(f, "{}", hit),
+            SessionEvent::ScriptLog(line) => write!(f, "script: {}", line),
+            SessionEvent::Replay(line) => write!(f, "replay: {}", line),
+            SessionEvent::Link(state @ LinkState::Reconnecting { delay, .. }) => {
+                write!(f, "link: {} in {:.1}s", state, delay.as_secs_f64())
+            }
+            SessionEvent::Link(state) => write!(f, "link: {}", state),
+            SessionEvent::ConnectFailed(reason) => write!(f, "connect failed: {}", reason),
+            SessionEvent::Timeout(expired) => write!(f, "{}", expired),
+            SessionEvent::OptionFailed(reason) => write!(f, "socket option failed: {}", reason),
+            SessionEvent::Unsent { bytes, queued: true } => {
+                write!(f, "not connected: queued {} bytes until reconnected", bytes)
+            }
+            SessionEvent::Unsent { bytes, queued: false } => {
+                write!(f, "not connected: dropped {} bytes", bytes)
+            }
+            SessionEvent::LocalClose(ConnectionControl::Shutdown) => write!(f, "write side closed (FIN sent), still reading"),
+            SessionEvent::LocalClose(ConnectionControl::Abort) => write!(f, "connection aborted (RST sent)"),
+            SessionEvent::LocalClose(_) => write!(f, "connection closed by local side"),
+            SessionEvent::WriteClosed { bytes } => write!(f, "write side closed: dropped {} bytes", bytes),
+            SessionEvent::Resolved(resolved) => write!(f, "{}", resolved),
+            SessionEvent::PeerCredentials(credentials) => write!(f, "peer: {}", credentials),
+            SessionEvent::Proxy(event) => write!(f, "proxy: {}", event),
+            SessionEvent::Fault(event) => write!(f, "{}", event),
+            SessionEvent::Tunnel(step) => write!(f, "{}", step),
+            SessionEvent::FrameTooLong(error) => write!(f, "{}", error),
+            SessionEvent::IoError(reason) => write!(f, "I/O error: {}", reason),
+        }
+    }
+}
+
+impl MessageType {
+    /// 根据收到的原始数据构造消息: 合法 UTF-8 作为文本，否则作为二进制
+    pub fn from_payload(data: Bytes) -> Self {
+        match std::str::from_utf8(&data) {
+            Ok(text) => MessageType::Text(text.to_string()),
+            Err(_) => MessageType::Binary(data),
+        }
+    }
+
+    /// 获取消息负载的原始字节，非数据类消息或非法十六进制返回 None
+    pub fn payload(&self) -> Option<Bytes> {
+        match self {
+            MessageType::Text(text) => Some(Bytes::from(text.clone().into_bytes())),
+            MessageType::Binary(bytes) | MessageType::Raw(bytes) => Some(bytes.clone()),
+            MessageType::Hex(hex_str) => hex_to_bytes(hex_str).ok().map(Bytes::from),
+            _ => None,
+        }
+    }
 }
 
 /// 消息方向
@@ -35,10 +222,28 @@ pub enum MessageDirection {
 /// 连接信息
 #[derive(Debug, Clone)]
 pub struct ConnectionInfo {
-    /// 远程地址
+    /// 远程地址，非 IP 连接 (Unix 套接字) 为未指定地址
     pub remote_addr: SocketAddr,
     /// 连接 ID (用于区分不同客户端)
     pub connection_id: String,
+    /// 本端地址，多地址监听时区分连接所属的监听地址；未知时为 None
+    pub local_addr: Option<SocketAddr>,
+}
+
+impl ConnectionInfo {
+    /// 非 IP 连接 (Unix 套接字) 的连接信息
+    pub fn non_ip(connection_id: String) -> Self {
+        Self {
+            remote_addr: SocketAddr::from(([0, 0, 0, 0], 0)),
+            connection_id,
+            local_addr: None,
+        }
+    }
+
+    /// 连接的地址族，非 IP 连接返回 None
+    pub fn family(&self) -> Option<IpFamily> {
+        (!self.remote_addr.ip().is_unspecified()).then(|| IpFamily::of(&self.remote_addr))
+    }
 }
 
 /// 消息
@@ -52,6 +257,43 @@ pub struct Message {
     pub timestamp: DateTime<Local>,
     /// 连接信息
     pub connection_info: Option<ConnectionInfo>,
+    /// 发送的消息写出后的通知
+    pub ack: WriteAck,
+}
+
+/// 发送的消息经某个连接实际写出的数据
+#[derive(Debug, Clone)]
+pub struct Written {
+    /// 写出的连接
+    pub connection_info: ConnectionInfo,
+    /// 线路上的数据 (已分帧，含故障注入的篡改)
+    pub frame: Bytes,
+    /// 写出时间
+    pub timestamp: DateTime<Local>,
+}
+
+/// 发送消息写出的通知: 消息每经一个连接写出就上报一次写出的数据；
+/// 消息被丢弃 (未连接、写入失败等) 且所有副本释放后，未再有其他消息共用的接收端随之关闭
+#[derive(Debug, Clone, Default)]
+pub struct WriteAck(Option<UnboundedSender<Written>>);
+
+impl WriteAck {
+    /// 创建通知及其接收端，可复制后供多条消息共用
+    pub fn new() -> (Self, UnboundedReceiver<Written>) {
+        let (tx, rx) = unbounded_channel();
+        (Self(Some(tx)), rx)
+    }
+
+    /// 上报消息已经由 `connection_info` 写出
+    pub fn written(&self, connection_info: &ConnectionInfo, frame: &Bytes) {
+        if let Some(tx) = &self.0 {
+            let _ = tx.send(Written {
+                connection_info: connection_info.clone(),
+                frame: frame.clone(),
+                timestamp: Local::now(),
+            });
+        }
+    }
 }
 
 impl Message {
@@ -62,6 +304,7 @@ impl Message {
             direction: MessageDirection::Received,
             timestamp: Local::now(),
             connection_info,
+            ack: WriteAck::default(),
         }
     }
 
@@ -72,10 +315,67 @@ impl Message {
             direction: MessageDirection::Sent,
             timestamp: Local::now(),
             connection_info,
+            ack: WriteAck::default(),
         }
     }
 }
 
+/// 会话选项，由命令行参数构建并传递给协议处理器
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    /// 流分帧配置
+    pub framing: FramingConfig,
+    /// 校验配置
+    pub checksum: Option<ChecksumConfig>,
+    /// 自动回复规则
+    pub auto_reply: AutoReplyRules,
+    /// 会话记录
+    pub capture: SessionCapture,
+    /// TCP 客户端自动重连策略，None 表示断开后结束会话
+    pub reconnect: Option<ReconnectPolicy>,
+    /// 连接断开期间发送的消息处理方式
+    pub offline: OfflineSend,
+    /// 连接超时配置
+    pub timeouts: Timeouts,
+    /// 套接字选项
+    pub socket: SocketOptions,
+    /// UDP 组播/广播模式，None 为普通 UDP 服务器
+    pub udp_group: Option<UdpGroup>,
+    /// 故障注入设置
+    pub faults: Faults,
+    /// TCP 客户端经由的代理隧道，此时远程地址为代理服务器的地址
+    pub tunnel: Option<Tunnel>,
+}
+
+impl SessionOptions {
+    /// 获取待发送消息的负载，十六进制/二进制消息按配置追加校验值 (不分帧)
+    pub fn outgoing_payload(&self, message: &MessageType) -> Option<Bytes> {
+        let payload = message.payload()?;
+        match (&self.checksum, message) {
+            (Some(checksum), MessageType::Binary(_) | MessageType::Hex(_)) if checksum.append => {
+                Some(Bytes::from(checksum.append_to(&payload)))
+            }
+            _ => Some(payload),
+        }
+    }
+
+    /// 待发送消息在线路上的帧: 负载追加校验值后按分帧方式封装，原始数据原样发送
+    ///
+    /// 非数据类消息返回 `Ok(None)`，负载超出长度字段范围时返回错误。
+    pub fn outgoing_frame(&self, message: &MessageType) -> Result<Option<Bytes>, FrameTooLong> {
+        match message {
+            MessageType::Raw(data) => Ok(Some(data.clone())),
+            message => self.outgoing_payload(message).map(|payload| self.framing.encode(&payload)).transpose(),
+        }
+    }
+
+    /// 校验收到的帧，校验失败时返回对应的会话事件
+    pub fn check_frame(&self, frame: &[u8]) -> Option<SessionEvent> {
+        let checksum = self.checksum.as_ref().filter(|c| c.verify)?;
+        checksum.verify_frame(frame).err().map(SessionEvent::ChecksumMismatch)
+    }
+}
+
 /// 通讯协议处理接口
 #[async_trait]
 pub trait ProtocolHandler {
@@ -109,29 +409,67 @@ pub async fn create_protocol_handler(
     protocol: &str,
     is_server: bool,
     server_to_ui_tx: Option<Sender<Message>>,
-    local_addr: SocketAddr,
-    remote_addr: Option<SocketAddr>,
+    local_addrs: Vec<SocketAddr>,
+    remote_addrs: Vec<SocketAddr>,
+    unix_path: Option<&str>,
+    options: SessionOptions,
 ) -> Result<Box<dyn ProtocolHandler + Send + Sync>> {
+    // 客户端只使用第一个本地地址
+    let local_addr = *local_addrs.first().ok_or_else(|| anyhow::anyhow!("Local address is required"))?;
     match (protocol.to_lowercase().as_str(), is_server) {
         ("tcp", true) => {
-            let mut handler = TcpServerHandler::new(local_addr);
+            let mut handler = TcpServerHandler::new(local_addrs, options);
             handler.set_server_to_ui_sender(server_to_ui_tx.unwrap());
             handler.start().await?;
             Ok(Box::new(handler))
         }
         ("tcp", false) => {
             // 创建 TCP 客户端处理器
-            let remote = remote_addr.ok_or_else(|| anyhow::anyhow!("TCP client requires remote address"))?;
-            let mut handler = crate::protocols::tcp::TcpClientHandler::new(local_addr, remote);
+            if remote_addrs.is_empty() {
+                anyhow::bail!("TCP client requires remote address");
+            }
+            let mut handler = crate::protocols::tcp::TcpClientHandler::new(local_addr, remote_addrs, options);
+            handler.set_server_to_ui_sender(server_to_ui_tx.ok_or_else(|| anyhow::anyhow!("Server to UI sender is required"))?);
+            handler.start().await?;
+            Ok(Box::new(handler))
+        }
+        ("tcp-proxy", _) => {
+            if remote_addrs.is_empty() {
+                anyhow::bail!("TCP proxy requires upstream address");
+            }
+            let mut handler = TcpProxyHandler::new(local_addrs, remote_addrs, options);
             handler.set_server_to_ui_sender(server_to_ui_tx.ok_or_else(|| anyhow::anyhow!("Server to UI sender is required"))?);
             handler.start().await?;
             Ok(Box::new(handler))
         }
         ("udp", true) => {
-            anyhow::bail!("UDP server handler not yet implemented")
+            let mut handler = UdpServerHandler::new(local_addrs, options);
+            handler.set_server_to_ui_sender(server_to_ui_tx.ok_or_else(|| anyhow::anyhow!("Server to UI sender is required"))?);
+            handler.start().await?;
+            Ok(Box::new(handler))
         }
         ("udp", false) => {
-            anyhow::bail!("UDP client handler not yet implemented")
+            // 数据报无需建立连接，只使用首选地址
+            let remote = remote_addrs.first().copied().ok_or_else(|| anyhow::anyhow!("UDP client requires remote address"))?;
+            let mut handler = UdpClientHandler::new(local_addr, remote, options);
+            handler.set_server_to_ui_sender(server_to_ui_tx.ok_or_else(|| anyhow::anyhow!("Server to UI sender is required"))?);
+            handler.start().await?;
+            Ok(Box::new(handler))
+        }
+        #[cfg(unix)]
+        ("unix" | "unixgram", _) => {
+            use crate::protocols::unix::*;
+            let path = UnixPath::parse(unix_path.ok_or_else(|| anyhow::anyhow!("Unix socket path is required"))?);
+            let stream = protocol.eq_ignore_ascii_case("unix");
+            let mut handler: Box<dyn ProtocolHandler + Send + Sync> = match (stream, is_server) {
+                (true, true) => Box::new(UnixStreamServerHandler::new(path, options)),
+                (true, false) => Box::new(UnixStreamClientHandler::new(path, options)),
+                (false, true) => Box::new(UnixDatagramServerHandler::new(path, options)),
+                (false, false) => Box::new(UnixDatagramClientHandler::new(path, options)),
+            };
+            handler.set_server_to_ui_sender(server_to_ui_tx.ok_or_else(|| anyhow::anyhow!("Server to UI sender is required"))?);
+            handler.start().await?;
+            Ok(handler)
         }
         ("websocket", true) => {
             anyhow::bail!("WebSocket server handler not yet implemented")
@@ -0,0 +1,306 @@
+use bytes::Bytes;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    time::Instant,
+};
+
+use crate::protocols::common::ConnectionControl;
+use crate::utils::random::random_unit;
+
+/// 故障注入配置，作用于发送方向
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FaultConfig {
+    /// 每次写入前增加的延迟
+    pub latency: Duration,
+    /// 延迟的随机抖动，实际延迟在 latency ± jitter 之间
+    pub jitter: Duration,
+    /// 带宽上限 (字节/秒)
+    pub bandwidth: Option<u64>,
+    /// 随机丢弃数据报的概率 (0~1，仅 UDP)
+    pub drop: f64,
+    /// 每个字节被随机篡改的概率 (0~1)
+    pub corrupt: f64,
+    /// 将写入拆分为不超过该字节数的小段 (仅 TCP，写入期间临时开启 TCP_NODELAY)
+    pub segment: Option<usize>,
+    /// 连接建立后经过该时间由本端关闭 (新连接生效)
+    pub close_after: Option<Duration>,
+    /// 本端关闭连接时发送 RST 而不是 FIN
+    pub abrupt_close: bool,
+}
+
+impl FaultConfig {
+    /// 写入前的等待时间: 延迟加上随机抖动
+    pub fn delay(&self) -> Duration {
+        let jitter = self.jitter.as_secs_f64() * (random_unit() * 2.0 - 1.0);
+        Duration::from_secs_f64((self.latency.as_secs_f64() + jitter).max(0.0))
+    }
+
+    /// 按带宽上限发送 `len` 字节需要的时间
+    pub fn transmit_time(&self, len: usize) -> Duration {
+        match self.bandwidth {
+            Some(bandwidth) if bandwidth > 0 => Duration::from_secs_f64(len as f64 / bandwidth as f64),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// 是否丢弃这个数据报
+    pub fn drops(&self) -> bool {
+        self.drop > 0.0 && random_unit() < self.drop
+    }
+
+    /// 按篡改概率随机翻转字节，返回篡改后的数据和被篡改的字节数
+    pub fn corrupt(&self, data: Bytes) -> (Bytes, usize) {
+        if self.corrupt <= 0.0 || data.is_empty() {
+            return (data, 0);
+        }
+        let mut rng = XorShift::new();
+        let mut bytes = data.to_vec();
+        let mut corrupted = 0;
+        for byte in &mut bytes {
+            if rng.unit() < self.corrupt {
+                // 至少翻转一位，保证数据确实被改变
+                *byte ^= (rng.next() as u8).max(1);
+                corrupted += 1;
+            }
+        }
+        (Bytes::from(bytes), corrupted)
+    }
+
+    /// 本端关闭连接实际使用的命令: 设置了 abrupt_close 时关闭改为中止 (RST)
+    pub fn close_control(&self, control: ConnectionControl) -> ConnectionControl {
+        match control {
+            ConnectionControl::Close if self.abrupt_close => ConnectionControl::Abort,
+            control => control,
+        }
+    }
+
+    /// 按 close_after 由本端关闭连接的时刻
+    pub fn close_deadline(&self) -> Option<Instant> {
+        self.close_after.map(|after| Instant::now() + after)
+    }
+}
+
+impl fmt::Display for FaultConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "latency={}±{}ms", self.latency.as_millis(), self.jitter.as_millis())?;
+        if let Some(bandwidth) = self.bandwidth {
+            write!(f, " bandwidth={}B/s", bandwidth)?;
+        }
+        write!(f, " drop={:.1}% corrupt={:.1}%", self.drop * 100.0, self.corrupt * 100.0)?;
+        if let Some(segment) = self.segment {
+            write!(f, " segment={}", segment)?;
+        }
+        if let Some(after) = self.close_after {
+            write!(f, " close-after={}s", after.as_secs())?;
+        }
+        if self.abrupt_close {
+            write!(f, " rst")?;
+        }
+        Ok(())
+    }
+}
+
+/// 故障注入对一次写入的影响
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultEvent {
+    /// 数据报被丢弃
+    Dropped { bytes: usize },
+    /// `corrupted` 个字节被篡改
+    Corrupted { corrupted: usize, bytes: usize },
+    /// 按 close_after 关闭了连接
+    Closed { after: Duration },
+}
+
+impl fmt::Display for FaultEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultEvent::Dropped { bytes } => write!(f, "fault: dropped {} bytes", bytes),
+            FaultEvent::Corrupted { corrupted, bytes } => write!(f, "fault: corrupted {} of {} bytes", corrupted, bytes),
+            FaultEvent::Closed { after } => write!(f, "fault: closed after {:.1}s", after.as_secs_f64()),
+        }
+    }
+}
+
+/// 故障注入设置，在UI和各协议处理器之间共享，运行时修改立即生效；可为单个连接单独设置
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    state: Arc<RwLock<FaultState>>,
+}
+
+#[derive(Debug, Default)]
+struct FaultState {
+    session: FaultConfig,
+    connections: HashMap<String, FaultConfig>,
+}
+
+impl Faults {
+    pub fn new(session: FaultConfig) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(FaultState {
+                session,
+                connections: HashMap::new(),
+            })),
+        }
+    }
+
+    /// 连接 (None 为整个会话) 生效的设置，连接没有单独设置时使用会话设置
+    pub fn get(&self, connection_id: Option<&str>) -> FaultConfig {
+        let Ok(state) = self.state.read() else {
+            return FaultConfig::default();
+        };
+        connection_id
+            .and_then(|id| state.connections.get(id))
+            .copied()
+            .unwrap_or(state.session)
+    }
+
+    /// 修改连接 (None 为整个会话) 的设置，连接首次单独设置时以会话设置为起点
+    pub fn update(&self, connection_id: Option<&str>, change: impl FnOnce(&mut FaultConfig)) {
+        let Ok(mut state) = self.state.write() else {
+            return;
+        };
+        match connection_id {
+            Some(id) => {
+                let session = state.session;
+                change(state.connections.entry(id.to_string()).or_insert(session));
+            }
+            None => change(&mut state.session),
+        }
+    }
+
+    /// 连接关闭后清除其单独设置
+    pub fn forget(&self, connection_id: &str) {
+        if let Ok(mut state) = self.state.write() {
+            state.connections.remove(connection_id);
+        }
+    }
+}
+
+/// 按故障注入设置写入流: 等待延迟后篡改数据，按分段大小拆分并按带宽逐段写入
+///
+/// 返回实际写出的数据和需要提示的故障事件
+pub async fn write_stream<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    fault: &FaultConfig,
+    data: Bytes,
+) -> std::io::Result<(Bytes, Option<FaultEvent>)> {
+    if *fault == FaultConfig::default() {
+        writer.write_all(&data).await?;
+        return Ok((data, None));
+    }
+
+    let delay = fault.delay();
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    let bytes = data.len();
+    let (data, corrupted) = fault.corrupt(data);
+
+    // 只限速时按约 50ms 的数据量分段，避免整块突发
+    let segment = match (fault.segment, fault.bandwidth) {
+        (Some(segment), _) => segment.max(1),
+        (None, Some(bandwidth)) => (bandwidth as usize / 20).max(1),
+        (None, None) => data.len().max(1),
+    };
+    for chunk in data.chunks(segment) {
+        let transmit = fault.transmit_time(chunk.len());
+        if !transmit.is_zero() {
+            tokio::time::sleep(transmit).await;
+        }
+        writer.write_all(chunk).await?;
+        writer.flush().await?;
+    }
+    let event = (corrupted > 0).then_some(FaultEvent::Corrupted { corrupted, bytes });
+    Ok((data, event))
+}
+
+/// 按故障注入设置处理一个数据报: 等待延迟和发送时间，随机丢弃或篡改；丢弃时返回 None
+pub async fn impair_datagram(fault: &FaultConfig, data: Bytes) -> (Option<Bytes>, Option<FaultEvent>) {
+    if *fault == FaultConfig::default() {
+        return (Some(data), None);
+    }
+
+    let wait = fault.delay() + fault.transmit_time(data.len());
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+    let bytes = data.len();
+    if fault.drops() {
+        return (None, Some(FaultEvent::Dropped { bytes }));
+    }
+    let (data, corrupted) = fault.corrupt(data);
+    (Some(data), (corrupted > 0).then_some(FaultEvent::Corrupted { corrupted, bytes }))
+}
+
+/// 篡改字节用的快速随机数发生器
+struct XorShift(u64);
+
+impl XorShift {
+    fn new() -> Self {
+        Self(((random_unit() * u64::MAX as f64) as u64) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// [0, 1) 之间的随机数
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corrupt_and_close_control() {
+        let data = Bytes::from_static(b"hello world");
+        let all = FaultConfig { corrupt: 1.0, ..Default::default() };
+        let (corrupted, count) = all.corrupt(data.clone());
+        assert_eq!(count, data.len());
+        assert!(corrupted.iter().zip(data.iter()).all(|(a, b)| a != b));
+        assert_eq!(FaultConfig::default().corrupt(data.clone()), (data, 0));
+
+        let abrupt = FaultConfig { abrupt_close: true, ..Default::default() };
+        assert_eq!(abrupt.close_control(ConnectionControl::Close), ConnectionControl::Abort);
+        assert_eq!(abrupt.close_control(ConnectionControl::Shutdown), ConnectionControl::Shutdown);
+        assert_eq!(FaultConfig::default().close_control(ConnectionControl::Close), ConnectionControl::Close);
+    }
+
+    #[test]
+    fn test_per_connection_override() {
+        let faults = Faults::new(FaultConfig { latency: Duration::from_millis(100), ..Default::default() });
+        faults.update(Some("a"), |config| config.drop = 0.5);
+        assert_eq!(faults.get(Some("a")).latency, Duration::from_millis(100));
+        assert_eq!(faults.get(Some("a")).drop, 0.5);
+        assert_eq!(faults.get(Some("b")).drop, 0.0);
+
+        faults.forget("a");
+        assert_eq!(faults.get(Some("a")).drop, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_write_stream_segmented() {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let fault = FaultConfig { segment: Some(3), ..Default::default() };
+        let (written, event) = write_stream(&mut client, &fault, Bytes::from_static(b"abcdefgh")).await.unwrap();
+        assert_eq!(&written[..], b"abcdefgh");
+        assert_eq!(event, None);
+        drop(client);
+
+        let mut received = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut server, &mut received).await.unwrap();
+        assert_eq!(received, b"abcdefgh");
+    }
+}
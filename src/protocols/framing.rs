@@ -0,0 +1,481 @@
+use bytes::{Bytes, BytesMut};
+use std::{fmt, io, str::FromStr, time::Duration};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::utils::data_format::{hex_to_bytes, read_uint, write_uint, Endian};
+
+/// 单帧最大长度，超过后缓冲区内容将被直接作为一帧上报，避免错误的长度字段耗尽内存
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// 单次读取的缓冲区大小
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// 分帧方式
+///
+/// 解码出的帧只保留负载，去掉分隔符、长度字段等分帧信息，与 [`FramingConfig::encode`] 互为逆过程:
+/// 对完整的帧重新封装即得到线路上的原始数据。
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FramingMode {
+    /// 不分帧，每次 read 返回的数据作为一条消息 (配合空闲间隔时按间隔合并)
+    #[default]
+    Raw,
+    /// 以分隔符结尾的帧，上报时去掉分隔符
+    Delimiter(Vec<u8>),
+    /// 长度前缀帧，上报时去掉长度字段 (保留长度字段之前的头部)
+    LengthPrefix {
+        /// 长度字段宽度 (1/2/4/8 字节)
+        width: usize,
+        /// 长度字段字节序
+        endian: Endian,
+        /// 长度值是否包含头部和长度字段本身
+        inclusive: bool,
+        /// 长度字段之前的头部字节数
+        offset: usize,
+    },
+    /// 固定长度记录
+    FixedSize(usize),
+}
+
+impl FromStr for FramingMode {
+    type Err = String;
+
+    /// 解析分帧描述，支持以下格式:
+    /// `raw`、`line`、`delim:<hex>`、`len:<width>[be|le][,incl][,offset=N]`、`fixed:<n>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (kind, spec) = s.split_once(':').unwrap_or((s, ""));
+        match kind.to_ascii_lowercase().as_str() {
+            "raw" | "none" => Ok(FramingMode::Raw),
+            "line" => Ok(FramingMode::Delimiter(b"\n".to_vec())),
+            "delim" => {
+                let delimiter = hex_to_bytes(spec)?;
+                if delimiter.is_empty() {
+                    return Err("Delimiter must not be empty".to_string());
+                }
+                Ok(FramingMode::Delimiter(delimiter))
+            }
+            "len" => {
+                let mut parts = spec.split(',');
+                let width_spec = parts.next().unwrap_or("").to_ascii_lowercase();
+                let (digits, endian) = if let Some(d) = width_spec.strip_suffix("le") {
+                    (d, Endian::Little)
+                } else {
+                    (width_spec.strip_suffix("be").unwrap_or(&width_spec), Endian::Big)
+                };
+                let width: usize = digits
+                    .parse()
+                    .map_err(|_| format!("Invalid length width: {}", width_spec))?;
+                if ![1, 2, 4, 8].contains(&width) {
+                    return Err(format!("Length width must be 1, 2, 4 or 8, got {}", width));
+                }
+
+                let mut inclusive = false;
+                let mut offset = 0;
+                for option in parts {
+                    match option.split_once('=') {
+                        None if option == "incl" => inclusive = true,
+                        None if option == "excl" => inclusive = false,
+                        Some(("offset", value)) => {
+                            offset = value.parse().map_err(|_| format!("Invalid offset: {}", value))?;
+                        }
+                        _ => return Err(format!("Unknown length-prefix option: {}", option)),
+                    }
+                }
+
+                Ok(FramingMode::LengthPrefix {
+                    width,
+                    endian,
+                    inclusive,
+                    offset,
+                })
+            }
+            "fixed" => {
+                let size: usize = spec.parse().map_err(|_| format!("Invalid record size: {}", spec))?;
+                if size == 0 {
+                    return Err("Record size must be greater than 0".to_string());
+                }
+                Ok(FramingMode::FixedSize(size))
+            }
+            other => Err(format!("Unknown framing mode: {}", other)),
+        }
+    }
+}
+
+/// 分帧配置
+#[derive(Debug, Clone, Default)]
+pub struct FramingConfig {
+    /// 分帧方式
+    pub mode: FramingMode,
+    /// 空闲间隔: 超过该时间没有新数据时，缓冲中的剩余数据作为一帧上报
+    pub idle_gap: Option<Duration>,
+}
+
+/// 待发送数据的长度超出长度字段能表示的范围，数据未发送
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTooLong {
+    /// 需要写入长度字段的值
+    pub length: u64,
+    /// 长度字段能表示的最大值
+    pub max: u64,
+}
+
+impl fmt::Display for FrameTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame length {} exceeds length field maximum {}, not sent", self.length, self.max)
+    }
+}
+
+impl FramingConfig {
+    /// 按分帧方式封装待发送的数据，长度超出长度字段范围时返回错误
+    pub fn encode(&self, payload: &[u8]) -> Result<Bytes, FrameTooLong> {
+        let frame = match &self.mode {
+            FramingMode::Raw => Bytes::copy_from_slice(payload),
+            FramingMode::Delimiter(delimiter) => {
+                // 负载本身以分隔符结尾时也追加，否则解码时会与下一帧合并
+                let mut frame = payload.to_vec();
+                frame.extend_from_slice(delimiter);
+                Bytes::from(frame)
+            }
+            FramingMode::LengthPrefix {
+                width,
+                endian,
+                inclusive,
+                offset,
+            } => {
+                // 负载的前 offset 字节视为头部，长度字段插入在头部之后
+                let header_len = (*offset).min(payload.len());
+                let body = &payload[header_len..];
+                let length = if *inclusive {
+                    header_len + width + body.len()
+                } else {
+                    body.len()
+                } as u64;
+                let max = u64::MAX >> (64 - 8 * width);
+                if length > max {
+                    return Err(FrameTooLong { length, max });
+                }
+
+                let mut frame = Vec::with_capacity(header_len + width + body.len());
+                frame.extend_from_slice(&payload[..header_len]);
+                frame.extend_from_slice(&write_uint(length, *width, *endian));
+                frame.extend_from_slice(body);
+                Bytes::from(frame)
+            }
+            FramingMode::FixedSize(size) => {
+                // 最后一条记录不足时补零
+                let mut frame = payload.to_vec();
+                let remainder = frame.len() % size;
+                if remainder != 0 || frame.is_empty() {
+                    frame.resize(frame.len() + size - remainder, 0);
+                }
+                Bytes::from(frame)
+            }
+        };
+        Ok(frame)
+    }
+
+    /// 未经解码的数据 (如整个数据报): 不分帧时即为负载，否则原样上报
+    pub fn undecoded(&self, data: Bytes) -> Frame {
+        match self.mode {
+            FramingMode::Raw => Frame::Payload(data),
+            _ => Frame::Wire(data),
+        }
+    }
+}
+
+/// 解码器上报的一段接收数据
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    /// 完整的帧，分帧已去掉，只含负载
+    Payload(Bytes),
+    /// 未能按分帧方式解析的线路数据 (不完整的帧、长度超限等)，原样上报
+    Wire(Bytes),
+}
+
+impl Frame {
+    /// 帧在线路上的原始数据，完整的帧按分帧方式重新封装即得到 (与解码互为逆过程)
+    pub fn wire(&self, config: &FramingConfig) -> Bytes {
+        match self {
+            Frame::Payload(payload) => config.encode(payload).unwrap_or_else(|_| payload.clone()),
+            Frame::Wire(data) => data.clone(),
+        }
+    }
+}
+
+/// 流式分帧解码器，每个连接持有一个
+pub struct FrameDecoder {
+    /// 分帧配置
+    config: FramingConfig,
+    /// 尚未组成完整帧的数据
+    buffer: BytesMut,
+    /// 缓冲区开头已确认不含分隔符的字节数，避免每次读取都从头查找
+    scanned: usize,
+    /// 读取缓冲区
+    read_buf: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new(config: FramingConfig) -> Self {
+        Self {
+            config,
+            buffer: BytesMut::new(),
+            scanned: 0,
+            read_buf: vec![0u8; READ_BUFFER_SIZE],
+        }
+    }
+
+    /// 是否有尚未组成完整帧的数据
+    pub fn has_pending(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// 输入新数据，返回其中所有完整的帧
+    pub fn decode(&mut self, data: &[u8]) -> Vec<Frame> {
+        self.buffer.extend_from_slice(data);
+        let mut frames = Vec::new();
+
+        loop {
+            let frame = match &self.config.mode {
+                FramingMode::Raw => {
+                    // 配置了空闲间隔时等待间隔到期再整体上报
+                    if self.config.idle_gap.is_some() || self.buffer.is_empty() {
+                        None
+                    } else {
+                        Some(Frame::Payload(self.buffer.split().freeze()))
+                    }
+                }
+                FramingMode::Delimiter(delimiter) => {
+                    let found = self.buffer[self.scanned..]
+                        .windows(delimiter.len())
+                        .position(|w| w == delimiter.as_slice());
+                    match found {
+                        Some(pos) => {
+                            let frame = self.buffer.split_to(self.scanned + pos).freeze();
+                            let _ = self.buffer.split_to(delimiter.len());
+                            self.scanned = 0;
+                            Some(Frame::Payload(frame))
+                        }
+                        None => {
+                            // 末尾可能是分隔符的前半部分，下次从这里继续查找
+                            self.scanned = self.buffer.len().saturating_sub(delimiter.len() - 1);
+                            None
+                        }
+                    }
+                }
+                FramingMode::LengthPrefix {
+                    width,
+                    endian,
+                    inclusive,
+                    offset,
+                } => {
+                    let header_end = offset + width;
+                    if self.buffer.len() < header_end {
+                        None
+                    } else {
+                        let length = read_uint(&self.buffer[*offset..header_end], *endian) as usize;
+                        let total = if *inclusive { length } else { header_end.saturating_add(length) };
+                        if total > MAX_FRAME_LEN || total < header_end {
+                            // 长度字段不可信 (超限或小于头部)，丢弃分帧状态，将缓冲数据原样上报
+                            Some(Frame::Wire(self.buffer.split().freeze()))
+                        } else if self.buffer.len() >= total {
+                            // 去掉长度字段，头部和帧体拼接为负载
+                            let mut frame = self.buffer.split_to(total);
+                            let body = frame.split_off(header_end);
+                            frame.truncate(*offset);
+                            frame.unsplit(body);
+                            Some(Frame::Payload(frame.freeze()))
+                        } else {
+                            None
+                        }
+                    }
+                }
+                FramingMode::FixedSize(size) => {
+                    if self.buffer.len() >= *size {
+                        Some(Frame::Payload(self.buffer.split_to(*size).freeze()))
+                    } else {
+                        None
+                    }
+                }
+            };
+
+            match frame {
+                Some(frame) => frames.push(frame),
+                None => break,
+            }
+        }
+
+        if self.buffer.len() > MAX_FRAME_LEN {
+            self.scanned = 0;
+            frames.push(Frame::Wire(self.buffer.split().freeze()));
+        }
+
+        frames
+    }
+
+    /// 取出缓冲中的剩余数据 (空闲间隔到期或连接关闭时调用)
+    ///
+    /// 不分帧时剩余数据即为负载，其他分帧方式下是不完整的帧，原样上报。
+    pub fn flush(&mut self) -> Option<Frame> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            self.scanned = 0;
+            Some(self.config.undecoded(self.buffer.split().freeze()))
+        }
+    }
+
+    /// 从读取端读取数据并返回完整的帧
+    ///
+    /// 配置了空闲间隔且缓冲中有数据时，间隔到期会返回剩余数据组成的帧。
+    /// 返回 `Ok(None)` 表示对端已关闭，调用方应再调用 `flush` 取出剩余数据。
+    pub async fn read_frames<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> io::Result<Option<Vec<Frame>>> {
+        let read_result = match self.config.idle_gap {
+            Some(gap) if self.has_pending() => {
+                match tokio::time::timeout(gap, reader.read(&mut self.read_buf)).await {
+                    Ok(result) => result,
+                    Err(_) => return Ok(Some(self.flush().into_iter().collect())),
+                }
+            }
+            _ => reader.read(&mut self.read_buf).await,
+        };
+
+        match read_result? {
+            0 => Ok(None),
+            n => {
+                let data = self.read_buf[..n].to_vec();
+                Ok(Some(self.decode(&data)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_decoder(mode: &str) -> FrameDecoder {
+        FrameDecoder::new(FramingConfig {
+            mode: mode.parse().unwrap(),
+            idle_gap: None,
+        })
+    }
+
+    #[test]
+    fn test_delimiter_split_and_merge() {
+        let mut decoder = new_decoder("line");
+        assert!(decoder.decode(b"hel").is_empty());
+        let frames = decoder.decode(b"lo\nwor");
+        assert_eq!(frames, vec![Frame::Payload(Bytes::from_static(b"hello"))]);
+        let frames = decoder.decode(b"ld\nagain\n");
+        assert_eq!(
+            frames,
+            vec![Frame::Payload(Bytes::from_static(b"world")), Frame::Payload(Bytes::from_static(b"again"))]
+        );
+        assert!(!decoder.has_pending());
+
+        // 分隔符跨两次读取
+        let mut decoder = new_decoder("delim:0d0a");
+        assert!(decoder.decode(b"abc\r").is_empty());
+        assert!(decoder.decode(b"").is_empty());
+        let frames = decoder.decode(b"\nd");
+        assert_eq!(frames, vec![Frame::Payload(Bytes::from_static(b"abc"))]);
+        assert_eq!(decoder.flush(), Some(Frame::Wire(Bytes::from_static(b"d"))));
+    }
+
+    #[test]
+    fn test_length_prefix() {
+        let mut decoder = new_decoder("len:2be");
+        let frames = decoder.decode(&[0x00, 0x02, b'a', b'b', 0x00, 0x01]);
+        assert_eq!(frames, vec![Frame::Payload(Bytes::from_static(b"ab"))]);
+        let frames = decoder.decode(b"c");
+        assert_eq!(frames, vec![Frame::Payload(Bytes::from_static(b"c"))]);
+
+        // 小端、包含头部的长度，长度字段前有 1 字节头部
+        let mut decoder = new_decoder("len:2le,incl,offset=1");
+        let frames = decoder.decode(&[0xAA, 0x05, 0x00, b'x', b'y', 0xBB]);
+        assert_eq!(frames, vec![Frame::Payload(Bytes::from_static(&[0xAA, b'x', b'y']))]);
+        assert!(decoder.has_pending());
+        // 不完整的帧原样上报，不能当作负载再次封装
+        assert_eq!(decoder.flush(), Some(Frame::Wire(Bytes::from_static(&[0xBB]))));
+
+        // 包含头部的长度小于头部时长度字段不可信
+        let mut decoder = new_decoder("len:1,incl");
+        let frames = decoder.decode(&[0x00, b'a']);
+        assert_eq!(frames, vec![Frame::Wire(Bytes::from_static(&[0x00, b'a']))]);
+    }
+
+    #[test]
+    fn test_decode_inverts_encode() {
+        for mode in ["line", "delim:0d0a", "len:1", "len:4le,incl,offset=2", "fixed:4"] {
+            let config = FramingConfig {
+                mode: mode.parse().unwrap(),
+                idle_gap: None,
+            };
+            // 负载以分隔符结尾时也要能还原
+            let mut wire = config.encode(b"\x01\x02payload").unwrap().to_vec();
+            wire.extend_from_slice(&config.encode(b"tail\r\n").unwrap());
+            let mut decoder = FrameDecoder::new(config.clone());
+            let frames = decoder.decode(&wire);
+            let reencoded: Vec<u8> = frames
+                .iter()
+                .flat_map(|frame| match frame {
+                    Frame::Payload(payload) => config.encode(payload).unwrap().to_vec(),
+                    Frame::Wire(data) => data.to_vec(),
+                })
+                .collect();
+            assert_eq!(reencoded, wire, "{}", mode);
+        }
+    }
+
+    #[test]
+    fn test_fixed_size() {
+        let mut decoder = new_decoder("fixed:3");
+        let frames = decoder.decode(b"abcdefg");
+        assert_eq!(
+            frames,
+            vec![Frame::Payload(Bytes::from_static(b"abc")), Frame::Payload(Bytes::from_static(b"def"))]
+        );
+        assert_eq!(decoder.flush(), Some(Frame::Wire(Bytes::from_static(b"g"))));
+
+        // 不分帧时剩余数据即为负载
+        let mut decoder = FrameDecoder::new(FramingConfig {
+            mode: FramingMode::Raw,
+            idle_gap: Some(Duration::from_millis(10)),
+        });
+        assert!(decoder.decode(b"abc").is_empty());
+        assert_eq!(decoder.flush(), Some(Frame::Payload(Bytes::from_static(b"abc"))));
+    }
+
+    #[test]
+    fn test_encode() {
+        let config = |mode: &str| FramingConfig {
+            mode: mode.parse().unwrap(),
+            idle_gap: None,
+        };
+        assert_eq!(config("delim:0d0a").encode(b"hi").unwrap(), Bytes::from_static(b"hi\r\n"));
+        assert_eq!(config("line").encode(b"hi\n").unwrap(), Bytes::from_static(b"hi\n\n"));
+        assert_eq!(config("len:1").encode(b"hi").unwrap(), Bytes::from_static(&[0x02, b'h', b'i']));
+        assert_eq!(
+            config("len:2be,incl,offset=1").encode(&[0xAA, b'h']).unwrap(),
+            Bytes::from_static(&[0xAA, 0x00, 0x04, b'h'])
+        );
+        assert_eq!(config("fixed:4").encode(b"hi").unwrap(), Bytes::from_static(&[b'h', b'i', 0, 0]));
+
+        // 长度超出长度字段范围时不能截断
+        assert_eq!(config("len:1").encode(&[0u8; 255]).unwrap().len(), 256);
+        assert_eq!(config("len:1").encode(&[0u8; 256]), Err(FrameTooLong { length: 256, max: 255 }));
+        assert_eq!(
+            config("len:1,incl").encode(&[0u8; 255]),
+            Err(FrameTooLong { length: 256, max: 255 })
+        );
+        assert!(config("len:8").encode(&[0u8; 256]).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        assert!("len:3".parse::<FramingMode>().is_err());
+        assert!("fixed:0".parse::<FramingMode>().is_err());
+        assert!("delim:".parse::<FramingMode>().is_err());
+        assert!("bogus".parse::<FramingMode>().is_err());
+    }
+}
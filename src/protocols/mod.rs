@@ -1,10 +1,23 @@
+pub mod autoreply;
 pub mod common;
+pub mod fault;
+pub mod framing;
+pub mod multicast;
+pub mod proxy;
+pub mod reconnect;
+pub mod resolve;
+pub mod session;
+pub mod sockopt;
 pub mod tcp;
+pub mod timeout;
+pub mod tunnel;
 pub mod udp;
+#[cfg(unix)]
+pub mod unix;
 pub mod websocket;
 pub mod http;
 pub mod http2;
 pub mod http3;
 
 // 重新导出常用的类型
-pub use common::{ProtocolHandler, Message, MessageDirection, MessageType, ConnectionInfo};
\ No newline at end of file
+pub use common::{ProtocolHandler, Message, MessageDirection, MessageType, ConnectionControl, ConnectionInfo, LinkState, SessionEvent, SessionOptions};
\ No newline at end of file
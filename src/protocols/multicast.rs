@@ -0,0 +1,326 @@
+use anyhow::{Context, Result};
+use socket2::{InterfaceIndexOrAddress, Socket};
+use std::{
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+};
+
+use crate::protocols::resolve::{self, HostPort};
+
+/// UDP 组播配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Multicast {
+    /// 组播组地址和端口，IPv6 地址的区域序号在未指定接口时作为加入的接口
+    pub group: SocketAddr,
+    /// 网络接口: 接口名、序号或 IPv4 地址，None 时由系统选择
+    pub iface: Option<String>,
+    /// 源特定组播 (SSM) 的源地址，只接收该地址发往组的数据
+    pub source: Option<IpAddr>,
+    /// 发送组播的 TTL (IPv6 为跳数限制)
+    pub ttl: Option<u32>,
+    /// 是否收到本机发往组的数据 (IP_MULTICAST_LOOP)
+    pub loopback: Option<bool>,
+}
+
+/// UDP 组播或广播模式，由 UDP 服务器处理器在绑定后设置到套接字上
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UdpGroup {
+    /// 加入组播组，未指定目标的消息发往组地址
+    Multicast(Multicast),
+    /// 开启 SO_BROADCAST，未指定目标的消息发往广播地址
+    Broadcast(SocketAddr),
+}
+
+/// 组播使用的网络接口
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Interface {
+    Index(u32),
+    Addr(Ipv4Addr),
+}
+
+impl Multicast {
+    /// 解析组播组 GROUP:PORT 或 [GROUP%IFACE]:PORT
+    pub fn parse_group(s: &str) -> Result<SocketAddr> {
+        let target = HostPort::parse(s, "").map_err(anyhow::Error::msg)?;
+        let group = target
+            .literal()?
+            .with_context(|| format!("Multicast group must be an IP address: {}", s))?;
+        if !group.ip().is_multicast() {
+            anyhow::bail!("Not a multicast address: {}", group.ip());
+        }
+        Ok(group)
+    }
+
+    /// 接收组播的监听地址: 与组地址族相同的未指定地址加组端口
+    pub fn listen_addr(&self) -> SocketAddr {
+        let ip = match self.group {
+            SocketAddr::V4(_) => IpAddr::from(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::from(std::net::Ipv6Addr::UNSPECIFIED),
+        };
+        SocketAddr::new(ip, self.group.port())
+    }
+
+    fn interface(&self) -> Result<Option<Interface>> {
+        match &self.iface {
+            Some(iface) => match iface.parse::<Ipv4Addr>() {
+                Ok(addr) => Ok(Some(Interface::Addr(addr))),
+                Err(_) => Ok(Some(Interface::Index(resolve::scope_id(iface)?))),
+            },
+            None => match self.group {
+                SocketAddr::V6(group) if group.scope_id() != 0 => Ok(Some(Interface::Index(group.scope_id()))),
+                _ => Ok(None),
+            },
+        }
+    }
+
+    /// 加入或离开组播组
+    fn membership(&self, socket: &Socket, join: bool) -> Result<()> {
+        let iface = self.interface()?;
+        let result = match (self.group.ip(), self.source, iface) {
+            (IpAddr::V4(group), None, iface) => {
+                let iface = match iface {
+                    Some(Interface::Index(index)) => InterfaceIndexOrAddress::Index(index),
+                    Some(Interface::Addr(addr)) => InterfaceIndexOrAddress::Address(addr),
+                    None => InterfaceIndexOrAddress::Address(Ipv4Addr::UNSPECIFIED),
+                };
+                match join {
+                    true => socket.join_multicast_v4_n(&group, &iface),
+                    false => socket.leave_multicast_v4_n(&group, &iface),
+                }
+            }
+            (IpAddr::V4(group), Some(IpAddr::V4(source)), Some(Interface::Addr(_)) | None) => {
+                let iface = match iface {
+                    Some(Interface::Addr(addr)) => addr,
+                    _ => Ipv4Addr::UNSPECIFIED,
+                };
+                match join {
+                    true => socket.join_ssm_v4(&source, &group, &iface),
+                    false => socket.leave_ssm_v4(&source, &group, &iface),
+                }
+            }
+            (IpAddr::V6(_), _, Some(Interface::Addr(addr))) => {
+                anyhow::bail!("IPv6 multicast needs an interface name or index, not {}", addr)
+            }
+            (IpAddr::V6(group), None, iface) => {
+                let index = match iface {
+                    Some(Interface::Index(index)) => index,
+                    _ => 0,
+                };
+                match join {
+                    true => socket.join_multicast_v6(&group, index),
+                    false => socket.leave_multicast_v6(&group, index),
+                }
+            }
+            (group, Some(source), iface) if group.is_ipv4() == source.is_ipv4() => {
+                let index = match iface {
+                    Some(Interface::Index(index)) => index,
+                    _ => 0,
+                };
+                source_group(socket, index, source, group, join)
+            }
+            (group, Some(source), _) => {
+                anyhow::bail!("Source {} and group {} are in different address families", source, group)
+            }
+        };
+        let action = if join { "join" } else { "leave" };
+        result.with_context(|| format!("Cannot {} multicast group {}", action, self))
+    }
+
+    /// 设置发送组播的接口、TTL 和回环
+    fn apply_send_options(&self, socket: &Socket) -> Result<()> {
+        let iface = self.interface()?;
+        match self.group {
+            SocketAddr::V4(_) => {
+                if let Some(ttl) = self.ttl {
+                    socket.set_multicast_ttl_v4(ttl)?;
+                }
+                if let Some(loopback) = self.loopback {
+                    socket.set_multicast_loop_v4(loopback)?;
+                }
+                match iface {
+                    Some(Interface::Addr(addr)) => socket.set_multicast_if_v4(&addr)?,
+                    Some(Interface::Index(index)) => multicast_if_index_v4(socket, index)?,
+                    None => {}
+                }
+            }
+            SocketAddr::V6(_) => {
+                if let Some(hops) = self.ttl {
+                    socket.set_multicast_hops_v6(hops)?;
+                }
+                if let Some(loopback) = self.loopback {
+                    socket.set_multicast_loop_v6(loopback)?;
+                }
+                if let Some(Interface::Index(index)) = iface {
+                    socket.set_multicast_if_v6(index)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Multicast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.source {
+            Some(source) => write!(f, "({}, {})", source, HostPort::from(self.group))?,
+            None => write!(f, "{}", HostPort::from(self.group))?,
+        }
+        if let Some(iface) = &self.iface {
+            write!(f, " on {}", iface)?;
+        }
+        Ok(())
+    }
+}
+
+impl UdpGroup {
+    /// 未指定目标的消息发往的地址
+    pub fn destination(&self) -> SocketAddr {
+        match self {
+            UdpGroup::Multicast(multicast) => multicast.group,
+            UdpGroup::Broadcast(addr) => *addr,
+        }
+    }
+
+    /// 绑定后设置到套接字上: 加入组播组并设置发送参数，或开启 SO_BROADCAST
+    pub fn join(&self, socket: &Socket) -> Result<()> {
+        match self {
+            UdpGroup::Multicast(multicast) => {
+                multicast.membership(socket, true)?;
+                multicast.apply_send_options(socket)
+            }
+            UdpGroup::Broadcast(_) => socket.set_broadcast(true).context("Cannot enable SO_BROADCAST"),
+        }
+    }
+
+    /// 停止时离开组播组
+    pub fn leave(&self, socket: &Socket) -> Result<()> {
+        match self {
+            UdpGroup::Multicast(multicast) => multicast.membership(socket, false),
+            UdpGroup::Broadcast(_) => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for UdpGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UdpGroup::Multicast(multicast) => write!(f, "multicast {}", multicast),
+            UdpGroup::Broadcast(addr) => write!(f, "broadcast {}", addr),
+        }
+    }
+}
+
+/// 按接口序号加入或离开源特定组播组 (MCAST_JOIN_SOURCE_GROUP，IPv4 和 IPv6 通用)
+#[cfg(target_os = "linux")]
+fn source_group(socket: &Socket, index: u32, source: IpAddr, group: IpAddr, join: bool) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    fn storage(ip: IpAddr) -> libc::sockaddr_storage {
+        let addr = socket2::SockAddr::from(SocketAddr::new(ip, 0));
+        // SAFETY: sockaddr_storage 全零是有效值，复制的长度不超过 SockAddr 内部的存储
+        unsafe {
+            let mut storage: libc::sockaddr_storage = std::mem::zeroed();
+            std::ptr::copy_nonoverlapping(
+                addr.as_ptr() as *const u8,
+                &mut storage as *mut libc::sockaddr_storage as *mut u8,
+                addr.len() as usize,
+            );
+            storage
+        }
+    }
+
+    let req = libc::group_source_req {
+        gsr_interface: index,
+        gsr_group: storage(group),
+        gsr_source: storage(source),
+    };
+    let level = if group.is_ipv4() { libc::IPPROTO_IP } else { libc::IPPROTO_IPV6 };
+    let name = if join { libc::MCAST_JOIN_SOURCE_GROUP } else { libc::MCAST_LEAVE_SOURCE_GROUP };
+    // SAFETY: req 在调用期间有效，传入的长度与其类型一致
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &req as *const libc::group_source_req as *const libc::c_void,
+            std::mem::size_of::<libc::group_source_req>() as libc::socklen_t,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn source_group(_socket: &Socket, _index: u32, _source: IpAddr, _group: IpAddr, _join: bool) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "source-specific multicast by interface name is not supported here, use an IPv4 interface address",
+    ))
+}
+
+/// 按接口序号设置发送 IPv4 组播的接口 (IP_MULTICAST_IF 使用 ip_mreqn)
+#[cfg(target_os = "linux")]
+fn multicast_if_index_v4(socket: &Socket, index: u32) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let req = libc::ip_mreqn {
+        imr_multiaddr: libc::in_addr { s_addr: 0 },
+        imr_address: libc::in_addr { s_addr: 0 },
+        imr_ifindex: index as libc::c_int,
+    };
+    // SAFETY: req 在调用期间有效，传入的长度与其类型一致
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_MULTICAST_IF,
+            &req as *const libc::ip_mreqn as *const libc::c_void,
+            std::mem::size_of::<libc::ip_mreqn>() as libc::socklen_t,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn multicast_if_index_v4(_socket: &Socket, _index: u32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "selecting the IPv4 multicast interface by name is not supported here, use its IPv4 address",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_group() {
+        assert_eq!(Multicast::parse_group("239.1.2.3:5000").unwrap(), "239.1.2.3:5000".parse().unwrap());
+        let group = Multicast::parse_group("[ff02::1234%2]:5000").unwrap();
+        assert_eq!(group, "[ff02::1234%2]:5000".parse().unwrap());
+        assert!(Multicast::parse_group("10.0.0.1:5000").is_err());
+        assert!(Multicast::parse_group("5000").is_err());
+        assert!(Multicast::parse_group("example.local:5000").is_err());
+    }
+
+    #[test]
+    fn test_listen_addr() {
+        let multicast = Multicast {
+            group: "[ff02::1234%2]:5000".parse().unwrap(),
+            iface: None,
+            source: None,
+            ttl: None,
+            loopback: None,
+        };
+        assert_eq!(multicast.listen_addr(), "[::]:5000".parse().unwrap());
+        assert_eq!(multicast.interface().unwrap(), Some(Interface::Index(2)));
+        assert_eq!(UdpGroup::Multicast(multicast).to_string(), "multicast [ff02::1234%2]:5000");
+    }
+}
@@ -0,0 +1,651 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::future::Either;
+use socket2::{SockRef, Socket};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{tcp::OwnedReadHalf, TcpStream},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Notify, RwLock,
+    },
+    task::JoinHandle,
+};
+
+use crate::capture::logger::LogEvent;
+use crate::protocols::common::{
+    ConnectionControl, ConnectionInfo, Message, MessageDirection, MessageType, ProtocolHandler, SessionEvent,
+    SessionOptions, WriteAck,
+};
+use crate::protocols::session::{frame_outgoing, report_event, report_option_error, write_impaired};
+use crate::protocols::tcp::{self, WriteHalf};
+use crate::utils::data_format::bytes_to_hex;
+
+/// 代理转发方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProxyDirection {
+    /// 客户端发往上游
+    ToUpstream,
+    /// 上游发往客户端
+    ToClient,
+}
+
+impl ProxyDirection {
+    /// 另一个方向
+    pub fn reverse(self) -> Self {
+        match self {
+            ProxyDirection::ToUpstream => ProxyDirection::ToClient,
+            ProxyDirection::ToClient => ProxyDirection::ToUpstream,
+        }
+    }
+
+    /// 该方向的数据在会话中的消息方向: 客户端发来的为收到，发往客户端的为发送
+    pub fn message_direction(self) -> MessageDirection {
+        match self {
+            ProxyDirection::ToUpstream => MessageDirection::Received,
+            ProxyDirection::ToClient => MessageDirection::Sent,
+        }
+    }
+}
+
+impl fmt::Display for ProxyDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyDirection::ToUpstream => write!(f, "client -> upstream"),
+            ProxyDirection::ToClient => write!(f, "upstream -> client"),
+        }
+    }
+}
+
+/// 代理命令 (由UI发往代理处理器)
+#[derive(Debug, Clone)]
+pub enum ProxyCommand {
+    /// 向指定方向注入数据 (已分帧)，不受暂停影响
+    Inject(ProxyDirection, Bytes),
+    /// 暂停或恢复指定方向的转发，暂停期间读到的数据被扣留，恢复时按顺序放行
+    TogglePause(ProxyDirection),
+    /// 用新数据替换该方向最早扣留的数据并放行，None 表示丢弃
+    Edit(ProxyDirection, Option<Bytes>),
+}
+
+/// 代理事件
+#[derive(Debug, Clone)]
+pub enum ProxyEvent {
+    /// 已连接到上游
+    Upstream { local: SocketAddr, remote: SocketAddr },
+    /// 暂停转发
+    Paused(ProxyDirection),
+    /// 恢复转发，放行了 `released` 块扣留的数据
+    Resumed { direction: ProxyDirection, released: usize },
+    /// 暂停期间扣留了一块数据，`pending` 为该方向扣留的块数
+    Held { direction: ProxyDirection, data: Bytes, pending: usize },
+    /// 最早扣留的数据被替换为 `bytes` 字节后放行
+    Edited { direction: ProxyDirection, bytes: usize },
+    /// 最早扣留的数据被丢弃
+    Dropped { direction: ProxyDirection, bytes: usize },
+    /// 该方向没有扣留的数据
+    NothingHeld(ProxyDirection),
+    /// 数据来源一端关闭了写入方向，已向另一端转发 FIN
+    HalfClosed(ProxyDirection),
+}
+
+impl fmt::Display for ProxyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyEvent::Upstream { local, remote } => write!(f, "upstream: {} -> {}", local, remote),
+            ProxyEvent::Paused(direction) => write!(f, "paused {}", direction),
+            ProxyEvent::Resumed { direction, released } => {
+                write!(f, "resumed {}, released {} held chunks", direction, released)
+            }
+            ProxyEvent::Held { direction, data, pending } => {
+                write!(f, "held {} bytes {} ({} pending): {}", data.len(), direction, pending, bytes_to_hex(data))
+            }
+            ProxyEvent::Edited { direction, bytes } => write!(f, "edited held data {}, forwarded {} bytes", direction, bytes),
+            ProxyEvent::Dropped { direction, bytes } => write!(f, "dropped {} held bytes {}", bytes, direction),
+            ProxyEvent::NothingHeld(direction) => write!(f, "no held data {}", direction),
+            ProxyEvent::HalfClosed(direction) => write!(f, "{}: sender closed, FIN forwarded", direction),
+        }
+    }
+}
+
+/// 发往单向转发任务的命令
+enum PumpCommand {
+    /// 注入数据，写出后通知发送方
+    Inject(Bytes, WriteAck),
+    TogglePause,
+    Edit(Option<Bytes>),
+    /// 关闭写入方向，之后读到的数据被丢弃
+    Shutdown,
+}
+
+/// 单向转发任务: 从一端读取原始字节写入另一端，暂停时扣留读到的数据
+struct Pump {
+    direction: ProxyDirection,
+    info: ConnectionInfo,
+    options: SessionOptions,
+    ui_tx: Option<Sender<Message>>,
+    paused: bool,
+    held: VecDeque<Bytes>,
+    write_closed: bool,
+}
+
+impl Pump {
+    fn new(direction: ProxyDirection, info: ConnectionInfo, options: SessionOptions, ui_tx: Option<Sender<Message>>) -> Self {
+        Self {
+            direction,
+            info,
+            options,
+            ui_tx,
+            paused: false,
+            held: VecDeque::new(),
+            write_closed: false,
+        }
+    }
+
+    async fn run(
+        mut self,
+        mut reader: OwnedReadHalf,
+        mut writer: WriteHalf,
+        mut commands: Receiver<PumpCommand>,
+    ) -> std::io::Result<()> {
+        let mut buf = vec![0u8; 8192];
+        let mut eof = false;
+        loop {
+            // 读到 EOF 且扣留的数据都已放行后，向另一端转发 FIN
+            if eof && self.held.is_empty() {
+                if !self.write_closed {
+                    writer.shutdown().await?;
+                    self.event(ProxyEvent::HalfClosed(self.direction)).await;
+                }
+                return Ok(());
+            }
+            tokio::select! {
+                read = reader.read(&mut buf), if !eof => match read? {
+                    0 => eof = true,
+                    n => {
+                        let data = Bytes::copy_from_slice(&buf[..n]);
+                        if self.paused {
+                            self.held.push_back(data.clone());
+                            let pending = self.held.len();
+                            self.event(ProxyEvent::Held { direction: self.direction, data, pending }).await;
+                        } else {
+                            self.forward(&mut writer, data).await?;
+                        }
+                    }
+                },
+                command = commands.recv() => match command {
+                    Some(command) => self.apply(command, &mut writer).await?,
+                    None => return Ok(()),
+                },
+            }
+        }
+    }
+
+    async fn apply(&mut self, command: PumpCommand, writer: &mut WriteHalf) -> std::io::Result<()> {
+        match command {
+            PumpCommand::Inject(data, ack) => {
+                if let Some(data) = self.forward(writer, data).await? {
+                    ack.written(&self.info, &data);
+                }
+            }
+            PumpCommand::TogglePause if !self.paused => {
+                self.paused = true;
+                self.event(ProxyEvent::Paused(self.direction)).await;
+            }
+            PumpCommand::TogglePause => {
+                self.paused = false;
+                let released = self.held.len();
+                while let Some(data) = self.held.pop_front() {
+                    self.forward(writer, data).await?;
+                }
+                self.event(ProxyEvent::Resumed { direction: self.direction, released }).await;
+            }
+            PumpCommand::Edit(replacement) => {
+                let direction = self.direction;
+                let Some(original) = self.held.pop_front() else {
+                    self.event(ProxyEvent::NothingHeld(direction)).await;
+                    return Ok(());
+                };
+                match replacement {
+                    Some(data) => {
+                        let bytes = data.len();
+                        self.forward(writer, data).await?;
+                        self.event(ProxyEvent::Edited { direction, bytes }).await;
+                    }
+                    None => self.event(ProxyEvent::Dropped { direction, bytes: original.len() }).await,
+                }
+            }
+            PumpCommand::Shutdown if self.write_closed => {}
+            PumpCommand::Shutdown => {
+                writer.shutdown().await?;
+                self.write_closed = true;
+                let event = SessionEvent::LocalClose(ConnectionControl::Shutdown);
+                report_event(self.ui_tx.as_ref(), &self.options, &self.info, event).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// 按故障注入设置写入另一端，并按方向显示在发送区或接收区，返回实际写出的数据 (写入方向已关闭时为 None)
+    async fn forward(&mut self, writer: &mut WriteHalf, data: Bytes) -> std::io::Result<Option<Bytes>> {
+        if self.write_closed {
+            let event = SessionEvent::WriteClosed { bytes: data.len() };
+            report_event(self.ui_tx.as_ref(), &self.options, &self.info, event).await;
+            return Ok(None);
+        }
+        let data = write_impaired(writer, data, self.ui_tx.as_ref(), &self.options, &self.info).await?;
+
+        let direction = self.direction.message_direction();
+        self.options.capture.record(direction, Some(&self.info), LogEvent::Data(data.clone()));
+        if let Some(tx) = &self.ui_tx {
+            let content = MessageType::from_payload(data.clone());
+            let message = match direction {
+                MessageDirection::Received => Message::new_received(content, Some(self.info.clone())),
+                MessageDirection::Sent => Message::new_sent(content, Some(self.info.clone())),
+            };
+            let _ = tx.send(message).await;
+        }
+        Ok(Some(data))
+    }
+
+    async fn event(&self, event: ProxyEvent) {
+        report_event(self.ui_tx.as_ref(), &self.options, &self.info, SessionEvent::Proxy(event)).await;
+    }
+}
+
+/// 等待两个方向的转发结束，任一方向出错时中止另一方向，返回转发出错的原因
+async fn join_pumps(a: JoinHandle<std::io::Result<()>>, b: JoinHandle<std::io::Result<()>>) -> Option<std::io::Error> {
+    let (Either::Left((result, other)) | Either::Right((result, other))) = futures_util::future::select(a, b).await;
+    match result {
+        Ok(Ok(())) => other.await.ok()?.err(),
+        Ok(Err(e)) => {
+            other.abort();
+            Some(e)
+        }
+        Err(_) => {
+            other.abort();
+            None
+        }
+    }
+}
+
+/// 客户端与上游的连接对
+struct ProxyPair {
+    /// 客户端地址
+    client_addr: SocketAddr,
+    /// 客户端到上游方向转发任务的命令通道
+    to_upstream: Sender<PumpCommand>,
+    /// 上游到客户端方向转发任务的命令通道
+    to_client: Sender<PumpCommand>,
+    /// 关闭通知，收到后结束两个方向的转发
+    close: Arc<Notify>,
+    /// 客户端和上游连接的套接字句柄
+    sockets: [Socket; 2],
+}
+
+impl ProxyPair {
+    fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            remote_addr: self.client_addr,
+            connection_id: self.client_addr.to_string(),
+            local_addr: None,
+        }
+    }
+
+    fn pump(&self, direction: ProxyDirection) -> &Sender<PumpCommand> {
+        match direction {
+            ProxyDirection::ToUpstream => &self.to_upstream,
+            ProxyDirection::ToClient => &self.to_client,
+        }
+    }
+
+    /// 向指定方向注入数据，写出后通过 `ack` 通知
+    async fn inject(&self, direction: ProxyDirection, data: Bytes, ack: WriteAck) {
+        let _ = self.pump(direction).send(PumpCommand::Inject(data, ack)).await;
+    }
+
+    async fn command(&self, command: ProxyCommand) {
+        let (direction, command) = match command {
+            ProxyCommand::Inject(direction, data) => (direction, PumpCommand::Inject(data, WriteAck::default())),
+            ProxyCommand::TogglePause(direction) => (direction, PumpCommand::TogglePause),
+            ProxyCommand::Edit(direction, data) => (direction, PumpCommand::Edit(data)),
+        };
+        let _ = self.pump(direction).send(command).await;
+    }
+
+    /// 执行连接控制命令，同时作用于客户端和上游两条连接
+    async fn apply_control(&self, control: ConnectionControl) -> std::io::Result<()> {
+        match control {
+            ConnectionControl::Close => self.close.notify_one(),
+            ConnectionControl::Shutdown => {
+                let _ = self.to_upstream.send(PumpCommand::Shutdown).await;
+                let _ = self.to_client.send(PumpCommand::Shutdown).await;
+            }
+            ConnectionControl::Abort => {
+                for socket in &self.sockets {
+                    socket.set_linger(Some(Duration::ZERO))?;
+                }
+                self.close.notify_one();
+            }
+            ConnectionControl::SetOption(option) => {
+                for socket in &self.sockets {
+                    option.apply(socket)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+type Pairs = Arc<RwLock<HashMap<String, ProxyPair>>>;
+
+/// 为接受的客户端连接上游并双向转发，直到两个方向都结束
+async fn run_pair(
+    client: TcpStream,
+    client_addr: SocketAddr,
+    upstream_addrs: Arc<Vec<SocketAddr>>,
+    options: SessionOptions,
+    ui_tx: Option<Sender<Message>>,
+    pairs: Pairs,
+) {
+    let info = ConnectionInfo {
+        remote_addr: client_addr,
+        connection_id: client_addr.to_string(),
+        local_addr: client.local_addr().ok(),
+    };
+    if let Err(e) = options.socket.apply(&SockRef::from(&client), true) {
+        report_option_error(ui_tx.as_ref(), &info, None, e).await;
+    }
+    options.capture.record(MessageDirection::Received, Some(&info), LogEvent::Connected);
+    if let Some(tx) = &ui_tx {
+        let _ = tx.send(Message::new_received(MessageType::ClientConnected, Some(info.clone()))).await;
+    }
+
+    let unspecified = SocketAddr::from(([0, 0, 0, 0], 0));
+    match tcp::connect(unspecified, &upstream_addrs, &options, None).await {
+        Ok(upstream) => relay(client, upstream, &info, &options, ui_tx.as_ref(), &pairs).await,
+        Err(e) => report_event(ui_tx.as_ref(), &options, &info, SessionEvent::ConnectFailed(e.to_string())).await,
+    }
+
+    pairs.write().await.remove(&info.connection_id);
+    options.capture.record(MessageDirection::Received, Some(&info), LogEvent::Disconnected);
+    if let Some(tx) = &ui_tx {
+        let _ = tx.send(Message::new_received(MessageType::ClientDisconnected, Some(info.clone()))).await;
+    }
+}
+
+/// 在客户端和上游之间双向转发
+async fn relay(
+    client: TcpStream,
+    upstream: TcpStream,
+    info: &ConnectionInfo,
+    options: &SessionOptions,
+    ui_tx: Option<&Sender<Message>>,
+    pairs: &Pairs,
+) {
+    if let (Ok(local), Ok(remote)) = (upstream.local_addr(), upstream.peer_addr()) {
+        report_event(ui_tx, options, info, SessionEvent::Proxy(ProxyEvent::Upstream { local, remote })).await;
+    }
+    let (Ok(client_socket), Ok(upstream_socket)) = (SockRef::from(&client).try_clone(), SockRef::from(&upstream).try_clone()) else {
+        return;
+    };
+
+    let (client_read, client_write) = client.into_split();
+    let (upstream_read, upstream_write) = upstream.into_split();
+    let (to_upstream, to_upstream_rx) = channel::<PumpCommand>(100);
+    let (to_client, to_client_rx) = channel::<PumpCommand>(100);
+    let close = Arc::new(Notify::new());
+    pairs.write().await.insert(info.connection_id.clone(), ProxyPair {
+        client_addr: info.remote_addr,
+        to_upstream,
+        to_client,
+        close: Arc::clone(&close),
+        sockets: [client_socket, upstream_socket],
+    });
+
+    let pump = |direction| Pump::new(direction, info.clone(), options.clone(), ui_tx.cloned());
+    let up = tokio::spawn(pump(ProxyDirection::ToUpstream).run(client_read, WriteHalf(Some(upstream_write)), to_upstream_rx));
+    let down = tokio::spawn(pump(ProxyDirection::ToClient).run(upstream_read, WriteHalf(Some(client_write)), to_client_rx));
+    let aborts = [up.abort_handle(), down.abort_handle()];
+    tokio::select! {
+        error = join_pumps(up, down) => {
+            if let Some(e) = error {
+                report_event(ui_tx, options, info, SessionEvent::IoError(format!("relay: {}", e))).await;
+            }
+        }
+        // 关闭或中止时直接结束两个方向，未转发的数据被丢弃
+        _ = close.notified() => aborts.iter().for_each(|abort| abort.abort()),
+    }
+}
+
+/// TCP 代理处理器: 接受客户端连接，为每个客户端建立到上游的连接并双向转发
+pub struct TcpProxyHandler {
+    /// 监听地址
+    local_addrs: Vec<SocketAddr>,
+    /// 上游地址，有多个地址时按 Happy Eyeballs 竞速连接
+    upstream_addrs: Arc<Vec<SocketAddr>>,
+    /// 连接对，以客户端地址为连接 ID
+    pairs: Pairs,
+    /// 控制通道 (用于停止代理)
+    control_tx: Option<Sender<()>>,
+    /// UI到代理发送通道
+    ui_to_server_tx: Option<Sender<Message>>,
+    /// 代理到UI发送通道
+    server_to_ui_tx: Option<Sender<Message>>,
+    /// 会话选项
+    options: SessionOptions,
+    /// 运行状态
+    running: bool,
+}
+
+impl TcpProxyHandler {
+    /// 创建新的TCP代理处理器，同时监听 `local_addrs` 中的每个地址
+    pub fn new(local_addrs: Vec<SocketAddr>, upstream_addrs: Vec<SocketAddr>, options: SessionOptions) -> Self {
+        Self {
+            local_addrs,
+            upstream_addrs: Arc::new(upstream_addrs),
+            pairs: Arc::new(RwLock::new(HashMap::new())),
+            control_tx: None,
+            ui_to_server_tx: None,
+            server_to_ui_tx: None,
+            options,
+            running: false,
+        }
+    }
+}
+
+#[async_trait]
+impl ProtocolHandler for TcpProxyHandler {
+    async fn start(&mut self) -> Result<()> {
+        let (ui_to_server_tx, mut ui_to_server_rx) = channel::<Message>(100);
+        let (control_tx, mut control_rx) = channel::<()>(1);
+        self.ui_to_server_tx = Some(ui_to_server_tx);
+        self.control_tx = Some(control_tx);
+
+        let listeners = self
+            .local_addrs
+            .iter()
+            .map(|addr| self.options.socket.tcp_listener(*addr))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        self.running = true;
+
+        // 将UI发来的命令转发给目标连接对 (未指定目标时作用于所有连接对)，普通数据注入到上游方向
+        let pairs = Arc::clone(&self.pairs);
+        let options = self.options.clone();
+        let server_to_ui_tx = self.server_to_ui_tx.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = ui_to_server_rx.recv().await {
+                let pairs = pairs.read().await;
+                let targets: Vec<&ProxyPair> = match &msg.connection_info {
+                    Some(info) => pairs.get(&info.connection_id).into_iter().collect(),
+                    None => pairs.values().collect(),
+                };
+
+                let command = match msg.content {
+                    MessageType::Control(control) => {
+                        for pair in targets {
+                            let info = pair.connection_info();
+                            if matches!(control, ConnectionControl::Close | ConnectionControl::Abort) {
+                                report_event(server_to_ui_tx.as_ref(), &options, &info, SessionEvent::LocalClose(control)).await;
+                            }
+                            if let Err(e) = pair.apply_control(control).await {
+                                report_option_error(server_to_ui_tx.as_ref(), &info, control.socket_option(), e).await;
+                            }
+                        }
+                        continue;
+                    }
+                    MessageType::Proxy(command) => command,
+                    content => {
+                        if let Some(data) = frame_outgoing(server_to_ui_tx.as_ref(), &options, &content).await {
+                            for pair in targets {
+                                pair.inject(ProxyDirection::ToUpstream, data.clone(), msg.ack.clone()).await;
+                            }
+                        }
+                        continue;
+                    }
+                };
+                for pair in targets {
+                    pair.command(command.clone()).await;
+                }
+            }
+        });
+
+        let pairs = Arc::clone(&self.pairs);
+        let upstream_addrs = Arc::clone(&self.upstream_addrs);
+        let options = self.options.clone();
+        let server_to_ui_tx = self.server_to_ui_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    result = tcp::accept_any(&listeners) => match result {
+                        Ok((stream, addr)) => {
+                            tokio::spawn(run_pair(
+                                stream,
+                                addr,
+                                Arc::clone(&upstream_addrs),
+                                options.clone(),
+                                server_to_ui_tx.clone(),
+                                Arc::clone(&pairs),
+                            ));
+                        }
+                        Err(e) => {
+                            report_event(server_to_ui_tx.as_ref(), &options, None, SessionEvent::IoError(format!("accept: {}", e))).await;
+                        }
+                    },
+                    _ = control_rx.recv() => break,
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if self.running {
+            if let Some(control_tx) = self.control_tx.take() {
+                let _ = control_tx.send(()).await;
+            }
+            self.running = false;
+        }
+        Ok(())
+    }
+
+    async fn send_message(&mut self, message: MessageType, target: Option<String>) -> Result<()> {
+        let Some(data) = frame_outgoing(self.server_to_ui_tx.as_ref(), &self.options, &message).await else {
+            return Ok(());
+        };
+        let pairs = self.pairs.read().await;
+        for (id, pair) in pairs.iter() {
+            if target.as_ref().is_none_or(|target| target == id) {
+                pair.command(ProxyCommand::Inject(ProxyDirection::ToUpstream, data.clone())).await;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_ui_to_server_sender(&self) -> Option<Sender<Message>> {
+        self.ui_to_server_tx.clone()
+    }
+
+    fn set_server_to_ui_sender(&mut self, sender: Sender<Message>) {
+        self.server_to_ui_tx = Some(sender);
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn get_connections(&self) -> Vec<ConnectionInfo> {
+        let rt = tokio::runtime::Handle::current();
+        rt.block_on(async {
+            let pairs = self.pairs.read().await;
+            pairs.values().map(ProxyPair::connection_info).collect()
+        })
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "TCP Proxy"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// 建立一条本地 TCP 连接，返回 (连接端, 接受端)
+    async fn tcp_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (accepted, _) = listener.accept().await.unwrap();
+        (client, accepted)
+    }
+
+    #[tokio::test]
+    async fn test_pump_holds_and_edits() {
+        let (mut source, source_peer) = tcp_pair().await;
+        let (dest_peer, mut dest) = tcp_pair().await;
+        let (ui_tx, mut ui_rx) = channel::<Message>(100);
+        let (commands, commands_rx) = channel::<PumpCommand>(10);
+
+        let info = ConnectionInfo::non_ip("pair".to_string());
+        let pump = Pump::new(ProxyDirection::ToUpstream, info, SessionOptions::default(), Some(ui_tx));
+        let (reader, _) = source_peer.into_split();
+        let (_, writer) = dest_peer.into_split();
+        let task = tokio::spawn(pump.run(reader, WriteHalf(Some(writer)), commands_rx));
+
+        commands.send(PumpCommand::TogglePause).await.unwrap();
+        assert!(matches!(
+            ui_rx.recv().await.unwrap().content,
+            MessageType::Event(SessionEvent::Proxy(ProxyEvent::Paused(ProxyDirection::ToUpstream)))
+        ));
+        source.write_all(b"abc").await.unwrap();
+        match ui_rx.recv().await.unwrap().content {
+            MessageType::Event(SessionEvent::Proxy(ProxyEvent::Held { data, pending, .. })) => {
+                assert_eq!(&data[..], b"abc");
+                assert_eq!(pending, 1);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        commands.send(PumpCommand::Edit(Some(Bytes::from_static(b"xyz")))).await.unwrap();
+        let mut buf = [0u8; 3];
+        dest.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"xyz");
+        let forwarded = ui_rx.recv().await.unwrap();
+        assert_eq!(forwarded.direction, MessageDirection::Received);
+
+        // 发送端关闭后向接收端转发 FIN
+        drop(source);
+        assert_eq!(dest.read(&mut buf).await.unwrap(), 0);
+        task.await.unwrap().unwrap();
+    }
+}
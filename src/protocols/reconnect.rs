@@ -0,0 +1,122 @@
+use std::{str::FromStr, time::Duration};
+
+use crate::utils::random::random_unit;
+
+/// 重连间隔的增长方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backoff {
+    /// 固定间隔
+    Fixed,
+    /// 每次失败后间隔加倍
+    #[default]
+    Exponential,
+}
+
+impl FromStr for Backoff {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(Backoff::Fixed),
+            "exp" | "exponential" => Ok(Backoff::Exponential),
+            other => Err(format!("Unknown backoff: {} (expected fixed or exp)", other)),
+        }
+    }
+}
+
+/// 自动重连策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    pub backoff: Backoff,
+    /// 首次重连前的等待时间
+    pub initial_delay: Duration,
+    /// 指数退避的最大等待时间
+    pub max_delay: Duration,
+    /// 随机抖动比例 (0~1)，实际等待时间在 delay × (1 ± jitter) 之间
+    pub jitter: f64,
+    /// 每次断开后的最大重连次数，None 表示不限
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            backoff: Backoff::Exponential,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.1,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 `attempt` 次 (从 0 开始) 重连前的等待时间 (不含抖动)
+    pub fn base_delay(&self, attempt: u32) -> Duration {
+        match self.backoff {
+            Backoff::Fixed => self.initial_delay,
+            Backoff::Exponential => self
+                .initial_delay
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(self.max_delay.max(self.initial_delay)),
+        }
+    }
+
+    /// 第 `attempt` 次 (从 0 开始) 重连前的等待时间，按抖动比例随机调整
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + jitter * (random_unit() * 2.0 - 1.0);
+        self.base_delay(attempt).mul_f64(factor)
+    }
+}
+
+/// 连接断开期间发送的消息如何处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OfflineSend {
+    /// 丢弃并提示
+    #[default]
+    Reject,
+    /// 排队，重新连接后依次发送
+    Queue,
+}
+
+impl FromStr for OfflineSend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "reject" => Ok(OfflineSend::Reject),
+            "queue" => Ok(OfflineSend::Queue),
+            other => Err(format!("Unknown offline mode: {} (expected queue or reject)", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(3),
+            jitter: 0.0,
+            ..Default::default()
+        };
+        let delays: Vec<u64> = (0..5).map(|n| policy.delay(n).as_millis() as u64).collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 3000, 3000]);
+
+        let fixed = ReconnectPolicy {
+            backoff: Backoff::Fixed,
+            ..policy.clone()
+        };
+        assert_eq!(fixed.delay(10), Duration::from_millis(500));
+
+        let jittered = ReconnectPolicy { jitter: 0.5, ..policy };
+        for _ in 0..100 {
+            let delay = jittered.delay(1);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1500));
+        }
+    }
+}
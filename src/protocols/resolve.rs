@@ -0,0 +1,322 @@
+use anyhow::{Context, Result};
+use std::{
+    fmt, io,
+    future::Future,
+    net::{IpAddr, SocketAddr, SocketAddrV6},
+    time::Duration,
+};
+use tokio::task::JoinSet;
+
+/// Happy Eyeballs 中相邻两次连接尝试的间隔 (RFC 8305 推荐值)
+pub const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// 解析主机名时限定的地址族
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IpFamily {
+    /// IPv4 和 IPv6 均可
+    #[default]
+    Any,
+    /// 只用 IPv4 (-4)
+    V4,
+    /// 只用 IPv6 (-6)
+    V6,
+}
+
+impl IpFamily {
+    /// 地址是否属于该地址族
+    pub fn allows(&self, addr: &SocketAddr) -> bool {
+        match self {
+            IpFamily::Any => true,
+            IpFamily::V4 => addr.is_ipv4(),
+            IpFamily::V6 => addr.is_ipv6(),
+        }
+    }
+
+    /// 地址所属的地址族，双栈监听收到的 IPv4 连接 (IPv4 映射的 IPv6 地址) 视为 IPv4
+    pub fn of(addr: &SocketAddr) -> Self {
+        match addr.ip().to_canonical() {
+            IpAddr::V4(_) => IpFamily::V4,
+            IpAddr::V6(_) => IpFamily::V6,
+        }
+    }
+}
+
+impl fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpFamily::Any => write!(f, "IP"),
+            IpFamily::V4 => write!(f, "IPv4"),
+            IpFamily::V6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// 主机名或 IP 地址加端口
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostPort {
+    pub host: String,
+    pub port: u16,
+}
+
+impl HostPort {
+    /// 解析 HOST:PORT、[IPv6]:PORT 或 PORT，只有端口时主机为 `default_host`；
+    /// IPv6 地址可带区域 (接口名或序号)，如 [fe80::1%eth0]:8000
+    pub fn parse(s: &str, default_host: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid address: {} (expected HOST:PORT or PORT)", s);
+        if let Ok(port) = s.parse::<u16>() {
+            return Ok(Self {
+                host: default_host.to_string(),
+                port,
+            });
+        }
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Ok(Self::from(addr));
+        }
+        let (host, port) = match s.strip_prefix('[') {
+            Some(rest) => rest.split_once("]:").ok_or_else(invalid)?,
+            None => {
+                let (host, port) = s.rsplit_once(':').ok_or_else(invalid)?;
+                // 未加方括号的 IPv6 地址中含有冒号，无法区分端口
+                if host.contains(':') {
+                    return Err(invalid());
+                }
+                (host, port)
+            }
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        let port = port.parse::<u16>().map_err(|_| invalid())?;
+        Ok(Self {
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    /// 主机为 IP 地址时返回该地址 (无需解析)
+    pub fn ip(&self) -> Option<IpAddr> {
+        let ip = self.host.split_once('%').map_or(self.host.as_str(), |(ip, _)| ip);
+        ip.parse().ok()
+    }
+
+    /// 主机为 IP 地址时直接构造套接字地址，带区域的 IPv6 地址换算出区域序号
+    pub fn literal(&self) -> Result<Option<SocketAddr>> {
+        let Some(ip) = self.ip() else {
+            return Ok(None);
+        };
+        match (ip, self.host.split_once('%')) {
+            (ip, None) => Ok(Some(SocketAddr::new(ip, self.port))),
+            (IpAddr::V6(ip), Some((_, zone))) => Ok(Some(SocketAddrV6::new(ip, self.port, 0, scope_id(zone)?).into())),
+            (IpAddr::V4(_), Some(_)) => anyhow::bail!("Zone index is only valid for IPv6 addresses: {}", self.host),
+        }
+    }
+}
+
+impl From<SocketAddr> for HostPort {
+    fn from(addr: SocketAddr) -> Self {
+        let host = match addr {
+            SocketAddr::V6(addr) if addr.scope_id() != 0 => format!("{}%{}", addr.ip(), addr.scope_id()),
+            addr => addr.ip().to_string(),
+        };
+        Self {
+            host,
+            port: addr.port(),
+        }
+    }
+}
+
+impl fmt::Display for HostPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ip() {
+            Some(IpAddr::V6(_)) => write!(f, "[{}]:{}", self.host, self.port),
+            _ => write!(f, "{}:{}", self.host, self.port),
+        }
+    }
+}
+
+/// 将 IPv6 区域 (接口名或序号) 换算为区域序号
+pub(crate) fn scope_id(zone: &str) -> Result<u32> {
+    if let Ok(index) = zone.parse::<u32>() {
+        return Ok(index);
+    }
+    #[cfg(unix)]
+    {
+        let name = std::ffi::CString::new(zone).with_context(|| format!("Invalid interface name: {}", zone))?;
+        // SAFETY: name 是以 NUL 结尾的有效 C 字符串，函数不会保留该指针
+        let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if index == 0 {
+            anyhow::bail!("Unknown network interface: {}", zone);
+        }
+        Ok(index)
+    }
+    #[cfg(not(unix))]
+    anyhow::bail!("Interface names are not supported here, use the numeric zone index instead of {}", zone)
+}
+
+/// 主机名的解析结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    pub target: HostPort,
+    /// 全部地址，按连接尝试的顺序排列
+    pub addrs: Vec<SocketAddr>,
+}
+
+impl Resolved {
+    /// 首选地址
+    pub fn first(&self) -> SocketAddr {
+        self.addrs[0]
+    }
+}
+
+impl fmt::Display for Resolved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "resolved {}:", self.target.host)?;
+        for addr in &self.addrs {
+            write!(f, " {}", addr.ip())?;
+        }
+        Ok(())
+    }
+}
+
+/// 异步解析地址，按 `family` 过滤后交替排列 IPv6/IPv4 地址
+pub async fn resolve(target: &HostPort, family: IpFamily) -> Result<Resolved> {
+    let found: Vec<SocketAddr> = match target.literal()? {
+        Some(addr) => vec![addr],
+        None => tokio::net::lookup_host((target.host.as_str(), target.port))
+            .await
+            .with_context(|| format!("Cannot resolve {}", target.host))?
+            .collect(),
+    };
+
+    let mut addrs: Vec<SocketAddr> = Vec::new();
+    for addr in found.iter().filter(|addr| family.allows(addr)) {
+        if !addrs.contains(addr) {
+            addrs.push(*addr);
+        }
+    }
+    if addrs.is_empty() {
+        match target.ip() {
+            Some(_) => anyhow::bail!("{} is not an {} address", target.host, family),
+            None if found.is_empty() => anyhow::bail!("Cannot resolve {}: no addresses", target.host),
+            None => anyhow::bail!("{} has no {} address", target.host, family),
+        }
+    }
+    Ok(Resolved {
+        target: target.clone(),
+        addrs: interleave(addrs),
+    })
+}
+
+/// 按 RFC 8305 交替排列两个地址族的地址，以解析结果中第一个地址的地址族开头
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first().copied() else {
+        return addrs;
+    };
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) =
+        addrs.into_iter().partition(|addr| addr.is_ipv4() == first.is_ipv4());
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    preferred.reverse();
+    other.reverse();
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    ordered
+}
+
+/// Happy Eyeballs: 按顺序向各地址发起连接，前一个尝试失败或 [`ATTEMPT_DELAY`] 内未完成时开始下一个，
+/// 返回最先成功的连接；全部失败时返回带地址的各个错误
+pub async fn race<T, F, Fut>(addrs: &[SocketAddr], connect: F) -> io::Result<T>
+where
+    F: Fn(SocketAddr) -> Fut,
+    Fut: Future<Output = io::Result<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    let mut remaining = addrs.iter().copied();
+    let mut attempts = JoinSet::new();
+    let mut errors: Vec<(SocketAddr, io::Error)> = Vec::new();
+    loop {
+        let started = match remaining.next() {
+            Some(addr) => {
+                let attempt = connect(addr);
+                attempts.spawn(async move { (addr, attempt.await) });
+                true
+            }
+            None => false,
+        };
+
+        // 等待任一尝试完成，或到时间开始下一个尝试
+        let finished = tokio::select! {
+            finished = attempts.join_next() => finished,
+            _ = tokio::time::sleep(ATTEMPT_DELAY), if started => continue,
+        };
+        match finished {
+            Some(Ok((_, Ok(conn)))) => return Ok(conn),
+            Some(Ok((addr, Err(e)))) => errors.push((addr, e)),
+            Some(Err(e)) => return Err(io::Error::other(e)),
+            None => break,
+        }
+        if attempts.is_empty() && remaining.len() == 0 {
+            break;
+        }
+    }
+
+    if errors.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to"));
+    }
+    let kind = errors[0].1.kind();
+    let reason = errors
+        .iter()
+        .map(|(addr, e)| format!("{}: {}", addr, e))
+        .collect::<Vec<_>>()
+        .join("; ");
+    Err(io::Error::new(kind, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_host_port() {
+        let target = HostPort::parse("example.local:80", "127.0.0.1").unwrap();
+        assert_eq!(target.host, "example.local");
+        assert_eq!(target.port, 80);
+        assert_eq!(target.ip(), None);
+
+        let target = HostPort::parse("[::1]:8080", "127.0.0.1").unwrap();
+        assert_eq!(target.ip(), Some("::1".parse().unwrap()));
+        assert_eq!(target.to_string(), "[::1]:8080");
+
+        assert_eq!(HostPort::parse("9000", "127.0.0.1").unwrap().to_string(), "127.0.0.1:9000");
+        let target = HostPort::parse("[fe80::1%3]:8000", "127.0.0.1").unwrap();
+        assert_eq!(target.host, "fe80::1%3");
+        let addr: SocketAddr = "[fe80::1%3]:8000".parse().unwrap();
+        assert_eq!(target.literal().unwrap(), Some(addr));
+        assert_eq!(HostPort::parse("[fe80::1%eth0]:80", "127.0.0.1").unwrap().host, "fe80::1%eth0");
+        assert!(HostPort::parse("[fe80::1%eth0]", "127.0.0.1").is_err());
+        assert!(HostPort::parse("example.local", "127.0.0.1").is_err());
+        assert!(HostPort::parse("::1:80", "127.0.0.1").is_err());
+        assert!(HostPort::parse("host:http", "127.0.0.1").is_err());
+    }
+
+    #[test]
+    fn test_family_of() {
+        let family = |s: &str| IpFamily::of(&s.parse().unwrap());
+        assert_eq!(family("10.0.0.1:80"), IpFamily::V4);
+        assert_eq!(family("[::ffff:10.0.0.1]:80"), IpFamily::V4);
+        assert_eq!(family("[fe80::1%2]:80"), IpFamily::V6);
+    }
+
+    #[test]
+    fn test_interleave_families() {
+        let addrs: Vec<SocketAddr> = ["[::1]:1", "[::2]:1", "[::3]:1", "10.0.0.1:1"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let ordered: Vec<String> = interleave(addrs).iter().map(|addr| addr.to_string()).collect();
+        assert_eq!(ordered, ["[::1]:1", "10.0.0.1:1", "[::2]:1", "[::3]:1"]);
+    }
+}
@@ -0,0 +1,201 @@
+use bytes::Bytes;
+use tokio::{net::tcp::OwnedWriteHalf, sync::mpsc::Sender};
+
+use crate::capture::logger::LogEvent;
+use crate::protocols::autoreply::AutoReplyHit;
+use crate::protocols::common::{ConnectionInfo, Message, MessageDirection, MessageType, SessionEvent, SessionOptions};
+use crate::protocols::fault;
+use crate::protocols::framing::Frame;
+use crate::protocols::sockopt::SocketOption;
+use crate::protocols::timeout::TimeoutExpired;
+
+/// 将收到的帧发送到UI，校验失败或命中自动回复规则时紧随其后发送对应事件，返回命中的自动回复
+pub(crate) async fn forward_frame(
+    tx: Option<&Sender<Message>>,
+    options: &SessionOptions,
+    frame: Frame,
+    connection_info: &ConnectionInfo,
+) -> Vec<AutoReplyHit> {
+    // 会话记录与发送方向一样保存线路上的原始数据
+    let wire = frame.wire(&options.framing);
+    let (content, frame) = match frame {
+        Frame::Payload(data) => (MessageType::from_payload(data.clone()), data),
+        // 未解码的线路数据原样保留，导出时不再按分帧方式封装
+        Frame::Wire(data) => (MessageType::Raw(data.clone()), data),
+    };
+    let mut events: Vec<SessionEvent> = options.check_frame(&frame).into_iter().collect();
+    // 无法按分帧方式封装的回复不发送
+    let mut hits = options.auto_reply.evaluate(&frame);
+    hits.retain(|hit| match options.outgoing_frame(&MessageType::Binary(hit.reply.clone())) {
+        Ok(_) => true,
+        Err(error) => {
+            events.push(SessionEvent::FrameTooLong(error));
+            false
+        }
+    });
+    events.extend(hits.iter().cloned().map(SessionEvent::AutoReply));
+
+    let capture = &options.capture;
+    capture.record(MessageDirection::Received, Some(connection_info), LogEvent::Data(wire));
+    for event in &events {
+        capture.record(MessageDirection::Received, Some(connection_info), LogEvent::Note(event.to_string()));
+    }
+
+    if let Some(tx) = tx {
+        let _ = tx
+            .send(Message::new_received(content, Some(connection_info.clone())))
+            .await;
+        for event in events {
+            let _ = tx
+                .send(Message::new_received(MessageType::Event(event), Some(connection_info.clone())))
+                .await;
+        }
+    }
+    hits
+}
+
+/// 上报超时事件并写入会话记录
+pub(crate) async fn report_timeout(
+    tx: Option<&Sender<Message>>,
+    options: &SessionOptions,
+    connection_info: &ConnectionInfo,
+    expired: TimeoutExpired,
+) {
+    report_event(tx, options, connection_info, SessionEvent::Timeout(expired)).await;
+}
+
+/// 上报会话事件并写入会话记录，`connection_info` 为 None 表示事件不属于任何连接
+pub(crate) async fn report_event<'a>(
+    tx: Option<&Sender<Message>>,
+    options: &SessionOptions,
+    connection_info: impl Into<Option<&'a ConnectionInfo>>,
+    event: SessionEvent,
+) {
+    let connection_info = connection_info.into();
+    options.capture.record(MessageDirection::Received, connection_info, LogEvent::Note(event.to_string()));
+    if let Some(tx) = tx {
+        let _ = tx
+            .send(Message::new_received(MessageType::Event(event), connection_info.cloned()))
+            .await;
+    }
+}
+
+/// 封装待发送的消息，负载超出长度字段范围时上报事件并丢弃，非数据类消息返回 None
+pub(crate) async fn frame_outgoing(
+    tx: Option<&Sender<Message>>,
+    options: &SessionOptions,
+    message: &MessageType,
+) -> Option<Bytes> {
+    match options.outgoing_frame(message) {
+        Ok(frame) => frame,
+        Err(error) => {
+            report_event(tx, options, None, SessionEvent::FrameTooLong(error)).await;
+            None
+        }
+    }
+}
+
+/// 以事件提示套接字选项设置失败，`option` 为 None 表示按配置设置初始选项时失败
+pub(crate) async fn report_option_error(
+    tx: Option<&Sender<Message>>,
+    connection_info: &ConnectionInfo,
+    option: impl Into<Option<SocketOption>>,
+    error: std::io::Error,
+) {
+    let reason = match option.into() {
+        Some(option) => format!("{}: {}", option, error),
+        None => error.to_string(),
+    };
+    if let Some(tx) = tx {
+        let event = MessageType::Event(SessionEvent::OptionFailed(reason));
+        let _ = tx.send(Message::new_received(event, Some(connection_info.clone()))).await;
+    }
+}
+
+/// 按故障注入设置写入数据，提示篡改等故障事件，返回实际写出的数据
+pub(crate) async fn write_impaired(
+    write_half: &mut OwnedWriteHalf,
+    data: Bytes,
+    tx: Option<&Sender<Message>>,
+    options: &SessionOptions,
+    connection_info: &ConnectionInfo,
+) -> std::io::Result<Bytes> {
+    let config = options.faults.get(Some(&connection_info.connection_id));
+    // 分段写入期间临时开启 TCP_NODELAY，否则 Nagle 算法会把小段合并发送
+    let nagle = config.segment.is_some() && !write_half.as_ref().nodelay()?;
+    if nagle {
+        write_half.as_ref().set_nodelay(true)?;
+    }
+    let result = fault::write_stream(write_half, &config, data).await;
+    if nagle {
+        let _ = write_half.as_ref().set_nodelay(false);
+    }
+    let (data, event) = result?;
+    if let Some(event) = event {
+        report_event(tx, options, connection_info, SessionEvent::Fault(event)).await;
+    }
+    Ok(data)
+}
+
+/// 按规则配置的延迟将自动回复放入写入通道
+pub(crate) fn spawn_auto_replies<T: Send + 'static>(hits: Vec<AutoReplyHit>, tx: &Sender<T>, make: impl Fn(Bytes) -> T) {
+    for hit in hits {
+        let tx = tx.clone();
+        let item = make(hit.reply);
+        tokio::spawn(async move {
+            if !hit.delay.is_zero() {
+                tokio::time::sleep(hit.delay).await;
+            }
+            let _ = tx.send(item).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::logger::{LogFormat, LogRecord};
+    use crate::protocols::framing::{FrameDecoder, FramingConfig};
+
+    #[tokio::test]
+    async fn test_capture_records_wire_bytes() {
+        let path = std::env::temp_dir().join(format!("nt-session-{}.jsonl", std::process::id()));
+        let options = SessionOptions {
+            framing: FramingConfig {
+                mode: "len:2".parse().unwrap(),
+                idle_gap: None,
+            },
+            ..Default::default()
+        };
+        options.capture.start(&path, LogFormat::Jsonl).unwrap();
+        let info = ConnectionInfo {
+            remote_addr: "127.0.0.1:9000".parse().unwrap(),
+            connection_id: "a".to_string(),
+            local_addr: None,
+        };
+
+        // 收到一帧后回复一帧，两个方向都应记录带长度前缀的原始数据
+        let request = [0x00, 0x03, b'a', b'b', b'c'];
+        for frame in FrameDecoder::new(options.framing.clone()).decode(&request) {
+            forward_frame(None, &options, frame, &info).await;
+        }
+        let response = options.outgoing_frame(&MessageType::Text("ok".to_string())).unwrap().unwrap();
+        options.capture.record(MessageDirection::Sent, Some(&info), LogEvent::Data(response.clone()));
+        options.capture.finish().await;
+
+        let source = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let records: Vec<(MessageDirection, LogEvent)> = source
+            .lines()
+            .map(|line| LogRecord::from_json(&serde_json::from_str(line).unwrap()).unwrap())
+            .map(|record| (record.direction, record.event))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (MessageDirection::Received, LogEvent::Data(Bytes::copy_from_slice(&request))),
+                (MessageDirection::Sent, LogEvent::Data(response)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use socket2::{SockRef, Socket, TcpKeepalive};
+use std::{
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+    time::Duration,
+};
+use tokio::net::{TcpListener, TcpSocket, TcpStream, UdpSocket};
+
+/// TCP 保活参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keepalive {
+    /// 空闲多久后开始探测
+    pub idle: Duration,
+    /// 探测间隔
+    pub interval: Option<Duration>,
+    /// 探测失败多少次后断开
+    pub count: Option<u32>,
+}
+
+impl FromStr for Keepalive {
+    type Err = String;
+
+    /// 解析 IDLE[,INTERVAL[,COUNT]]，时间单位为秒
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(str::trim);
+        let seconds = |part: &str| {
+            part.parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|_| format!("Invalid keepalive seconds: {}", part))
+        };
+        let idle = seconds(parts.next().unwrap_or_default())?;
+        let interval = parts.next().map(seconds).transpose()?;
+        let count = parts
+            .next()
+            .map(|part| part.parse::<u32>().map_err(|_| format!("Invalid keepalive count: {}", part)))
+            .transpose()?;
+        if parts.next().is_some() {
+            return Err(format!("Invalid keepalive: {} (expected IDLE[,INTERVAL[,COUNT]])", s));
+        }
+        Ok(Self { idle, interval, count })
+    }
+}
+
+impl fmt::Display for Keepalive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}s", self.idle.as_secs())?;
+        if let Some(interval) = self.interval {
+            write!(f, ",{}s", interval.as_secs())?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ",x{}", count)?;
+        }
+        Ok(())
+    }
+}
+
+/// 可在连接建立后修改的套接字选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketOption {
+    /// TCP_NODELAY
+    NoDelay(bool),
+    /// SO_KEEPALIVE，None 表示关闭
+    Keepalive(Option<Keepalive>),
+    /// SO_RCVBUF
+    RecvBuffer(usize),
+    /// SO_SNDBUF
+    SendBuffer(usize),
+    /// SO_LINGER，Some(0) 表示关闭时直接发送 RST
+    Linger(Option<Duration>),
+    /// IP_TTL (IPv6 为 IPV6_UNICAST_HOPS)
+    Ttl(u32),
+}
+
+impl SocketOption {
+    /// 是否只适用于 TCP 连接
+    fn stream_only(&self) -> bool {
+        matches!(self, SocketOption::NoDelay(_) | SocketOption::Keepalive(_) | SocketOption::Linger(_))
+    }
+
+    /// 设置到套接字上
+    pub fn apply(&self, socket: &Socket) -> io::Result<()> {
+        match *self {
+            SocketOption::NoDelay(nodelay) => socket.set_tcp_nodelay(nodelay),
+            SocketOption::Keepalive(None) => socket.set_keepalive(false),
+            SocketOption::Keepalive(Some(keepalive)) => {
+                let params = TcpKeepalive::new().with_time(keepalive.idle);
+                #[cfg(any(target_os = "linux", target_os = "macos", windows))]
+                let params = match keepalive.interval {
+                    Some(interval) => params.with_interval(interval),
+                    None => params,
+                };
+                #[cfg(any(target_os = "linux", target_os = "macos"))]
+                let params = match keepalive.count {
+                    Some(count) => params.with_retries(count),
+                    None => params,
+                };
+                socket.set_tcp_keepalive(&params)
+            }
+            SocketOption::RecvBuffer(size) => socket.set_recv_buffer_size(size),
+            SocketOption::SendBuffer(size) => socket.set_send_buffer_size(size),
+            SocketOption::Linger(linger) => socket.set_linger(linger),
+            SocketOption::Ttl(ttl) => match socket.local_addr()?.as_socket() {
+                Some(SocketAddr::V6(_)) => socket.set_unicast_hops_v6(ttl),
+                _ => socket.set_ttl_v4(ttl),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SocketOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SocketOption::NoDelay(nodelay) => write!(f, "TCP_NODELAY={}", if *nodelay { "on" } else { "off" }),
+            SocketOption::Keepalive(None) => write!(f, "SO_KEEPALIVE=off"),
+            SocketOption::Keepalive(Some(keepalive)) => write!(f, "SO_KEEPALIVE={}", keepalive),
+            SocketOption::RecvBuffer(size) => write!(f, "SO_RCVBUF={}", size),
+            SocketOption::SendBuffer(size) => write!(f, "SO_SNDBUF={}", size),
+            SocketOption::Linger(None) => write!(f, "SO_LINGER=off"),
+            SocketOption::Linger(Some(linger)) => write!(f, "SO_LINGER={}s", linger.as_secs()),
+            SocketOption::Ttl(ttl) => write!(f, "IP_TTL={}", ttl),
+        }
+    }
+}
+
+/// 套接字选项配置，未设置的项保持系统默认值
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    pub nodelay: Option<bool>,
+    pub keepalive: Option<Keepalive>,
+    pub recv_buffer: Option<usize>,
+    pub send_buffer: Option<usize>,
+    pub linger: Option<Duration>,
+    pub ttl: Option<u32>,
+    /// SO_REUSEADDR (绑定前设置)
+    pub reuse_addr: Option<bool>,
+    /// SO_REUSEPORT (绑定前设置)
+    pub reuse_port: Option<bool>,
+    /// IPV6_V6ONLY (绑定前设置)
+    pub only_v6: Option<bool>,
+}
+
+impl SocketOptions {
+    /// 已配置的可在运行时修改的选项
+    pub fn runtime(&self) -> Vec<SocketOption> {
+        [
+            self.nodelay.map(SocketOption::NoDelay),
+            self.keepalive.map(|keepalive| SocketOption::Keepalive(Some(keepalive))),
+            self.recv_buffer.map(SocketOption::RecvBuffer),
+            self.send_buffer.map(SocketOption::SendBuffer),
+            self.linger.map(|linger| SocketOption::Linger(Some(linger))),
+            self.ttl.map(SocketOption::Ttl),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// 记录运行时修改后的值
+    pub fn update(&mut self, option: SocketOption) {
+        match option {
+            SocketOption::NoDelay(nodelay) => self.nodelay = Some(nodelay),
+            SocketOption::Keepalive(keepalive) => self.keepalive = keepalive,
+            SocketOption::RecvBuffer(size) => self.recv_buffer = Some(size),
+            SocketOption::SendBuffer(size) => self.send_buffer = Some(size),
+            SocketOption::Linger(linger) => self.linger = linger,
+            SocketOption::Ttl(ttl) => self.ttl = Some(ttl),
+        }
+    }
+
+    /// 绑定前设置地址复用、仅 IPv6 和缓冲区大小 (缓冲区影响握手时通告的窗口)；
+    /// 未设置 IPV6_V6ONLY 时监听 [::] 同时接受 IPv4 连接
+    fn prepare(&self, socket: &Socket, addr: &SocketAddr) -> io::Result<()> {
+        if let Some(reuse) = self.reuse_addr {
+            socket.set_reuse_address(reuse)?;
+        }
+        if let Some(reuse) = self.reuse_port {
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            socket.set_reuse_port(reuse)?;
+            #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+            if reuse {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "SO_REUSEPORT is not supported"));
+            }
+        }
+        let dual_stack = addr.ip().is_unspecified().then_some(false);
+        if let (Some(only_v6), true) = (self.only_v6.or(dual_stack), addr.is_ipv6()) {
+            socket.set_only_v6(only_v6)?;
+        }
+        if let Some(size) = self.recv_buffer {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer {
+            socket.set_send_buffer_size(size)?;
+        }
+        Ok(())
+    }
+
+    /// 将配置的运行时选项设置到已建立的套接字上，`stream` 为 false 时跳过 TCP 专用选项
+    pub fn apply(&self, socket: &Socket, stream: bool) -> io::Result<()> {
+        for option in self.runtime() {
+            if stream || !option.stream_only() {
+                option.apply(socket)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn tcp_socket(&self, addr: &SocketAddr) -> io::Result<TcpSocket> {
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        self.prepare(&SockRef::from(&socket), addr)?;
+        Ok(socket)
+    }
+
+    /// 按配置创建 TCP 监听套接字
+    pub fn tcp_listener(&self, addr: SocketAddr) -> io::Result<TcpListener> {
+        let socket = self.tcp_socket(&addr)?;
+        socket.bind(addr).map_err(|e| bind_error(addr, e))?;
+        socket.listen(1024)
+    }
+
+    /// 按配置从本地地址 `local` 连接到远程地址，`local` 为未指定地址且端口为 0 时由系统选择
+    pub async fn tcp_connect(&self, local: SocketAddr, remote: SocketAddr) -> io::Result<TcpStream> {
+        let socket = self.tcp_socket(&remote)?;
+        let local = local_for(local, remote);
+        if !local.ip().is_unspecified() || local.port() != 0 {
+            // 固定源端口重连时上次的连接可能仍处于 TIME_WAIT
+            if self.reuse_addr.is_none() && local.port() != 0 {
+                socket.set_reuseaddr(true)?;
+            }
+            socket.bind(local).map_err(|e| bind_error(local, e))?;
+        }
+        let stream = socket.connect(remote).await?;
+        self.apply(&SockRef::from(&stream), true)?;
+        Ok(stream)
+    }
+
+    /// 按配置创建并绑定 UDP 套接字，`addr` 为未指定地址时使用 `peer` 的地址族
+    pub fn udp_bind_for(&self, addr: SocketAddr, peer: SocketAddr) -> io::Result<UdpSocket> {
+        self.udp_bind(local_for(addr, peer))
+    }
+
+    /// 按配置创建并绑定 UDP 套接字
+    pub fn udp_bind(&self, addr: SocketAddr) -> io::Result<UdpSocket> {
+        let domain = socket2::Domain::for_address(addr);
+        let socket = Socket::new(domain, socket2::Type::DGRAM, Some(socket2::Protocol::UDP))?;
+        self.prepare(&socket, &addr)?;
+        socket.bind(&addr.into()).map_err(|e| bind_error(addr, e))?;
+        self.apply(&socket, false)?;
+        socket.set_nonblocking(true)?;
+        UdpSocket::from_std(socket.into())
+    }
+}
+
+fn bind_error(addr: SocketAddr, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("bind {}: {}", addr, e))
+}
+
+/// 本地地址为未指定地址 (0.0.0.0 或 ::) 时换成与远程地址相同的地址族
+pub fn local_for(local: SocketAddr, remote: SocketAddr) -> SocketAddr {
+    if !local.ip().is_unspecified() || local.is_ipv4() == remote.is_ipv4() {
+        return local;
+    }
+    let ip: IpAddr = match remote {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    SocketAddr::new(ip, local.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keepalive() {
+        let keepalive: Keepalive = "30,5,3".parse().unwrap();
+        assert_eq!(keepalive.idle, Duration::from_secs(30));
+        assert_eq!(keepalive.interval, Some(Duration::from_secs(5)));
+        assert_eq!(keepalive.count, Some(3));
+        assert_eq!(keepalive.to_string(), "30s,5s,x3");
+
+        assert_eq!("60".parse::<Keepalive>().unwrap().interval, None);
+        assert!("60,x".parse::<Keepalive>().is_err());
+        assert!("1,2,3,4".parse::<Keepalive>().is_err());
+    }
+}
@@ -1,52 +1,134 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use socket2::{SockRef, Socket};
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    ops::{Deref, DerefMut},
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::{TcpListener, TcpStream},
+    io::AsyncWriteExt,
+    net::{tcp::OwnedWriteHalf, TcpListener, TcpStream},
     sync::{
         mpsc::{channel, Receiver, Sender},
-        RwLock,
+        Notify, RwLock,
     },
+    task::AbortHandle,
+};
+
+use crate::capture::logger::LogEvent;
+use crate::protocols::common::{
+    ConnectionControl, ConnectionInfo, LinkState, Message, MessageDirection, MessageType, ProtocolHandler, SessionEvent,
+    SessionOptions, WriteAck,
+};
+use crate::protocols::fault::FaultEvent;
+use crate::protocols::framing::FrameDecoder;
+use crate::protocols::reconnect::OfflineSend;
+use crate::protocols::resolve;
+use crate::protocols::session::{
+    forward_frame, frame_outgoing, report_event, report_option_error, report_timeout, spawn_auto_replies, write_impaired,
 };
+use crate::protocols::sockopt::SocketOption;
+use crate::protocols::timeout::{self, TimeoutKind};
+use crate::protocols::tunnel;
+
+/// 连接的写入端；释放时若 SO_LINGER 为 0 则不发送 FIN，关闭套接字时直接发送 RST
+pub(crate) struct WriteHalf(pub(crate) Option<OwnedWriteHalf>);
+
+impl Deref for WriteHalf {
+    type Target = OwnedWriteHalf;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref().expect("write half is present until dropped")
+    }
+}
+
+impl DerefMut for WriteHalf {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.as_mut().expect("write half is present until dropped")
+    }
+}
+
+impl Drop for WriteHalf {
+    fn drop(&mut self) {
+        let Some(write_half) = self.0.take() else {
+            return;
+        };
+        let linger = SockRef::from(write_half.as_ref()).linger();
+        if matches!(linger, Ok(Some(linger)) if linger.is_zero()) {
+            write_half.forget();
+        }
+    }
+}
+
+/// 客户端连接的连接信息: 经由代理隧道时以隧道目标而不是代理服务器标识连接，
+/// 目标为主机名 (由代理解析) 时地址仍为代理服务器
+fn client_connection_info(options: &SessionOptions, peer: SocketAddr) -> ConnectionInfo {
+    match &options.tunnel {
+        Some(tunnel) => ConnectionInfo {
+            remote_addr: tunnel.target.literal().ok().flatten().unwrap_or(peer),
+            connection_id: tunnel.target.to_string(),
+            local_addr: None,
+        },
+        None => ConnectionInfo {
+            remote_addr: peer,
+            connection_id: peer.to_string(),
+            local_addr: None,
+        },
+    }
+}
 
-use crate::protocols::common::{ConnectionInfo, Message, MessageDirection, MessageType, ProtocolHandler};
-
-// 简单的十六进制解码辅助函数
-fn hex_decode(hex_str: &str) -> Option<Vec<u8>> {
-    let hex_str = hex_str.trim();
-    if hex_str.is_empty() {
-        return Some(Vec::new());
-    }
-    
-    // 移除可能的 0x 前缀
-    let hex_str = hex_str.strip_prefix("0x").unwrap_or(hex_str);
-    let hex_str = hex_str.strip_prefix("0X").unwrap_or(hex_str);
-    
-    // 移除所有空白字符
-    let hex_str: String = hex_str.chars().filter(|c| !c.is_whitespace()).collect();
-    
-    if hex_str.len() % 2 != 0 {
-        return None;
-    }
-    
-    let mut result = Vec::with_capacity(hex_str.len() / 2);
-    for i in (0..hex_str.len()).step_by(2) {
-        let byte_str = &hex_str[i..i + 2];
-        match u8::from_str_radix(byte_str, 16) {
-            Ok(byte) => result.push(byte),
-            Err(_) => return None,
+/// 按连接超时配置连接到服务器，有多个地址时按 Happy Eyeballs 竞速连接；
+/// 配置了代理隧道时连接的是代理服务器，随后完成代理握手并将各步骤作为连接事件上报
+pub(crate) async fn connect(
+    local_addr: SocketAddr,
+    remote_addrs: &[SocketAddr],
+    options: &SessionOptions,
+    tx: Option<&Sender<Message>>,
+) -> std::io::Result<TcpStream> {
+    let mut steps = Vec::new();
+    let mut peer = remote_addrs[0];
+    let establish = async {
+        let attempts = resolve::race(remote_addrs, |remote_addr| {
+            let socket = options.socket;
+            async move { socket.tcp_connect(local_addr, remote_addr).await }
+        });
+        let mut stream = attempts.await?;
+        if let Some(tunnel) = &options.tunnel {
+            peer = stream.peer_addr().unwrap_or(peer);
+            tunnel::handshake(&mut stream, tunnel, &mut steps).await?;
         }
+        Ok(stream)
+    };
+    let result = timeout::within(options.timeouts.connect, establish).await;
+
+    let connection_info = client_connection_info(options, peer);
+    for step in steps {
+        report_event(tx, options, &connection_info, SessionEvent::Tunnel(step)).await;
     }
-    
-    Some(result)
+    match result {
+        Some(result) => result,
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("connect to {} timed out", remote_addrs[0]),
+        )),
+    }
+}
+
+/// 等待任一监听套接字接受新连接
+pub(crate) async fn accept_any(listeners: &[TcpListener]) -> std::io::Result<(TcpStream, SocketAddr)> {
+    let accepts = listeners.iter().map(|listener| Box::pin(listener.accept()));
+    futures_util::future::select_all(accepts).await.0
 }
 
 /// TCP 服务器处理器
 pub struct TcpServerHandler {
-    /// 本地地址
-    local_addr: SocketAddr,
+    /// 监听地址
+    local_addrs: Vec<SocketAddr>,
     /// 连接的客户端
     clients: Arc<RwLock<HashMap<String, TcpClientInfo>>>,
     /// 控制通道 (用于停止服务器)
@@ -57,28 +139,74 @@ pub struct TcpServerHandler {
     ui_to_server_rx: Option<Receiver<Message>>,
     /// 服务器到UI发送通道
     server_to_ui_tx: Option<Sender<Message>>,
+    /// 会话选项
+    options: SessionOptions,
     /// 运行状态
     running: bool,
 }
 
+/// 发往客户端写入任务的命令
+enum Outgoing {
+    /// 写入一帧 (已按分帧方式封装)，写出后通知发送方
+    Data(Bytes, WriteAck),
+    /// 自动回复的负载，写入前按分帧方式封装
+    Reply(Bytes),
+    /// 写完之前的数据后关闭写入方向
+    Shutdown,
+}
+
 /// TCP 客户端信息
 struct TcpClientInfo {
     /// 远程地址
     addr: SocketAddr,
     /// 发送通道
-    tx: Sender<Bytes>,
+    tx: Sender<Outgoing>,
+    /// 关闭通知，读取任务收到后按连接断开处理
+    close: Arc<Notify>,
+    /// 写入任务，中止连接时直接结束以丢弃未发送的数据
+    writer: AbortHandle,
+    /// 用于运行时修改套接字选项的句柄
+    socket: Socket,
+}
+
+impl TcpClientInfo {
+    fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            remote_addr: self.addr,
+            connection_id: self.addr.to_string(),
+            local_addr: None,
+        }
+    }
+
+    /// 执行连接控制命令
+    async fn apply_control(&self, control: ConnectionControl) -> std::io::Result<()> {
+        match control {
+            ConnectionControl::Close => self.close.notify_one(),
+            ConnectionControl::Shutdown => {
+                let _ = self.tx.send(Outgoing::Shutdown).await;
+            }
+            ConnectionControl::Abort => {
+                self.socket.set_linger(Some(Duration::ZERO))?;
+                self.writer.abort();
+                self.close.notify_one();
+            }
+            ConnectionControl::SetOption(option) => option.apply(&self.socket)?,
+        }
+        Ok(())
+    }
 }
 
 impl TcpServerHandler {
-    /// 创建新的TCP服务器处理器
-    pub fn new(local_addr: SocketAddr) -> Self {
+    /// 创建新的TCP服务器处理器，同时监听 `local_addrs` 中的每个地址
+    pub fn new(local_addrs: Vec<SocketAddr>, options: SessionOptions) -> Self {
         Self {
-            local_addr,
+            local_addrs,
             clients: Arc::new(RwLock::new(HashMap::new())),
             control_tx: None,
             ui_to_server_tx: None,
             ui_to_server_rx: None,
             server_to_ui_tx: None,
+            options,
             running: false,
         }
     }
@@ -96,23 +224,132 @@ impl ProtocolHandler for TcpServerHandler {
         self.control_tx = Some(control_tx);
         self.running = true;
 
-        // 绑定监听地址
-        let listener = TcpListener::bind(self.local_addr).await?;
+        // 绑定所有监听地址
+        let listeners = self
+            .local_addrs
+            .iter()
+            .map(|addr| self.options.socket.tcp_listener(*addr))
+            .collect::<std::io::Result<Vec<_>>>()?;
 
         let clients = Arc::clone(&self.clients);
         let server_to_ui_tx = self.server_to_ui_tx.clone();
+        let options = self.options.clone();
+
+        // 将UI发来的消息转发给目标客户端 (未指定目标时广播)
+        if let Some(mut ui_to_server_rx) = self.ui_to_server_rx.take() {
+            let clients = Arc::clone(&self.clients);
+            let options = options.clone();
+            let server_to_ui_tx = self.server_to_ui_tx.clone();
+            tokio::spawn(async move {
+                while let Some(msg) = ui_to_server_rx.recv().await {
+                    let clients = clients.read().await;
+                    let targets: Vec<&TcpClientInfo> = match &msg.connection_info {
+                        Some(info) => clients.get(&info.connection_id).into_iter().collect(),
+                        None => clients.values().collect(),
+                    };
+
+                    if let MessageType::Control(control) = msg.content {
+                        for client in targets {
+                            let info = client.connection_info();
+                            let control = options.faults.get(Some(&info.connection_id)).close_control(control);
+                            // 半关闭由写入任务在发送完之前的数据后上报
+                            if matches!(control, ConnectionControl::Close | ConnectionControl::Abort) {
+                                report_event(server_to_ui_tx.as_ref(), &options, &info, SessionEvent::LocalClose(control)).await;
+                            }
+                            if let Err(e) = client.apply_control(control).await {
+                                report_option_error(server_to_ui_tx.as_ref(), &info, control.socket_option(), e).await;
+                            }
+                        }
+                        continue;
+                    }
+                    let Some(frame) = frame_outgoing(server_to_ui_tx.as_ref(), &options, &msg.content).await else {
+                        continue;
+                    };
+                    for client in targets {
+                        let _ = client.tx.send(Outgoing::Data(frame.clone(), msg.ack.clone())).await;
+                    }
+                }
+            });
+        }
 
         // 启动服务器监听任务
         tokio::spawn(async move {
             loop {
                 tokio::select! {
                     // 处理新的客户端连接
-                    result = listener.accept() => {
+                    result = accept_any(&listeners) => {
                         match result {
                             Ok((stream, addr)) => {
                                 // 为每个客户端创建处理任务
                                 let client_id = addr.to_string();
-                                let (client_tx, mut client_rx) = channel::<Bytes>(100);
+                                let local_addr = stream.local_addr().ok();
+                                let client_info = ConnectionInfo {
+                                    remote_addr: addr,
+                                    connection_id: client_id.clone(),
+                                    local_addr,
+                                };
+                                let socket = SockRef::from(&stream);
+                                if let Err(e) = options.socket.apply(&socket, true) {
+                                    report_option_error(server_to_ui_tx.as_ref(), &client_info, None, e).await;
+                                }
+                                let Ok(socket) = socket.try_clone() else {
+                                    continue;
+                                };
+                                let (client_tx, mut client_rx) = channel::<Outgoing>(100);
+
+                                // 自动回复直接写入该客户端
+                                let reply_tx = client_tx.clone();
+                                let close = Arc::new(Notify::new());
+                                let close_for_read = Arc::clone(&close);
+
+                                // 分离读写流
+                                let (mut read_half, write_half) = stream.into_split();
+                                let mut write_half = WriteHalf(Some(write_half));
+
+                                // 处理客户端写入任务
+                                let options_for_write = options.clone();
+                                let server_to_ui_tx_for_write = server_to_ui_tx.clone();
+                                let write_info = client_info.clone();
+                                let writer = tokio::spawn(async move {
+                                    let mut write_closed = false;
+                                    while let Some(outgoing) = client_rx.recv().await {
+                                        let (data, ack) = match outgoing {
+                                            Outgoing::Data(data, ack) => (data, ack),
+                                            Outgoing::Reply(reply) => match frame_outgoing(server_to_ui_tx_for_write.as_ref(), &options_for_write, &MessageType::Binary(reply)).await {
+                                                Some(data) => (data, WriteAck::default()),
+                                                None => continue,
+                                            },
+                                            Outgoing::Shutdown if write_closed => continue,
+                                            Outgoing::Shutdown => {
+                                                if let Err(e) = write_half.shutdown().await {
+                                                    let event = SessionEvent::IoError(format!("shutdown: {}", e));
+                                                    report_event(server_to_ui_tx_for_write.as_ref(), &options_for_write, &write_info, event).await;
+                                                    break;
+                                                }
+                                                write_closed = true;
+                                                let event = SessionEvent::LocalClose(ConnectionControl::Shutdown);
+                                                report_event(server_to_ui_tx_for_write.as_ref(), &options_for_write, &write_info, event).await;
+                                                continue;
+                                            }
+                                        };
+                                        if write_closed {
+                                            let event = SessionEvent::WriteClosed { bytes: data.len() };
+                                            report_event(server_to_ui_tx_for_write.as_ref(), &options_for_write, &write_info, event).await;
+                                            continue;
+                                        }
+                                        let frame = match write_impaired(&mut write_half, data, server_to_ui_tx_for_write.as_ref(), &options_for_write, &write_info).await {
+                                            Ok(frame) => frame,
+                                            Err(e) => {
+                                                println!("向客户端 {} 发送数据时出错: {}", addr, e);
+                                                break;
+                                            }
+                                        };
+                                        ack.written(&write_info, &frame);
+                                        options_for_write.capture.record(MessageDirection::Sent, Some(&write_info), LogEvent::Data(frame));
+                                    }
+
+                                    drop(write_half);
+                                });
 
                                 // 保存客户端信息
                                 {
@@ -120,70 +357,97 @@ impl ProtocolHandler for TcpServerHandler {
                                     clients_lock.insert(client_id.clone(), TcpClientInfo {
                                         addr,
                                         tx: client_tx,
+                                        close,
+                                        writer: writer.abort_handle(),
+                                        socket,
+                                    });
+                                }
+
+                                // 故障注入: 到时由本端关闭连接
+                                let fault = options.faults.get(Some(&client_id));
+                                if let Some(after) = fault.close_after {
+                                    let clients = Arc::clone(&clients);
+                                    let client_id = client_id.clone();
+                                    let server_to_ui_tx = server_to_ui_tx.clone();
+                                    let options = options.clone();
+                                    tokio::spawn(async move {
+                                        tokio::time::sleep(after).await;
+                                        let clients = clients.read().await;
+                                        let Some(client) = clients.get(&client_id) else {
+                                            return;
+                                        };
+                                        let info = client.connection_info();
+                                        report_event(server_to_ui_tx.as_ref(), &options, &info, SessionEvent::Fault(FaultEvent::Closed { after })).await;
+                                        let control = fault.close_control(ConnectionControl::Close);
+                                        if let Err(e) = client.apply_control(control).await {
+                                            report_option_error(server_to_ui_tx.as_ref(), &info, control.socket_option(), e).await;
+                                        }
                                     });
                                 }
 
                                 // 通知UI有新连接
+                                options.capture.record(MessageDirection::Received, Some(&client_info), LogEvent::Connected);
                                 if let Some(ref server_to_ui_sender) = server_to_ui_tx {
                                     let _ = server_to_ui_sender.send(Message {
                                         content: MessageType::ClientConnected,
                                         direction: MessageDirection::Received,
                                         timestamp: chrono::Local::now(),
-                                        connection_info: Some(ConnectionInfo {
-                                           remote_addr: addr,
-                                           connection_id: client_id.clone(),
-                                        }),
+                                        connection_info: Some(client_info.clone()),
+                                        ack: WriteAck::default(),
                                     }).await;
                                 }
 
-                                // 分离读写流
-                                let (mut read_half, mut write_half) = stream.into_split();
+                                let options = options.clone();
                                 let clients_for_read = Arc::clone(&clients);
                                 let server_to_ui_tx_for_read = server_to_ui_tx.clone();
 
                                 // 处理客户端读取任务
                                 let read_client_id = client_id.clone();
+                                let mut decoder = FrameDecoder::new(options.framing.clone());
                                 tokio::spawn(async move {
-                                    let mut buffer = vec![0u8; 4096];
+                                    let connection_info = ConnectionInfo {
+                                        remote_addr: addr,
+                                        connection_id: read_client_id.clone(),
+                                        local_addr,
+                                    };
                                     loop {
-                                        match read_half.read(&mut buffer).await {
-                                            Ok(0) => {
+                                        // 收到关闭命令时按对端关闭处理
+                                        let result = tokio::select! {
+                                            result = decoder.read_frames(&mut read_half) => result,
+                                            _ = close_for_read.notified() => Ok(None),
+                                        };
+                                        match result {
+                                            Ok(None) => {
+                                                // 连接关闭前上报缓冲中的剩余数据
+                                                if let Some(frame) = decoder.flush() {
+                                                    forward_frame(server_to_ui_tx_for_read.as_ref(), &options, frame, &connection_info).await;
+                                                }
+
                                                 // 从客户端列表中移除
                                                 {
                                                     let mut clients_lock = clients_for_read.write().await;
                                                     clients_lock.remove(&read_client_id);
                                                 }
+                                                options.faults.forget(&read_client_id);
 
                                                 // 通知UI连接断开
+                                                options.capture.record(MessageDirection::Received, Some(&connection_info), LogEvent::Disconnected);
                                                 if let Some(ref server_to_ui_sender) = server_to_ui_tx_for_read {
                                                     let _ = server_to_ui_sender.send(Message {
                                                         direction: MessageDirection::Received,
                                                         content: MessageType::ClientDisconnected,
                                                         timestamp: chrono::Local::now(),
-                                                        connection_info: Some(ConnectionInfo {
-                                                            remote_addr: addr,
-                                                            connection_id: read_client_id.clone(),
-                                                        }),
+                                                        connection_info: Some(connection_info.clone()),
+                                                        ack: WriteAck::default(),
                                                     }).await;
                                                 }
                                                 break;
                                             }
-                                            Ok(n) => {
-                                                // 接收到数据
-                                                let data = &buffer[..n];
-                                                let message_content = String::from_utf8_lossy(data).to_string();
-
-                                                // 发送到UI
-                                                if let Some(ref server_to_ui_sender) = server_to_ui_tx_for_read {
-                                                    let _ = server_to_ui_sender.send(Message {
-                                                        direction: MessageDirection::Received,
-                                                        content: MessageType::Text(message_content.clone()),
-                                                        timestamp: chrono::Local::now(),
-                                                        connection_info: Some(ConnectionInfo {
-                                                            remote_addr: addr,
-                                                            connection_id: read_client_id.clone(),
-                                                        }),
-                                                    }).await;
+                                            Ok(Some(frames)) => {
+                                                // 每个完整帧作为一条消息发送到UI
+                                                for frame in frames {
+                                                    let hits = forward_frame(server_to_ui_tx_for_read.as_ref(), &options, frame, &connection_info).await;
+                                                    spawn_auto_replies(hits, &reply_tx, Outgoing::Reply);
                                                 }
                                             }
                                             Err(e) => {
@@ -195,18 +459,6 @@ impl ProtocolHandler for TcpServerHandler {
 
                                     drop(read_half);
                                 });
-
-                                // 处理客户端写入任务
-                                tokio::spawn(async move {
-                                    while let Some(data) = client_rx.recv().await {
-                                        if let Err(e) = write_half.write_all(&data).await {
-                                            println!("向客户端 {} 发送数据时出错: {}", addr, e);
-                                            break;
-                                        }
-                                    }
-
-                                    drop(write_half);
-                                });
                             }
                             Err(e) => {
                                 println!("接受客户端连接时出错: {}", e);
@@ -249,28 +501,22 @@ impl ProtocolHandler for TcpServerHandler {
     }
 
     async fn send_message(&mut self, message: MessageType, target: Option<String>) -> Result<()> {
-        let data = match message {
-            MessageType::Text(text) => Bytes::from(text.into_bytes()),
-            MessageType::Binary(bytes) => bytes,
-            MessageType::Hex(hex_str) => {
-                // 将十六进制字符串转换为字节
-                let bytes = hex_decode(&hex_str).unwrap_or_default();
-                Bytes::from(bytes)
-            }
-            _ => return Ok(()), // 不支持发送其他类型的消息
+        // 不支持发送非数据类消息
+        let Some(data) = frame_outgoing(self.server_to_ui_tx.as_ref(), &self.options, &message).await else {
+            return Ok(());
         };
 
         if let Some(target_id) = target {
             // 发送到特定客户端
             let clients = self.clients.read().await;
             if let Some(client) = clients.get(&target_id) {
-                let _ = client.tx.send(data).await;
+                let _ = client.tx.send(Outgoing::Data(data, WriteAck::default())).await;
             }
         } else {
             // 广播到所有客户端
             let clients = self.clients.read().await;
             for (_, client) in clients.iter() {
-                let _ = client.tx.send(data.clone()).await;
+                let _ = client.tx.send(Outgoing::Data(data.clone(), WriteAck::default())).await;
             }
         }
 
@@ -294,13 +540,7 @@ impl ProtocolHandler for TcpServerHandler {
         let rt = tokio::runtime::Handle::current();
         rt.block_on(async {
             let clients = self.clients.read().await;
-            clients
-                .values()
-                .map(|client| ConnectionInfo {
-                    remote_addr: client.addr,
-                    connection_id: client.addr.to_string(),
-                })
-                .collect()
+            clients.values().map(TcpClientInfo::connection_info).collect()
         })
     }
 
@@ -313,154 +553,433 @@ impl ProtocolHandler for TcpServerHandler {
 pub struct TcpClientHandler {
     /// 本地地址
     local_addr: SocketAddr,
-    /// 远程服务器地址
+    /// 远程服务器地址 (首选地址)
     remote_addr: SocketAddr,
-    /// TCP 流
-    stream: Option<TcpStream>,
+    /// 远程主机的全部地址，按连接尝试顺序排列
+    remote_addrs: Vec<SocketAddr>,
     /// 控制通道 (用于停止客户端)
     control_tx: Option<Sender<()>>,
-    /// 消息接收通道
-    ui_to_server_rx: Option<Receiver<Message>>,
     /// 消息发送通道
     ui_to_server_tx: Option<Sender<Message>>,
     /// UI消息发送通道
     server_to_ui_tx: Option<Sender<Message>>,
+    /// 会话选项
+    options: SessionOptions,
     /// 运行状态
     running: bool,
 }
 
 impl TcpClientHandler {
-    /// 创建新的TCP客户端处理器
-    pub fn new(local_addr: SocketAddr, remote_addr: SocketAddr) -> Self {
+    /// 创建新的TCP客户端处理器，`remote_addrs` 不能为空
+    pub fn new(local_addr: SocketAddr, remote_addrs: Vec<SocketAddr>, options: SessionOptions) -> Self {
         Self {
             local_addr,
-            remote_addr,
-            stream: None,
+            remote_addr: remote_addrs[0],
+            remote_addrs,
             control_tx: None,
-            ui_to_server_rx: None,
             ui_to_server_tx: None,
             server_to_ui_tx: None,
+            options,
             running: false,
         }
     }
 }
 
-#[async_trait]
-impl ProtocolHandler for TcpClientHandler {
-    async fn start(&mut self) -> Result<()> {
-        // 连接到远程服务器
-        let stream = TcpStream::connect(self.remote_addr).await?;
-        self.stream = Some(stream);
-        self.running = true;
+/// 断开期间最多排队的消息数
+const OFFLINE_QUEUE_LIMIT: usize = 1000;
 
-        // 创建消息通道
-        let (ui_to_server_tx, ui_to_server_rx) = channel::<Message>(100);
-        self.ui_to_server_tx = Some(ui_to_server_tx);
-        self.ui_to_server_rx = Some(ui_to_server_rx);
+/// 单次连接的结束原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinkEnd {
+    /// 对端关闭或读写出错，按策略重连
+    Lost,
+    /// 用户关闭连接或停止客户端
+    Closed,
+}
 
-        // 获取流的引用用于读取任务
-        let stream = self.stream.as_ref().unwrap();
-        let (mut read_half, mut write_half) = stream.into_split();
-        let server_to_ui_tx = self.server_to_ui_tx.clone();
-        let remote_addr = self.remote_addr;
+/// TCP 客户端会话: 管理连接、按策略重连，以及断开期间的待发送消息
+struct ClientSession {
+    local_addr: SocketAddr,
+    /// 当前连接的地址 (连接前为首选地址)
+    remote_addr: SocketAddr,
+    remote_addrs: Vec<SocketAddr>,
+    options: SessionOptions,
+    server_to_ui_tx: Option<Sender<Message>>,
+    /// 自动回复经由发送通道写入
+    reply_tx: Sender<Message>,
+    /// 断开期间排队的消息
+    queue: VecDeque<Message>,
+}
 
-        // 启动读取任务
-        tokio::spawn(async move {
-            let mut buffer = vec![0u8; 4096];
-            loop {
-                match read_half.read(&mut buffer).await {
-                    Ok(0) => {
-                        // 服务器断开连接
-                        if let Some(ref tx) = server_to_ui_tx {
-                            let _ = tx.send(Message {
-                                content: MessageType::ClientDisconnected,
-                                direction: MessageDirection::Received,
-                                timestamp: chrono::Local::now(),
-                                connection_info: Some(ConnectionInfo {
-                                    remote_addr,
-                                    connection_id: remote_addr.to_string(),
-                                }),
-                            }).await;
+impl ClientSession {
+    fn connection_info(&self) -> ConnectionInfo {
+        client_connection_info(&self.options, self.remote_addr)
+    }
+
+    async fn notify(&self, content: MessageType) {
+        if let Some(ref tx) = self.server_to_ui_tx {
+            let _ = tx.send(Message::new_received(content, Some(self.connection_info()))).await;
+        }
+    }
+
+    /// 上报会话事件并写入会话记录
+    async fn report(&self, event: SessionEvent) {
+        report_event(self.server_to_ui_tx.as_ref(), &self.options, &self.connection_info(), event).await;
+    }
+
+    async fn set_link(&self, state: LinkState) {
+        if self.options.reconnect.is_some() {
+            self.notify(MessageType::Event(SessionEvent::Link(state))).await;
+        }
+    }
+
+    /// 连接断开期间收到的消息按设置排队或丢弃，返回 false 表示收到关闭命令
+    async fn hold(&mut self, msg: Message) -> bool {
+        match msg.content {
+            MessageType::Control(ConnectionControl::Close | ConnectionControl::Abort) => return false,
+            MessageType::Control(ConnectionControl::Shutdown) => return true,
+            // 断开期间修改的选项在重新连接时生效
+            MessageType::Control(ConnectionControl::SetOption(option)) => {
+                self.options.socket.update(option);
+                return true;
+            }
+            _ => {}
+        }
+        let Some(bytes) = self.options.outgoing_payload(&msg.content).map(|data| data.len()) else {
+            return true;
+        };
+        let queued = self.options.offline == OfflineSend::Queue && self.queue.len() < OFFLINE_QUEUE_LIMIT;
+        if queued {
+            self.queue.push_back(msg);
+        }
+        self.notify(MessageType::Event(SessionEvent::Unsent { bytes, queued })).await;
+        true
+    }
+
+    /// 等待 `future` 完成，期间收到的消息交给 [`Self::hold`] 处理；收到关闭或停止命令时返回 None
+    async fn offline<F: Future>(
+        &mut self,
+        future: F,
+        rx: &mut Receiver<Message>,
+        control_rx: &mut Receiver<()>,
+    ) -> Option<F::Output> {
+        tokio::pin!(future);
+        loop {
+            tokio::select! {
+                output = &mut future => return Some(output),
+                msg = rx.recv() => match msg {
+                    Some(msg) => {
+                        if !self.hold(msg).await {
+                            return None;
                         }
-                        break;
                     }
-                    Ok(n) => {
-                        let data = &buffer[..n];
-                        let message_content = String::from_utf8_lossy(data).to_string();
-
-                        if let Some(ref tx) = server_to_ui_tx {
-                            let _ = tx.send(Message {
-                                content: MessageType::Text(message_content),
-                                direction: MessageDirection::Received,
-                                timestamp: chrono::Local::now(),
-                                connection_info: Some(ConnectionInfo {
-                                    remote_addr,
-                                    connection_id: remote_addr.to_string(),
-                                }),
-                            }).await;
+                    None => return None,
+                },
+                _ = control_rx.recv() => return None,
+            }
+        }
+    }
+
+    /// 写入一条消息，记录实际发送的帧
+    async fn write(&self, write_half: &mut WriteHalf, msg: &Message) -> std::io::Result<()> {
+        let Some(frame) = frame_outgoing(self.server_to_ui_tx.as_ref(), &self.options, &msg.content).await else {
+            return Ok(());
+        };
+        let timeouts = self.options.timeouts;
+        let info = self.connection_info();
+        let write = write_impaired(write_half, frame, self.server_to_ui_tx.as_ref(), &self.options, &info);
+        let Some(result) = timeout::within(timeouts.write, write).await else {
+            // 写入超时: 按配置关闭连接或丢弃该消息
+            let expired = timeouts.expired(TimeoutKind::Write).expect("write timeout is configured");
+            report_timeout(self.server_to_ui_tx.as_ref(), &self.options, &self.connection_info(), expired).await;
+            if expired.closed {
+                return Err(std::io::ErrorKind::TimedOut.into());
+            }
+            return Ok(());
+        };
+        let frame = result?;
+        msg.ack.written(&info, &frame);
+        self.options.capture.record(MessageDirection::Sent, Some(&info), LogEvent::Data(frame));
+        Ok(())
+    }
+
+    /// 设置 SO_LINGER 为 0，使写入端释放时发送 RST 中止连接
+    async fn abort(&self, write_half: &WriteHalf) {
+        // WriteHalf 释放时检查 SO_LINGER，为 0 时不发送 FIN
+        let linger = SocketOption::Linger(Some(Duration::ZERO));
+        match linger.apply(&SockRef::from(write_half.as_ref())) {
+            Ok(()) => self.report(SessionEvent::LocalClose(ConnectionControl::Abort)).await,
+            Err(e) => report_option_error(self.server_to_ui_tx.as_ref(), &self.connection_info(), linger, e).await,
+        }
+    }
+
+    /// 运行会话直到停止；`stream` 为已建立的首个连接，None 时由重连策略负责首次连接
+    async fn run(mut self, mut stream: Option<TcpStream>, mut rx: Receiver<Message>, mut control_rx: Receiver<()>) {
+        // 上次连接断开 (或首次连接失败) 后已进行的重连次数
+        let mut attempts = 0u32;
+        let mut first = stream.is_none();
+        loop {
+            let stream = match stream.take() {
+                Some(stream) => stream,
+                None => {
+                    let Some(policy) = self.options.reconnect.clone() else {
+                        break;
+                    };
+                    if first {
+                        first = false;
+                        self.set_link(LinkState::Connecting).await;
+                    } else {
+                        if policy.max_attempts.is_some_and(|max| attempts >= max) {
+                            break;
+                        }
+                        let delay = policy.delay(attempts);
+                        attempts += 1;
+                        self.set_link(LinkState::Reconnecting {
+                            attempt: attempts,
+                            max_attempts: policy.max_attempts,
+                            delay,
+                        })
+                        .await;
+                        if self.offline(tokio::time::sleep(delay), &mut rx, &mut control_rx).await.is_none() {
+                            break;
                         }
                     }
-                    Err(e) => {
-                        println!("读取数据时出错: {}", e);
-                        break;
+
+                    let remote_addrs = self.remote_addrs.clone();
+                    let options = self.options.clone();
+                    let timeouts = options.timeouts;
+                    let ui_tx = self.server_to_ui_tx.clone();
+                    let connecting = connect(self.local_addr, &remote_addrs, &options, ui_tx.as_ref());
+                    match self.offline(connecting, &mut rx, &mut control_rx).await {
+                        Some(Ok(stream)) => stream,
+                        Some(Err(e)) => {
+                            // 系统返回的超时 (未配置连接超时) 按连接失败上报，错误中已包含各地址
+                            let event = match timeouts.expired(TimeoutKind::Connect) {
+                                Some(expired) if e.kind() == std::io::ErrorKind::TimedOut => SessionEvent::Timeout(expired),
+                                _ => SessionEvent::ConnectFailed(e.to_string()),
+                            };
+                            self.notify(MessageType::Event(event)).await;
+                            continue;
+                        }
+                        None => break,
                     }
                 }
+            };
+
+            attempts = 0;
+            if let Ok(peer) = stream.peer_addr() {
+                self.remote_addr = peer;
             }
-        });
+            self.set_link(LinkState::Connected).await;
+            if self.serve(stream, &mut rx, &mut control_rx).await == LinkEnd::Closed {
+                break;
+            }
+        }
+        self.set_link(LinkState::Disconnected).await;
+    }
 
-        // 启动写入任务
-        let ui_to_server_rx = self.ui_to_server_rx.take().unwrap();
-        tokio::spawn(async move {
-            let mut rx = ui_to_server_rx;
-            while let Some(msg) = rx.recv().await {
-                let data = match msg.content {
-                    MessageType::Text(text) => Bytes::from(text.into_bytes()),
-                    MessageType::Binary(bytes) => bytes,
-                    _ => continue,
+    /// 在已建立的连接上收发数据直到连接结束
+    async fn serve(&mut self, stream: TcpStream, rx: &mut Receiver<Message>, control_rx: &mut Receiver<()>) -> LinkEnd {
+        let connection_info = self.connection_info();
+        let (mut read_half, write_half) = stream.into_split();
+        let mut write_half = WriteHalf(Some(write_half));
+
+        // 通知 UI 已连接
+        self.options.capture.record(MessageDirection::Received, Some(&connection_info), LogEvent::Connected);
+        self.notify(MessageType::ClientConnected).await;
+
+        // 启动读取任务，自动回复经由写入通道发送
+        let mut decoder = FrameDecoder::new(self.options.framing.clone());
+        let options = self.options.clone();
+        let server_to_ui_tx = self.server_to_ui_tx.clone();
+        let reply_tx = self.reply_tx.clone();
+        let close = Arc::new(Notify::new());
+        let close_for_read = Arc::clone(&close);
+        let mut reader = tokio::spawn(async move {
+            let mut idle_deadline = options.timeouts.idle_deadline();
+            loop {
+                // 收到关闭命令时按对端关闭处理
+                let result = tokio::select! {
+                    result = decoder.read_frames(&mut read_half) => result,
+                    _ = close_for_read.notified() => Ok(None),
+                    _ = timeout::sleep_until(idle_deadline) => {
+                        // 每段空闲只提示一次，收到数据后重新计时
+                        idle_deadline = None;
+                        let expired = options.timeouts.expired(TimeoutKind::ReadIdle).expect("idle timeout is configured");
+                        report_timeout(server_to_ui_tx.as_ref(), &options, &connection_info, expired).await;
+                        if !expired.closed {
+                            continue;
+                        }
+                        Ok(None)
+                    }
                 };
+                match result {
+                    Ok(Some(frames)) => {
+                        idle_deadline = options.timeouts.idle_deadline();
+                        for frame in frames {
+                            let hits = forward_frame(server_to_ui_tx.as_ref(), &options, frame, &connection_info).await;
+                            spawn_auto_replies(hits, &reply_tx, |reply| {
+                                Message::new_sent(MessageType::Binary(reply), None)
+                            });
+                        }
+                        continue;
+                    }
+                    Ok(None) => {
+                        // 连接关闭前上报缓冲中的剩余数据
+                        if let Some(frame) = decoder.flush() {
+                            forward_frame(server_to_ui_tx.as_ref(), &options, frame, &connection_info).await;
+                        }
+                    }
+                    Err(e) => println!("读取数据时出错: {}", e),
+                }
 
-                if let Err(e) = write_half.write_all(&data).await {
-                    println!("发送数据时出错: {}", e);
-                    break;
+                options.capture.record(MessageDirection::Received, Some(&connection_info), LogEvent::Disconnected);
+                if let Some(ref tx) = server_to_ui_tx {
+                    // 服务器断开连接
+                    let _ = tx
+                        .send(Message::new_received(MessageType::ClientDisconnected, Some(connection_info.clone())))
+                        .await;
                 }
+                break;
             }
         });
 
-        // 通知 UI 已连接
-        if let Some(ref tx) = self.server_to_ui_tx {
-            let _ = tx.send(Message {
-                content: MessageType::ClientConnected,
-                direction: MessageDirection::Received,
-                timestamp: chrono::Local::now(),
-                connection_info: Some(ConnectionInfo {
-                    remote_addr,
-                    connection_id: remote_addr.to_string(),
-                }),
-            }).await;
+        // 重新连接后先发送断开期间排队的消息
+        let mut end = None;
+        while let Some(msg) = self.queue.pop_front() {
+            if let Err(e) = self.write(&mut write_half, &msg).await {
+                println!("发送数据时出错: {}", e);
+                self.queue.push_front(msg);
+                end = Some(LinkEnd::Lost);
+                break;
+            }
         }
 
+        let mut reader_done = false;
+        // 本端已半关闭，对端随后关闭时不再重连
+        let mut write_closed = false;
+        let fault = self.options.faults.get(Some(&self.connection_info().connection_id));
+        let close_deadline = fault.close_deadline();
+        let end = match end {
+            Some(end) => end,
+            None => loop {
+                tokio::select! {
+                    _ = &mut reader => {
+                        reader_done = true;
+                        break if write_closed { LinkEnd::Closed } else { LinkEnd::Lost };
+                    }
+                    msg = rx.recv() => match msg {
+                        None => break LinkEnd::Closed,
+                        Some(Message { content: MessageType::Control(control), .. }) => match fault.close_control(control) {
+                            ConnectionControl::Close => {
+                                self.report(SessionEvent::LocalClose(control)).await;
+                                break LinkEnd::Closed;
+                            }
+                            ConnectionControl::Shutdown => {
+                                if write_closed {
+                                    continue;
+                                }
+                                if let Err(e) = write_half.shutdown().await {
+                                    self.report(SessionEvent::IoError(format!("shutdown: {}", e))).await;
+                                    break LinkEnd::Lost;
+                                }
+                                write_closed = true;
+                                self.report(SessionEvent::LocalClose(control)).await;
+                            }
+                            ConnectionControl::Abort => {
+                                self.abort(&write_half).await;
+                                break LinkEnd::Closed;
+                            }
+                            ConnectionControl::SetOption(option) => {
+                                self.options.socket.update(option);
+                                if let Err(e) = option.apply(&SockRef::from(write_half.as_ref())) {
+                                    report_option_error(self.server_to_ui_tx.as_ref(), &self.connection_info(), option, e).await;
+                                }
+                            }
+                        },
+                        Some(msg) if write_closed => {
+                            if let Some(data) = self.options.outgoing_payload(&msg.content) {
+                                self.report(SessionEvent::WriteClosed { bytes: data.len() }).await;
+                            }
+                        }
+                        Some(msg) => {
+                            if let Err(e) = self.write(&mut write_half, &msg).await {
+                                println!("发送数据时出错: {}", e);
+                                break LinkEnd::Lost;
+                            }
+                        }
+                    },
+                    // 故障注入: 到时由本端关闭连接，按连接丢失处理 (启用自动重连时重连)
+                    _ = timeout::sleep_until(close_deadline) => {
+                        let after = fault.close_after.unwrap_or_default();
+                        self.report(SessionEvent::Fault(FaultEvent::Closed { after })).await;
+                        if fault.abrupt_close {
+                            self.abort(&write_half).await;
+                        }
+                        break LinkEnd::Lost;
+                    }
+                    _ = control_rx.recv() => break LinkEnd::Closed,
+                }
+            },
+        };
+
+        // 等待读取任务上报断开后再进入重连
+        drop(write_half);
+        if !reader_done {
+            close.notify_one();
+            let _ = reader.await;
+        }
+        end
+    }
+}
+
+#[async_trait]
+impl ProtocolHandler for TcpClientHandler {
+    async fn start(&mut self) -> Result<()> {
+        // 未启用自动重连时直接连接，连接失败返回错误
+        let stream = match self.options.reconnect {
+            Some(_) => None,
+            None => Some(connect(self.local_addr, &self.remote_addrs, &self.options, self.server_to_ui_tx.as_ref()).await?),
+        };
+        self.running = true;
+
+        // 创建消息通道
+        let (ui_to_server_tx, ui_to_server_rx) = channel::<Message>(100);
+        let (control_tx, control_rx) = channel::<()>(1);
+        self.ui_to_server_tx = Some(ui_to_server_tx.clone());
+        self.control_tx = Some(control_tx);
+
+        let session = ClientSession {
+            local_addr: self.local_addr,
+            remote_addr: self.remote_addr,
+            remote_addrs: self.remote_addrs.clone(),
+            options: self.options.clone(),
+            server_to_ui_tx: self.server_to_ui_tx.clone(),
+            reply_tx: ui_to_server_tx,
+            queue: VecDeque::new(),
+        };
+        tokio::spawn(session.run(stream, ui_to_server_rx, control_rx));
+
         Ok(())
     }
 
     async fn stop(&mut self) -> Result<()> {
         self.running = false;
-        if let Some(mut stream) = self.stream.take() {
-            // 关闭连接
-            let _ = stream.shutdown().await;
+        if let Some(tx) = self.control_tx.take() {
+            // 关闭连接并停止重连
+            let _ = tx.send(()).await;
         }
         Ok(())
     }
 
-    async fn send_message(&mut self, message: MessageType, target: Option<String>) -> Result<()> {
+    async fn send_message(&mut self, message: MessageType, _target: Option<String>) -> Result<()> {
         if let Some(ref tx) = self.ui_to_server_tx {
             let msg = Message {
                 content: message,
                 direction: MessageDirection::Sent,
                 timestamp: chrono::Local::now(),
-                connection_info: Some(ConnectionInfo {
-                    remote_addr: self.remote_addr,
-                    connection_id: self.remote_addr.to_string(),
-                }),
+                connection_info: Some(client_connection_info(&self.options, self.remote_addr)),
+                ack: WriteAck::default(),
             };
             let _ = tx.send(msg).await;
         }
@@ -481,10 +1000,7 @@ impl ProtocolHandler for TcpClientHandler {
 
     fn get_connections(&self) -> Vec<ConnectionInfo> {
         if self.running {
-            vec![ConnectionInfo {
-                remote_addr: self.remote_addr,
-                connection_id: self.remote_addr.to_string(),
-            }]
+            vec![client_connection_info(&self.options, self.remote_addr)]
         } else {
             vec![]
         }
@@ -494,3 +1010,160 @@ impl ProtocolHandler for TcpClientHandler {
         "TCP Client"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::reconnect::{Backoff, ReconnectPolicy};
+    use crate::protocols::timeout::Timeouts;
+    use tokio::io::AsyncReadExt;
+
+    /// 等待满足条件的下一条 UI 消息，跳过其他消息
+    async fn wait_for(rx: &mut Receiver<Message>, matches: impl Fn(&MessageType) -> bool) -> Message {
+        let wait = async {
+            loop {
+                let message = rx.recv().await.expect("session ended");
+                if matches(&message.content) {
+                    return message;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), wait).await.expect("timed out waiting for message")
+    }
+
+    /// 启动连接到 `addr` 的客户端，返回 (客户端, UI 接收通道)
+    async fn start_client(addr: SocketAddr, options: SessionOptions) -> (TcpClientHandler, Receiver<Message>) {
+        let (ui_tx, ui_rx) = channel::<Message>(100);
+        let mut client = TcpClientHandler::new("0.0.0.0:0".parse().unwrap(), vec![addr], options);
+        client.set_server_to_ui_sender(ui_tx);
+        client.start().await.unwrap();
+        (client, ui_rx)
+    }
+
+    #[tokio::test]
+    async fn test_offline_queue_flushes_on_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let options = SessionOptions {
+            reconnect: Some(ReconnectPolicy {
+                backoff: Backoff::Fixed,
+                initial_delay: Duration::from_millis(200),
+                jitter: 0.0,
+                ..Default::default()
+            }),
+            offline: OfflineSend::Queue,
+            ..Default::default()
+        };
+        let (mut client, mut ui_rx) = start_client(listener.local_addr().unwrap(), options).await;
+
+        // 对端关闭后进入重连等待，期间发送的消息排队
+        let (first, _) = listener.accept().await.unwrap();
+        drop(first);
+        wait_for(&mut ui_rx, |content| {
+            matches!(content, MessageType::Event(SessionEvent::Link(LinkState::Reconnecting { .. })))
+        })
+        .await;
+        client.send_message(MessageType::Text("queued".to_string()), None).await.unwrap();
+        wait_for(&mut ui_rx, |content| {
+            matches!(content, MessageType::Event(SessionEvent::Unsent { queued: true, .. }))
+        })
+        .await;
+
+        let (mut second, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 6];
+        second.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"queued");
+        client.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_closes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let options = SessionOptions {
+            timeouts: Timeouts {
+                read_idle: Some(Duration::from_millis(50)),
+                close_on_timeout: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (_client, mut ui_rx) = start_client(listener.local_addr().unwrap(), options).await;
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        let message = wait_for(&mut ui_rx, |content| matches!(content, MessageType::Event(SessionEvent::Timeout(_)))).await;
+        match message.content {
+            MessageType::Event(SessionEvent::Timeout(expired)) => {
+                assert_eq!(expired.kind, TimeoutKind::ReadIdle);
+                assert!(expired.closed);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+        wait_for(&mut ui_rx, |content| matches!(content, MessageType::ClientDisconnected)).await;
+        // 超时关闭后对端读到 EOF
+        assert_eq!(server.read(&mut [0u8; 1]).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_still_reads() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (client, mut ui_rx) = start_client(listener.local_addr().unwrap(), SessionOptions::default()).await;
+        let (mut server, _) = listener.accept().await.unwrap();
+        let tx = client.get_ui_to_server_sender().unwrap();
+
+        tx.send(Message::new_sent(MessageType::Control(ConnectionControl::Shutdown), None)).await.unwrap();
+        assert_eq!(server.read(&mut [0u8; 1]).await.unwrap(), 0);
+        // 半关闭后仍能收到对端数据
+        server.write_all(b"late").await.unwrap();
+        let received = wait_for(&mut ui_rx, |content| !matches!(content, MessageType::Event(_) | MessageType::ClientConnected)).await;
+        assert_eq!(SessionOptions::default().outgoing_payload(&received.content).unwrap(), &b"late"[..]);
+
+        // 写入方向已关闭的消息不发送
+        tx.send(Message::new_sent(MessageType::Text("dropped".to_string()), None)).await.unwrap();
+        wait_for(&mut ui_rx, |content| {
+            matches!(content, MessageType::Event(SessionEvent::WriteClosed { bytes: 7 }))
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_abort_resets_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (client, mut ui_rx) = start_client(listener.local_addr().unwrap(), SessionOptions::default()).await;
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        let tx = client.get_ui_to_server_sender().unwrap();
+        tx.send(Message::new_sent(MessageType::Control(ConnectionControl::Abort), None)).await.unwrap();
+        wait_for(&mut ui_rx, |content| matches!(content, MessageType::ClientDisconnected)).await;
+        let error = server.read(&mut [0u8; 1]).await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::ConnectionReset);
+    }
+
+    #[tokio::test]
+    async fn test_server_listens_on_each_address() {
+        // 取得两个空闲端口后释放，由服务器重新监听
+        let mut addrs = Vec::new();
+        for ip in ["127.0.0.1", "127.0.0.2"] {
+            let probe = std::net::TcpListener::bind((ip, 0)).unwrap();
+            addrs.push(probe.local_addr().unwrap());
+        }
+        let (ui_tx, mut ui_rx) = channel::<Message>(100);
+        let mut server = TcpServerHandler::new(addrs.clone(), SessionOptions::default());
+        server.set_server_to_ui_sender(ui_tx);
+        server.start().await.unwrap();
+
+        let mut peers = Vec::new();
+        for addr in &addrs {
+            peers.push(TcpStream::connect(addr).await.unwrap());
+            let connected = wait_for(&mut ui_rx, |content| matches!(content, MessageType::ClientConnected)).await;
+            assert_eq!(connected.connection_info.unwrap().local_addr, Some(*addr));
+        }
+
+        // 未指定目标的消息发往所有监听地址上的客户端
+        server.send_message(MessageType::Text("hi".to_string()), None).await.unwrap();
+        for peer in &mut peers {
+            let mut buf = [0u8; 2];
+            peer.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"hi");
+        }
+        server.stop().await.unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+use std::{fmt, future::Future, time::Duration};
+use tokio::time::Instant;
+
+/// 超时类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// 建立连接超时
+    Connect,
+    /// 连接上超过指定时间没有收到数据
+    ReadIdle,
+    /// 写入数据超时 (对端长时间不读取)
+    Write,
+    /// 发送请求后超过指定时间没有收到响应 (UDP)
+    Response,
+}
+
+/// 超时事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutExpired {
+    pub kind: TimeoutKind,
+    /// 配置的超时时间
+    pub after: Duration,
+    /// 是否因此关闭了连接
+    pub closed: bool,
+}
+
+impl fmt::Display for TimeoutExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let after = self.after.as_secs_f64();
+        match self.kind {
+            TimeoutKind::Connect => write!(f, "timeout: connect not completed within {:.1}s", after)?,
+            TimeoutKind::ReadIdle => write!(f, "timeout: no data received for {:.1}s", after)?,
+            TimeoutKind::Write => write!(f, "timeout: write not completed within {:.1}s", after)?,
+            TimeoutKind::Response => write!(f, "timeout: no response within {:.1}s", after)?,
+        }
+        if self.closed {
+            write!(f, ", connection closed")?;
+        }
+        Ok(())
+    }
+}
+
+/// 连接超时配置，未设置的项不限时
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    pub connect: Option<Duration>,
+    /// 读空闲超时，UDP 客户端作为发送后等待响应的超时
+    pub read_idle: Option<Duration>,
+    pub write: Option<Duration>,
+    /// 读空闲、写入或响应超时后关闭连接
+    pub close_on_timeout: bool,
+}
+
+impl Timeouts {
+    /// 构造超时事件
+    pub fn expired(&self, kind: TimeoutKind) -> Option<TimeoutExpired> {
+        let after = match kind {
+            TimeoutKind::Connect => self.connect,
+            TimeoutKind::ReadIdle | TimeoutKind::Response => self.read_idle,
+            TimeoutKind::Write => self.write,
+        }?;
+        // 连接超时时连接尚未建立，无需关闭
+        let closed = kind != TimeoutKind::Connect && self.close_on_timeout;
+        Some(TimeoutExpired { kind, after, closed })
+    }
+
+    /// 读空闲计时的截止时间
+    pub fn idle_deadline(&self) -> Option<Instant> {
+        self.read_idle.map(|idle| Instant::now() + idle)
+    }
+}
+
+/// 在限定时间内等待 `future` 完成，超时返回 None
+pub async fn within<F: Future>(limit: Option<Duration>, future: F) -> Option<F::Output> {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+/// 等待到截止时间，没有截止时间时永不完成
+pub async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
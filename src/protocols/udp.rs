@@ -1,22 +1,129 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use socket2::SockRef;
+use std::{collections::HashMap, io, net::SocketAddr, sync::Arc};
 use tokio::net::UdpSocket;
 use tokio::sync::{mpsc::{Receiver, Sender, channel}, RwLock};
 
+use crate::capture::logger::LogEvent;
 use crate::protocols::common::{
-    ConnectionInfo, Message, MessageDirection, MessageType, ProtocolHandler,
+    ConnectionControl, ConnectionInfo, Message, MessageDirection, MessageType, ProtocolHandler, SessionEvent,
+    SessionOptions, WriteAck,
 };
+use crate::protocols::fault;
+use crate::protocols::multicast::UdpGroup;
+use crate::protocols::session::{forward_frame, frame_outgoing, report_event, report_option_error, report_timeout, spawn_auto_replies};
+use crate::protocols::timeout::{self, TimeoutKind};
+
+/// 单个数据报的最大长度
+const MAX_DATAGRAM: usize = 65536;
+
+fn peer_info(addr: SocketAddr) -> ConnectionInfo {
+    ConnectionInfo {
+        remote_addr: addr,
+        connection_id: addr.to_string(),
+        local_addr: None,
+    }
+}
+
+/// 通知 UI 连接状态变化并写入会话记录
+async fn notify_link(tx: Option<&Sender<Message>>, options: &SessionOptions, info: &ConnectionInfo, connected: bool) {
+    let (content, event) = match connected {
+        true => (MessageType::ClientConnected, LogEvent::Connected),
+        false => (MessageType::ClientDisconnected, LogEvent::Disconnected),
+    };
+    options.capture.record(MessageDirection::Received, Some(info), event);
+    if let Some(tx) = tx {
+        let _ = tx.send(Message::new_received(content, Some(info.clone()))).await;
+    }
+}
+
+/// 按故障注入和写入超时配置发送一个已分帧的数据报 (`peer` 为 None 时发往已连接的地址)，返回是否需要因超时关闭
+async fn send_datagram(
+    socket: &UdpSocket,
+    peer: Option<SocketAddr>,
+    data: &Bytes,
+    ack: &WriteAck,
+    options: &SessionOptions,
+    info: &ConnectionInfo,
+    ui_tx: Option<&Sender<Message>>,
+) -> bool {
+    let config = options.faults.get(Some(&info.connection_id));
+    let (datagram, event) = fault::impair_datagram(&config, data.clone()).await;
+    if let Some(event) = event {
+        report_event(ui_tx, options, info, SessionEvent::Fault(event)).await;
+    }
+    let Some(datagram) = datagram else {
+        return false;
+    };
+    let send = async {
+        match peer {
+            Some(peer) => socket.send_to(&datagram, peer).await,
+            None => socket.send(&datagram).await,
+        }
+    };
+    match timeout::within(options.timeouts.write, send).await {
+        Some(Ok(_)) => {
+            ack.written(info, &datagram);
+            options.capture.record(MessageDirection::Sent, Some(info), LogEvent::Data(datagram));
+            false
+        }
+        Some(Err(e)) => {
+            // 对端端口不可达等错误以事件提示，不影响后续发送
+            let reason = format!("send to {}: {}", info.remote_addr, e);
+            report_event(ui_tx, options, info, SessionEvent::IoError(reason)).await;
+            false
+        }
+        None => {
+            let expired = options.timeouts.expired(TimeoutKind::Write).expect("write timeout is configured");
+            report_timeout(ui_tx, options, info, expired).await;
+            expired.closed
+        }
+    }
+}
+
+/// 等待任一套接字收到数据报，返回套接字序号和接收结果
+/// 接收错误是否只影响个别数据报 (如之前发出的数据报被 ICMP 端口不可达拒收)，可以继续接收
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+    )
+}
+
+async fn recv_any(sockets: &[Arc<UdpSocket>], bufs: &mut [Vec<u8>]) -> (usize, std::io::Result<(usize, SocketAddr)>) {
+    let recvs = sockets
+        .iter()
+        .zip(bufs.iter_mut())
+        .enumerate()
+        .map(|(index, (socket, buf))| Box::pin(async move { (index, socket.recv_from(buf).await) }));
+    futures_util::future::select_all(recvs).await.0
+}
+
+/// 选择发往 `peer` 的套接字: 已知对端使用收到其数据的套接字，否则使用地址族相同的第一个套接字
+fn socket_for(sockets: &[Arc<UdpSocket>], clients: &HashMap<SocketAddr, usize>, peer: SocketAddr) -> Arc<UdpSocket> {
+    let socket = clients.get(&peer).and_then(|index| sockets.get(*index)).or_else(|| {
+        sockets
+            .iter()
+            .find(|socket| socket.local_addr().is_ok_and(|local| local.is_ipv4() == peer.is_ipv4()))
+    });
+    Arc::clone(socket.unwrap_or(&sockets[0]))
+}
 
 /// UDP 服务器处理器
 pub struct UdpServerHandler {
-    /// 本地地址
-    local_addr: SocketAddr,
-    /// UDP 套接字
-    socket: Option<Arc<UdpSocket>>,
-    /// 已知客户端
-    clients: Arc<RwLock<HashMap<SocketAddr, String>>>,
+    /// 监听地址
+    local_addrs: Vec<SocketAddr>,
+    /// 每个监听地址的 UDP 套接字
+    sockets: Vec<Arc<UdpSocket>>,
+    /// 已知客户端 (收到过数据报的对端) 及收到其数据的套接字序号
+    clients: Arc<RwLock<HashMap<SocketAddr, usize>>>,
     /// 控制通道 (用于停止服务器)
     control_tx: Option<Sender<()>>,
     /// 消息接收通道
@@ -25,21 +132,24 @@ pub struct UdpServerHandler {
     message_tx: Option<Sender<Message>>,
     /// UI消息发送通道
     ui_tx: Option<Sender<Message>>,
+    /// 会话选项
+    options: SessionOptions,
     /// 运行状态
     running: bool,
 }
 
 impl UdpServerHandler {
-    /// 创建新的UDP服务器处理器
-    pub fn new(local_addr: SocketAddr) -> Self {
+    /// 创建新的UDP服务器处理器，同时监听 `local_addrs` 中的每个地址
+    pub fn new(local_addrs: Vec<SocketAddr>, options: SessionOptions) -> Self {
         Self {
-            local_addr,
-            socket: None,
+            local_addrs,
+            sockets: Vec::new(),
             clients: Arc::new(RwLock::new(HashMap::new())),
             control_tx: None,
             message_rx: None,
             message_tx: None,
             ui_tx: None,
+            options,
             running: false,
         }
     }
@@ -48,36 +158,171 @@ impl UdpServerHandler {
 #[async_trait]
 impl ProtocolHandler for UdpServerHandler {
     async fn start(&mut self) -> Result<()> {
-        anyhow::bail!("UDP server handler not yet fully implemented")
+        let sockets = self
+            .local_addrs
+            .iter()
+            .map(|addr| self.options.socket.udp_bind(*addr).map(Arc::new))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        if let Some(group) = &self.options.udp_group {
+            for socket in &sockets {
+                group.join(&SockRef::from(&**socket))?;
+            }
+        }
+        self.sockets = sockets.clone();
+
+        // 创建消息通道
+        let (message_tx, message_rx) = channel::<Message>(100);
+        let (control_tx, mut control_rx) = channel::<()>(1);
+        self.message_tx = Some(message_tx.clone());
+        self.message_rx = Some(message_rx);
+        self.control_tx = Some(control_tx);
+        self.running = true;
+
+        // 将UI发来的消息发往目标对端 (未指定目标时发往组播组或广播地址，普通服务器发往所有已知对端)
+        if let Some(mut message_rx) = self.message_rx.take() {
+            let sockets = sockets.clone();
+            let local_addrs = self.local_addrs.clone();
+            let clients = Arc::clone(&self.clients);
+            let options = self.options.clone();
+            let ui_tx = self.ui_tx.clone();
+            tokio::spawn(async move {
+                while let Some(msg) = message_rx.recv().await {
+                    let targets: Vec<SocketAddr> = match (&msg.connection_info, &options.udp_group) {
+                        (Some(info), _) => vec![info.remote_addr],
+                        (None, Some(group)) => vec![group.destination()],
+                        (None, None) => clients.read().await.keys().copied().collect(),
+                    };
+
+                    match msg.content {
+                        // 无连接协议的关闭只是将对端移出列表，半关闭无意义
+                        MessageType::Control(ConnectionControl::Close | ConnectionControl::Abort) => {
+                            for peer in targets {
+                                if clients.write().await.remove(&peer).is_some() {
+                                    notify_link(ui_tx.as_ref(), &options, &peer_info(peer), false).await;
+                                }
+                            }
+                            continue;
+                        }
+                        // 所有对端共用监听套接字
+                        MessageType::Control(ConnectionControl::SetOption(option)) => {
+                            for (socket, local_addr) in sockets.iter().zip(&local_addrs) {
+                                if let Err(e) = option.apply(&SockRef::from(&**socket)) {
+                                    let info = peer_info(*local_addr);
+                                    report_option_error(ui_tx.as_ref(), &info, option, e).await;
+                                }
+                            }
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    let Some(data) = frame_outgoing(ui_tx.as_ref(), &options, &msg.content).await else {
+                        continue;
+                    };
+                    for peer in targets {
+                        let socket = socket_for(&sockets, &*clients.read().await, peer);
+                        send_datagram(&socket, Some(peer), &data, &msg.ack, &options, &peer_info(peer), ui_tx.as_ref()).await;
+                    }
+                }
+            });
+        }
+
+        // 接收任务: 每个数据报作为一帧上报，首次收到某个对端的数据时视为新连接
+        let clients = Arc::clone(&self.clients);
+        let options = self.options.clone();
+        let ui_tx = self.ui_tx.clone();
+        tokio::spawn(async move {
+            let mut bufs = vec![vec![0u8; MAX_DATAGRAM]; sockets.len()];
+            loop {
+                tokio::select! {
+                    (index, result) = recv_any(&sockets, &mut bufs) => {
+                        let (len, peer) = match result {
+                            Ok(received) => received,
+                            // 之前发出的数据报被拒收等错误以事件提示后继续接收，其他错误停止接收
+                            Err(e) => {
+                                let event = SessionEvent::IoError(format!("receive: {}", e));
+                                report_event(ui_tx.as_ref(), &options, None, event).await;
+                                if is_transient(&e) {
+                                    continue;
+                                }
+                                break;
+                            }
+                        };
+                        let info = ConnectionInfo {
+                            local_addr: sockets[index].local_addr().ok(),
+                            ..peer_info(peer)
+                        };
+                        let is_new = clients.write().await.insert(peer, index).is_none();
+                        if is_new {
+                            notify_link(ui_tx.as_ref(), &options, &info, true).await;
+                        }
+
+                        let frame = options.framing.undecoded(Bytes::copy_from_slice(&bufs[index][..len]));
+                        let hits = forward_frame(ui_tx.as_ref(), &options, frame, &info).await;
+                        spawn_auto_replies(hits, &message_tx, |reply| {
+                            Message::new_sent(MessageType::Binary(reply), Some(info.clone()))
+                        });
+                    }
+                    _ = control_rx.recv() => break,
+                }
+            }
+        });
+
+        Ok(())
     }
-    
+
     async fn stop(&mut self) -> Result<()> {
-        self.running = false;
+        if self.running {
+            if let Some(ref control_tx) = self.control_tx {
+                let _ = control_tx.send(()).await;
+            }
+            self.running = false;
+            self.control_tx = None;
+            if let Some(group) = &self.options.udp_group {
+                for socket in &self.sockets {
+                    let _ = group.leave(&SockRef::from(&**socket));
+                }
+            }
+            self.sockets.clear();
+        }
         Ok(())
     }
-    
-    async fn send_message(&mut self, _message: MessageType, _target: Option<String>) -> Result<()> {
-        anyhow::bail!("UDP server send not yet implemented")
+
+    async fn send_message(&mut self, message: MessageType, target: Option<String>) -> Result<()> {
+        if let Some(ref tx) = self.message_tx {
+            let connection_info = match target {
+                Some(target) => Some(peer_info(target.parse()?)),
+                None => None,
+            };
+            let _ = tx.send(Message::new_sent(message, connection_info)).await;
+        }
+        Ok(())
     }
-    
+
     fn get_ui_to_server_sender(&self) -> Option<Sender<Message>> {
         self.message_tx.clone()
     }
-    
+
     fn set_server_to_ui_sender(&mut self, sender: Sender<Message>) {
         self.ui_tx = Some(sender);
     }
-    
+
     fn is_running(&self) -> bool {
         self.running
     }
-    
+
     fn get_connections(&self) -> Vec<ConnectionInfo> {
-        vec![]
+        self.clients
+            .try_read()
+            .map(|clients| clients.keys().copied().map(peer_info).collect())
+            .unwrap_or_default()
     }
-    
+
     fn protocol_name(&self) -> &'static str {
-        "UDP Server"
+        match self.options.udp_group {
+            Some(UdpGroup::Multicast(_)) => "UDP Multicast",
+            Some(UdpGroup::Broadcast(_)) => "UDP Broadcast",
+            None => "UDP Server",
+        }
     }
 }
 
@@ -97,13 +342,15 @@ pub struct UdpClientHandler {
     message_tx: Option<Sender<Message>>,
     /// UI消息发送通道
     ui_tx: Option<Sender<Message>>,
+    /// 会话选项
+    options: SessionOptions,
     /// 运行状态
     running: bool,
 }
 
 impl UdpClientHandler {
     /// 创建新的UDP客户端处理器
-    pub fn new(local_addr: SocketAddr, remote_addr: SocketAddr) -> Self {
+    pub fn new(local_addr: SocketAddr, remote_addr: SocketAddr, options: SessionOptions) -> Self {
         Self {
             local_addr,
             remote_addr,
@@ -112,6 +359,7 @@ impl UdpClientHandler {
             message_rx: None,
             message_tx: None,
             ui_tx: None,
+            options,
             running: false,
         }
     }
@@ -120,35 +368,206 @@ impl UdpClientHandler {
 #[async_trait]
 impl ProtocolHandler for UdpClientHandler {
     async fn start(&mut self) -> Result<()> {
-        anyhow::bail!("UDP client handler not yet fully implemented")
+        // 连接后只接收来自远程地址的数据报
+        let socket = Arc::new(self.options.socket.udp_bind_for(self.local_addr, self.remote_addr)?);
+        socket.connect(self.remote_addr).await?;
+        self.socket = Some(Arc::clone(&socket));
+
+        // 创建消息通道
+        let (message_tx, message_rx) = channel::<Message>(100);
+        let (control_tx, mut control_rx) = channel::<()>(1);
+        self.message_tx = Some(message_tx.clone());
+        self.message_rx = Some(message_rx);
+        self.control_tx = Some(control_tx);
+        self.running = true;
+
+        let info = peer_info(self.remote_addr);
+        let options = self.options.clone();
+        let ui_tx = self.ui_tx.clone();
+        let mut message_rx = self.message_rx.take().unwrap();
+        notify_link(ui_tx.as_ref(), &options, &info, true).await;
+
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_DATAGRAM];
+            // 发送后等待响应的截止时间
+            let mut response_deadline = None;
+            loop {
+                tokio::select! {
+                    result = socket.recv(&mut buf) => match result {
+                        Ok(len) => {
+                            response_deadline = None;
+                            let frame = options.framing.undecoded(Bytes::copy_from_slice(&buf[..len]));
+                            let hits = forward_frame(ui_tx.as_ref(), &options, frame, &info).await;
+                            spawn_auto_replies(hits, &message_tx, |reply| {
+                                Message::new_sent(MessageType::Binary(reply), None)
+                            });
+                        }
+                        Err(e) => {
+                            // 对端端口不可达等错误以事件提示后继续接收，其他错误停止接收
+                            let event = SessionEvent::IoError(format!("receive from {}: {}", info.remote_addr, e));
+                            report_event(ui_tx.as_ref(), &options, &info, event).await;
+                            if !is_transient(&e) {
+                                break;
+                            }
+                        }
+                    },
+                    msg = message_rx.recv() => {
+                        let Some(msg) = msg else {
+                            break;
+                        };
+                        match msg.content {
+                            MessageType::Control(ConnectionControl::Close | ConnectionControl::Abort) => break,
+                            MessageType::Control(ConnectionControl::SetOption(option)) => {
+                                if let Err(e) = option.apply(&SockRef::from(&*socket)) {
+                                    report_option_error(ui_tx.as_ref(), &info, option, e).await;
+                                }
+                                continue;
+                            }
+                            _ => {}
+                        }
+                        let Some(data) = frame_outgoing(ui_tx.as_ref(), &options, &msg.content).await else {
+                            continue;
+                        };
+                        if send_datagram(&socket, None, &data, &msg.ack, &options, &info, ui_tx.as_ref()).await {
+                            break;
+                        }
+                        if response_deadline.is_none() {
+                            response_deadline = options.timeouts.idle_deadline();
+                        }
+                    }
+                    _ = timeout::sleep_until(response_deadline) => {
+                        response_deadline = None;
+                        let expired = options.timeouts.expired(TimeoutKind::Response).expect("response timeout is configured");
+                        report_timeout(ui_tx.as_ref(), &options, &info, expired).await;
+                        if expired.closed {
+                            break;
+                        }
+                    }
+                    _ = control_rx.recv() => break,
+                }
+            }
+            notify_link(ui_tx.as_ref(), &options, &info, false).await;
+        });
+
+        Ok(())
     }
-    
+
     async fn stop(&mut self) -> Result<()> {
-        self.running = false;
+        if self.running {
+            if let Some(ref control_tx) = self.control_tx {
+                let _ = control_tx.send(()).await;
+            }
+            self.running = false;
+            self.control_tx = None;
+            self.socket = None;
+        }
         Ok(())
     }
-    
-    async fn send_message(&mut self, _message: MessageType, _target: Option<String>) -> Result<()> {
-        anyhow::bail!("UDP client send not yet implemented")
+
+    async fn send_message(&mut self, message: MessageType, _target: Option<String>) -> Result<()> {
+        if let Some(ref tx) = self.message_tx {
+            let _ = tx.send(Message::new_sent(message, Some(peer_info(self.remote_addr)))).await;
+        }
+        Ok(())
     }
-    
+
     fn get_ui_to_server_sender(&self) -> Option<Sender<Message>> {
         self.message_tx.clone()
     }
-    
+
     fn set_server_to_ui_sender(&mut self, sender: Sender<Message>) {
         self.ui_tx = Some(sender);
     }
-    
+
     fn is_running(&self) -> bool {
         self.running
     }
-    
+
     fn get_connections(&self) -> Vec<ConnectionInfo> {
-        vec![]
+        if self.running {
+            vec![peer_info(self.remote_addr)]
+        } else {
+            vec![]
+        }
     }
-    
+
     fn protocol_name(&self) -> &'static str {
         "UDP Client"
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 等待满足条件的下一条 UI 消息，跳过其他消息
+    async fn wait_for(rx: &mut Receiver<Message>, matches: impl Fn(&MessageType) -> bool) -> Message {
+        let wait = async {
+            loop {
+                let message = rx.recv().await.expect("session ended");
+                if matches(&message.content) {
+                    return message;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), wait).await.expect("timed out waiting for message")
+    }
+
+    #[tokio::test]
+    async fn test_server_echoes_datagram() {
+        let (ui_tx, mut ui_rx) = channel::<Message>(100);
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let mut server = UdpServerHandler::new(vec![addr], SessionOptions::default());
+        server.set_server_to_ui_sender(ui_tx);
+        server.start().await.unwrap();
+        let server_addr = server.sockets[0].local_addr().unwrap();
+
+        let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        peer.send_to(b"ping", server_addr).await.unwrap();
+        let connected = wait_for(&mut ui_rx, |content| matches!(content, MessageType::ClientConnected)).await;
+        let info = connected.connection_info.unwrap();
+        assert_eq!(info.remote_addr, peer.local_addr().unwrap());
+        assert_eq!(info.local_addr, Some(server_addr));
+        let received = wait_for(&mut ui_rx, |content| !matches!(content, MessageType::Event(_))).await;
+        assert_eq!(SessionOptions::default().outgoing_payload(&received.content).unwrap(), &b"ping"[..]);
+
+        // 回复发往收到数据的对端
+        server.send_message(MessageType::Text("pong".to_string()), Some(info.connection_id)).await.unwrap();
+        let mut buf = [0u8; 16];
+        let (len, from) = tokio::time::timeout(Duration::from_secs(5), peer.recv_from(&mut buf)).await.unwrap().unwrap();
+        assert_eq!((&buf[..len], from), (&b"pong"[..], server_addr));
+        server.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_response_timeout() {
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let options = SessionOptions {
+            timeouts: timeout::Timeouts {
+                read_idle: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (ui_tx, mut ui_rx) = channel::<Message>(100);
+        let mut client = UdpClientHandler::new("127.0.0.1:0".parse().unwrap(), silent.local_addr().unwrap(), options);
+        client.set_server_to_ui_sender(ui_tx);
+        client.start().await.unwrap();
+
+        client.send_message(MessageType::Text("ping".to_string()), None).await.unwrap();
+        let mut buf = [0u8; 16];
+        let len = silent.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"ping");
+        // 对端不回复时提示响应超时，未配置关闭时会话继续
+        let message = wait_for(&mut ui_rx, |content| matches!(content, MessageType::Event(SessionEvent::Timeout(_)))).await;
+        match message.content {
+            MessageType::Event(SessionEvent::Timeout(expired)) => {
+                assert_eq!(expired.kind, TimeoutKind::Response);
+                assert!(!expired.closed);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+        client.stop().await.unwrap();
+    }
+}
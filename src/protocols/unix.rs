@@ -0,0 +1,1055 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use socket2::{Domain, SockAddr, SockRef, Socket, Type};
+use std::{
+    collections::HashMap,
+    fmt, io,
+    os::unix::fs::FileTypeExt,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::{
+    io::{AsyncWriteExt, Interest},
+    net::{unix::OwnedWriteHalf, UnixDatagram, UnixListener, UnixStream},
+    sync::{
+        mpsc::{channel, Sender},
+        Notify, RwLock,
+    },
+    task::AbortHandle,
+};
+
+use crate::capture::logger::LogEvent;
+use crate::protocols::common::{
+    ConnectionControl, ConnectionInfo, Message, MessageDirection, MessageType, PeerCredentials, ProtocolHandler,
+    SessionEvent, SessionOptions, WriteAck,
+};
+use crate::protocols::framing::FrameDecoder;
+use crate::protocols::session::{forward_frame, frame_outgoing, report_event, report_option_error, report_timeout, spawn_auto_replies};
+use crate::protocols::timeout::{self, TimeoutKind};
+
+/// 单个数据报的最大长度
+const MAX_DATAGRAM: usize = 65536;
+
+/// Unix 套接字地址: 文件路径，或以 @ 开头的 Linux 抽象命名空间名称
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UnixPath {
+    File(PathBuf),
+    Abstract(String),
+}
+
+impl UnixPath {
+    /// 解析套接字路径，`@name` 表示抽象命名空间
+    pub fn parse(s: &str) -> Self {
+        match s.strip_prefix('@') {
+            Some(name) => UnixPath::Abstract(name.to_string()),
+            None => UnixPath::File(PathBuf::from(s)),
+        }
+    }
+
+    fn sock_addr(&self) -> io::Result<SockAddr> {
+        match self {
+            UnixPath::File(path) => SockAddr::unix(path),
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            UnixPath::Abstract(name) => SockAddr::unix(format!("\0{}", name)),
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            UnixPath::Abstract(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "abstract socket names are only supported on Linux",
+            )),
+        }
+    }
+
+    /// 由收到数据报的来源地址构造，未绑定地址的对端返回 None
+    fn from_peer(addr: &tokio::net::unix::SocketAddr) -> Option<Self> {
+        if let Some(path) = addr.as_pathname() {
+            return Some(UnixPath::File(path.to_path_buf()));
+        }
+        addr.as_abstract_name()
+            .map(|name| UnixPath::Abstract(String::from_utf8_lossy(name).into_owned()))
+    }
+
+    /// 创建套接字并绑定到该地址；路径上残留的套接字文件无进程使用时先删除，其他文件不删除
+    fn bind(&self, ty: Type) -> io::Result<Socket> {
+        if let UnixPath::File(path) = self {
+            match std::fs::symlink_metadata(path) {
+                Ok(metadata) if !metadata.file_type().is_socket() => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("bind {}: address in use and not a socket", self),
+                    ));
+                }
+                Ok(_) if matches!(self.connect(ty), Err(e) if e.kind() == io::ErrorKind::ConnectionRefused) => {
+                    std::fs::remove_file(path)?;
+                }
+                _ => {}
+            }
+        }
+        let socket = Socket::new(Domain::UNIX, ty, None)?;
+        socket
+            .bind(&self.sock_addr()?)
+            .map_err(|e| io::Error::new(e.kind(), format!("bind {}: {}", self, e)))?;
+        Ok(socket)
+    }
+
+    /// 创建套接字并连接到该地址 (本地套接字的连接立即完成)
+    fn connect(&self, ty: Type) -> io::Result<Socket> {
+        let socket = Socket::new(Domain::UNIX, ty, None)?;
+        socket
+            .connect(&self.sock_addr()?)
+            .map_err(|e| io::Error::new(e.kind(), format!("connect {}: {}", self, e)))?;
+        Ok(socket)
+    }
+
+    /// 删除本进程绑定时创建的套接字文件
+    fn remove(&self) {
+        if let UnixPath::File(path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl fmt::Display for UnixPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnixPath::File(path) => write!(f, "{}", path.display()),
+            UnixPath::Abstract(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+/// 数据报客户端绑定的本地地址，服务器据此回复；Linux 上使用抽象命名空间，无需清理文件
+fn client_dgram_path() -> UnixPath {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let name = format!("nt-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        UnixPath::Abstract(name)
+    } else {
+        UnixPath::File(std::env::temp_dir().join(format!("{}.sock", name)))
+    }
+}
+
+/// 读取流套接字对端的进程凭据
+fn peer_credentials(stream: &UnixStream) -> Option<PeerCredentials> {
+    let cred = stream.peer_cred().ok()?;
+    Some(PeerCredentials {
+        pid: cred.pid(),
+        uid: cred.uid(),
+        gid: cred.gid(),
+    })
+}
+
+/// 通知 UI 连接状态变化并写入会话记录，连接时随后上报对端凭据
+async fn notify_link(
+    tx: Option<&Sender<Message>>,
+    options: &SessionOptions,
+    info: &ConnectionInfo,
+    connected: bool,
+    credentials: Option<PeerCredentials>,
+) {
+    let (content, event) = match connected {
+        true => (MessageType::ClientConnected, LogEvent::Connected),
+        false => (MessageType::ClientDisconnected, LogEvent::Disconnected),
+    };
+    options.capture.record(MessageDirection::Received, Some(info), event);
+    if let Some(tx) = tx {
+        let _ = tx.send(Message::new_received(content, Some(info.clone()))).await;
+    }
+    if let Some(credentials) = credentials {
+        report_event(tx, options, info, SessionEvent::PeerCredentials(credentials)).await;
+    }
+}
+
+/// 发往连接写入任务的命令
+enum Outgoing {
+    /// 写入一帧 (已按分帧方式封装)，写出后通知发送方
+    Data(Bytes, WriteAck),
+    /// 自动回复的负载，写入前按分帧方式封装
+    Reply(Bytes),
+    /// 写完之前的数据后关闭写入方向
+    Shutdown,
+}
+
+/// 按写入超时配置写入一帧，返回是否应关闭连接
+async fn write_frame(
+    write_half: &mut OwnedWriteHalf,
+    data: &Bytes,
+    ack: &WriteAck,
+    options: &SessionOptions,
+    info: &ConnectionInfo,
+    ui_tx: Option<&Sender<Message>>,
+) -> bool {
+    match timeout::within(options.timeouts.write, write_half.write_all(data)).await {
+        Some(Ok(())) => {
+            options.capture.record(MessageDirection::Sent, Some(info), LogEvent::Data(data.clone()));
+            ack.written(info, data);
+            false
+        }
+        Some(Err(e)) => {
+            report_event(ui_tx, options, info, SessionEvent::IoError(format!("send: {}", e))).await;
+            true
+        }
+        None => {
+            let expired = options.timeouts.expired(TimeoutKind::Write).expect("write timeout is configured");
+            report_timeout(ui_tx, options, info, expired).await;
+            expired.closed
+        }
+    }
+}
+
+/// 已建立的流连接: 写入任务和读取任务的句柄
+struct StreamLink {
+    info: ConnectionInfo,
+    /// 发送通道
+    tx: Sender<Outgoing>,
+    /// 关闭通知，读取任务收到后按连接断开处理
+    close: Arc<Notify>,
+    /// 写入任务，中止连接时直接结束以丢弃未发送的数据
+    writer: AbortHandle,
+    /// 用于运行时修改套接字选项的句柄
+    socket: Socket,
+}
+
+impl StreamLink {
+    /// 启动连接的读写任务，对端关闭或收到关闭命令后上报断开并调用 `on_close`
+    async fn spawn(
+        stream: UnixStream,
+        info: ConnectionInfo,
+        options: SessionOptions,
+        ui_tx: Option<Sender<Message>>,
+        on_close: impl std::future::Future<Output = ()> + Send + 'static,
+    ) -> io::Result<Self> {
+        let socket = SockRef::from(&stream);
+        if let Err(e) = options.socket.apply(&socket, false) {
+            report_option_error(ui_tx.as_ref(), &info, None, e).await;
+        }
+        let socket = socket.try_clone()?;
+        let credentials = peer_credentials(&stream);
+        let (mut read_half, mut write_half) = stream.into_split();
+        let (tx, mut rx) = channel::<Outgoing>(100);
+        let close = Arc::new(Notify::new());
+
+        // 写入任务
+        let writer = {
+            let options = options.clone();
+            let ui_tx = ui_tx.clone();
+            let info = info.clone();
+            let close = Arc::clone(&close);
+            tokio::spawn(async move {
+                let mut write_closed = false;
+                while let Some(outgoing) = rx.recv().await {
+                    let (data, ack) = match outgoing {
+                        Outgoing::Data(data, ack) => (data, ack),
+                        Outgoing::Reply(reply) => match frame_outgoing(ui_tx.as_ref(), &options, &MessageType::Binary(reply)).await {
+                            Some(data) => (data, WriteAck::default()),
+                            None => continue,
+                        },
+                        Outgoing::Shutdown if write_closed => continue,
+                        Outgoing::Shutdown => {
+                            if let Err(e) = write_half.shutdown().await {
+                                let event = SessionEvent::IoError(format!("shutdown: {}", e));
+                                report_event(ui_tx.as_ref(), &options, &info, event).await;
+                                break;
+                            }
+                            write_closed = true;
+                            let event = SessionEvent::LocalClose(ConnectionControl::Shutdown);
+                            report_event(ui_tx.as_ref(), &options, &info, event).await;
+                            continue;
+                        }
+                    };
+                    if write_closed {
+                        report_event(ui_tx.as_ref(), &options, &info, SessionEvent::WriteClosed { bytes: data.len() }).await;
+                        continue;
+                    }
+                    if write_frame(&mut write_half, &data, &ack, &options, &info, ui_tx.as_ref()).await {
+                        close.notify_one();
+                        break;
+                    }
+                }
+            })
+        };
+
+        notify_link(ui_tx.as_ref(), &options, &info, true, credentials).await;
+
+        // 读取任务，自动回复直接写入该连接
+        let reply_tx = tx.clone();
+        let close_for_read = Arc::clone(&close);
+        let read_info = info.clone();
+        tokio::spawn(async move {
+            let info = read_info;
+            let mut decoder = FrameDecoder::new(options.framing.clone());
+            let mut idle_deadline = options.timeouts.idle_deadline();
+            loop {
+                // 收到关闭命令时按对端关闭处理
+                let result = tokio::select! {
+                    result = decoder.read_frames(&mut read_half) => result,
+                    _ = close_for_read.notified() => Ok(None),
+                    _ = timeout::sleep_until(idle_deadline) => {
+                        // 每段空闲只提示一次，收到数据后重新计时
+                        idle_deadline = None;
+                        let expired = options.timeouts.expired(TimeoutKind::ReadIdle).expect("idle timeout is configured");
+                        report_timeout(ui_tx.as_ref(), &options, &info, expired).await;
+                        if !expired.closed {
+                            continue;
+                        }
+                        Ok(None)
+                    }
+                };
+                match result {
+                    Ok(Some(frames)) => {
+                        idle_deadline = options.timeouts.idle_deadline();
+                        for frame in frames {
+                            let hits = forward_frame(ui_tx.as_ref(), &options, frame, &info).await;
+                            spawn_auto_replies(hits, &reply_tx, Outgoing::Reply);
+                        }
+                        continue;
+                    }
+                    Ok(None) => {
+                        // 连接关闭前上报缓冲中的剩余数据
+                        if let Some(frame) = decoder.flush() {
+                            forward_frame(ui_tx.as_ref(), &options, frame, &info).await;
+                        }
+                    }
+                    Err(e) => report_event(ui_tx.as_ref(), &options, &info, SessionEvent::IoError(format!("read: {}", e))).await,
+                }
+                on_close.await;
+                notify_link(ui_tx.as_ref(), &options, &info, false, None).await;
+                break;
+            }
+        });
+
+        Ok(Self {
+            info,
+            tx,
+            close,
+            writer: writer.abort_handle(),
+            socket,
+        })
+    }
+
+    /// 执行连接控制命令；Unix 套接字没有 RST，中止时丢弃未发送的数据后关闭
+    async fn apply_control(&self, control: ConnectionControl) -> io::Result<()> {
+        match control {
+            ConnectionControl::Close => self.close.notify_one(),
+            ConnectionControl::Shutdown => {
+                let _ = self.tx.send(Outgoing::Shutdown).await;
+            }
+            ConnectionControl::Abort => {
+                self.writer.abort();
+                self.close.notify_one();
+            }
+            ConnectionControl::SetOption(option) => option.apply(&self.socket)?,
+        }
+        Ok(())
+    }
+
+    /// 处理 UI 发来的消息
+    async fn handle(&self, msg: &Message, options: &SessionOptions, ui_tx: Option<&Sender<Message>>) {
+        if let MessageType::Control(control) = msg.content {
+            // 半关闭由写入任务在发送完之前的数据后上报
+            if matches!(control, ConnectionControl::Close | ConnectionControl::Abort) {
+                report_event(ui_tx, options, &self.info, SessionEvent::LocalClose(control)).await;
+            }
+            if let Err(e) = self.apply_control(control).await {
+                report_option_error(ui_tx, &self.info, control.socket_option(), e).await;
+            }
+            return;
+        }
+        if let Some(data) = frame_outgoing(ui_tx, options, &msg.content).await {
+            let _ = self.tx.send(Outgoing::Data(data, msg.ack.clone())).await;
+        }
+    }
+}
+
+/// Unix 流套接字服务器处理器
+pub struct UnixStreamServerHandler {
+    /// 监听路径
+    path: UnixPath,
+    /// 已接受的连接
+    clients: Arc<RwLock<HashMap<String, StreamLink>>>,
+    /// 控制通道 (用于停止服务器)
+    control_tx: Option<Sender<()>>,
+    /// UI到服务器发送通道
+    ui_to_server_tx: Option<Sender<Message>>,
+    /// 服务器到UI发送通道
+    server_to_ui_tx: Option<Sender<Message>>,
+    /// 会话选项
+    options: SessionOptions,
+    /// 运行状态
+    running: bool,
+}
+
+impl UnixStreamServerHandler {
+    /// 创建新的 Unix 流套接字服务器处理器
+    pub fn new(path: UnixPath, options: SessionOptions) -> Self {
+        Self {
+            path,
+            clients: Arc::new(RwLock::new(HashMap::new())),
+            control_tx: None,
+            ui_to_server_tx: None,
+            server_to_ui_tx: None,
+            options,
+            running: false,
+        }
+    }
+}
+
+#[async_trait]
+impl ProtocolHandler for UnixStreamServerHandler {
+    async fn start(&mut self) -> Result<()> {
+        let socket = self.path.bind(Type::STREAM)?;
+        socket.listen(1024)?;
+        socket.set_nonblocking(true)?;
+        let listener = UnixListener::from_std(socket.into())?;
+
+        let (ui_to_server_tx, mut ui_to_server_rx) = channel::<Message>(100);
+        let (control_tx, mut control_rx) = channel::<()>(1);
+        self.ui_to_server_tx = Some(ui_to_server_tx);
+        self.control_tx = Some(control_tx);
+        self.running = true;
+
+        // 将UI发来的消息转发给目标连接 (未指定目标时广播)
+        {
+            let clients = Arc::clone(&self.clients);
+            let options = self.options.clone();
+            let ui_tx = self.server_to_ui_tx.clone();
+            tokio::spawn(async move {
+                while let Some(msg) = ui_to_server_rx.recv().await {
+                    let clients = clients.read().await;
+                    let targets: Vec<&StreamLink> = match &msg.connection_info {
+                        Some(info) => clients.get(&info.connection_id).into_iter().collect(),
+                        None => clients.values().collect(),
+                    };
+                    for client in targets {
+                        client.handle(&msg, &options, ui_tx.as_ref()).await;
+                    }
+                }
+            });
+        }
+
+        // 接受连接，连接没有地址，按接受顺序编号
+        let clients = Arc::clone(&self.clients);
+        let options = self.options.clone();
+        let ui_tx = self.server_to_ui_tx.clone();
+        tokio::spawn(async move {
+            let mut next_id = 1u64;
+            loop {
+                tokio::select! {
+                    result = listener.accept() => {
+                        let stream = match result {
+                            Ok((stream, _)) => stream,
+                            Err(e) => {
+                                report_event(ui_tx.as_ref(), &options, None, SessionEvent::IoError(format!("accept: {}", e))).await;
+                                continue;
+                            }
+                        };
+                        let id = format!("unix#{}", next_id);
+                        next_id += 1;
+                        let info = ConnectionInfo::non_ip(id.clone());
+                        let clients_for_close = Arc::clone(&clients);
+                        let close_id = id.clone();
+                        let on_close = async move {
+                            clients_for_close.write().await.remove(&close_id);
+                        };
+                        // 先登记连接再启动，避免连接立即关闭时移除早于登记
+                        let mut clients = clients.write().await;
+                        match StreamLink::spawn(stream, info, options.clone(), ui_tx.clone(), on_close).await {
+                            Ok(link) => {
+                                clients.insert(id, link);
+                            }
+                            Err(e) => {
+                                let info = ConnectionInfo::non_ip(id);
+                                report_event(ui_tx.as_ref(), &options, &info, SessionEvent::IoError(e.to_string())).await;
+                            }
+                        }
+                    }
+                    _ = control_rx.recv() => break,
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if self.running {
+            if let Some(ref control_tx) = self.control_tx {
+                let _ = control_tx.send(()).await;
+            }
+            self.running = false;
+            self.control_tx = None;
+            self.path.remove();
+        }
+        Ok(())
+    }
+
+    async fn send_message(&mut self, message: MessageType, target: Option<String>) -> Result<()> {
+        if let Some(ref tx) = self.ui_to_server_tx {
+            let connection_info = target.map(ConnectionInfo::non_ip);
+            let _ = tx.send(Message::new_sent(message, connection_info)).await;
+        }
+        Ok(())
+    }
+
+    fn get_ui_to_server_sender(&self) -> Option<Sender<Message>> {
+        self.ui_to_server_tx.clone()
+    }
+
+    fn set_server_to_ui_sender(&mut self, sender: Sender<Message>) {
+        self.server_to_ui_tx = Some(sender);
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn get_connections(&self) -> Vec<ConnectionInfo> {
+        self.clients
+            .try_read()
+            .map(|clients| clients.values().map(|client| client.info.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "Unix Server"
+    }
+}
+
+/// Unix 流套接字客户端处理器
+pub struct UnixStreamClientHandler {
+    /// 服务器路径
+    path: UnixPath,
+    /// 当前连接，对端关闭后清空
+    link: Arc<RwLock<Option<StreamLink>>>,
+    /// UI到连接的发送通道
+    ui_to_server_tx: Option<Sender<Message>>,
+    /// 连接到UI发送通道
+    server_to_ui_tx: Option<Sender<Message>>,
+    /// 会话选项
+    options: SessionOptions,
+    /// 运行状态
+    running: bool,
+}
+
+impl UnixStreamClientHandler {
+    /// 创建新的 Unix 流套接字客户端处理器
+    pub fn new(path: UnixPath, options: SessionOptions) -> Self {
+        Self {
+            path,
+            link: Arc::new(RwLock::new(None)),
+            ui_to_server_tx: None,
+            server_to_ui_tx: None,
+            options,
+            running: false,
+        }
+    }
+}
+
+#[async_trait]
+impl ProtocolHandler for UnixStreamClientHandler {
+    async fn start(&mut self) -> Result<()> {
+        let path = self.path.clone();
+        let connect = tokio::task::spawn_blocking(move || path.connect(Type::STREAM));
+        let socket = match timeout::within(self.options.timeouts.connect, connect).await {
+            Some(result) => result??,
+            None => anyhow::bail!("connect to {} timed out", self.path),
+        };
+        socket.set_nonblocking(true)?;
+        let stream = UnixStream::from_std(socket.into())?;
+
+        let (ui_to_server_tx, mut ui_to_server_rx) = channel::<Message>(100);
+        self.ui_to_server_tx = Some(ui_to_server_tx);
+        self.running = true;
+
+        let info = ConnectionInfo::non_ip(self.path.to_string());
+        let link_for_close = Arc::clone(&self.link);
+        let on_close = async move {
+            link_for_close.write().await.take();
+        };
+        let mut link = self.link.write().await;
+        *link = Some(
+            StreamLink::spawn(stream, info, self.options.clone(), self.server_to_ui_tx.clone(), on_close)
+                .await
+                .with_context(|| format!("Cannot set up connection to {}", self.path))?,
+        );
+
+        let link = Arc::clone(&self.link);
+        let options = self.options.clone();
+        let ui_tx = self.server_to_ui_tx.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = ui_to_server_rx.recv().await {
+                match link.read().await.as_ref() {
+                    Some(link) => link.handle(&msg, &options, ui_tx.as_ref()).await,
+                    None => break,
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        self.running = false;
+        if let Some(link) = self.link.read().await.as_ref() {
+            link.close.notify_one();
+        }
+        Ok(())
+    }
+
+    async fn send_message(&mut self, message: MessageType, _target: Option<String>) -> Result<()> {
+        if let Some(ref tx) = self.ui_to_server_tx {
+            let _ = tx.send(Message::new_sent(message, None)).await;
+        }
+        Ok(())
+    }
+
+    fn get_ui_to_server_sender(&self) -> Option<Sender<Message>> {
+        self.ui_to_server_tx.clone()
+    }
+
+    fn set_server_to_ui_sender(&mut self, sender: Sender<Message>) {
+        self.server_to_ui_tx = Some(sender);
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn get_connections(&self) -> Vec<ConnectionInfo> {
+        self.link
+            .try_read()
+            .ok()
+            .and_then(|link| link.as_ref().map(|link| link.info.clone()))
+            .into_iter()
+            .collect()
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "Unix Client"
+    }
+}
+
+/// 按写入超时配置发送一个已分帧的数据报 (`peer` 为 None 时发往已连接的地址)，返回是否需要因超时关闭
+async fn send_datagram(
+    socket: &UnixDatagram,
+    peer: Option<&UnixPath>,
+    datagram: &Bytes,
+    ack: &WriteAck,
+    options: &SessionOptions,
+    info: &ConnectionInfo,
+    ui_tx: Option<&Sender<Message>>,
+) -> bool {
+    let send = async {
+        match peer {
+            Some(peer) => {
+                let addr = peer.sock_addr()?;
+                socket
+                    .async_io(Interest::WRITABLE, || SockRef::from(socket).send_to(datagram, &addr))
+                    .await
+            }
+            None => socket.send(datagram).await,
+        }
+    };
+    match timeout::within(options.timeouts.write, send).await {
+        Some(Ok(_)) => {
+            options.capture.record(MessageDirection::Sent, Some(info), LogEvent::Data(datagram.clone()));
+            ack.written(info, datagram);
+            false
+        }
+        Some(Err(e)) => {
+            report_event(ui_tx, options, info, SessionEvent::IoError(format!("send: {}", e))).await;
+            false
+        }
+        None => {
+            let expired = options.timeouts.expired(TimeoutKind::Write).expect("write timeout is configured");
+            report_timeout(ui_tx, options, info, expired).await;
+            expired.closed
+        }
+    }
+}
+
+/// 应用套接字选项，失败时以事件提示
+async fn apply_datagram_option(socket: &UnixDatagram, control: ConnectionControl, info: &ConnectionInfo, ui_tx: Option<&Sender<Message>>) {
+    if let ConnectionControl::SetOption(option) = control {
+        if let Err(e) = option.apply(&SockRef::from(socket)) {
+            report_option_error(ui_tx, info, option, e).await;
+        }
+    }
+}
+
+/// Unix 数据报套接字服务器处理器
+pub struct UnixDatagramServerHandler {
+    /// 绑定路径
+    path: UnixPath,
+    /// 已知对端 (收到过数据报且有地址的对端)，按连接 ID 保存回复地址
+    peers: Arc<RwLock<HashMap<String, UnixPath>>>,
+    /// 控制通道 (用于停止服务器)
+    control_tx: Option<Sender<()>>,
+    /// 消息发送通道
+    message_tx: Option<Sender<Message>>,
+    /// UI消息发送通道
+    ui_tx: Option<Sender<Message>>,
+    /// 会话选项
+    options: SessionOptions,
+    /// 运行状态
+    running: bool,
+}
+
+impl UnixDatagramServerHandler {
+    /// 创建新的 Unix 数据报套接字服务器处理器
+    pub fn new(path: UnixPath, options: SessionOptions) -> Self {
+        Self {
+            path,
+            peers: Arc::new(RwLock::new(HashMap::new())),
+            control_tx: None,
+            message_tx: None,
+            ui_tx: None,
+            options,
+            running: false,
+        }
+    }
+}
+
+#[async_trait]
+impl ProtocolHandler for UnixDatagramServerHandler {
+    async fn start(&mut self) -> Result<()> {
+        let socket = self.path.bind(Type::DGRAM)?;
+        socket.set_nonblocking(true)?;
+        let socket = Arc::new(UnixDatagram::from_std(socket.into())?);
+        if let Err(e) = self.options.socket.apply(&SockRef::from(&*socket), false) {
+            report_option_error(self.ui_tx.as_ref(), &ConnectionInfo::non_ip(self.path.to_string()), None, e).await;
+        }
+
+        let (message_tx, mut message_rx) = channel::<Message>(100);
+        let (control_tx, mut control_rx) = channel::<()>(1);
+        self.message_tx = Some(message_tx.clone());
+        self.control_tx = Some(control_tx);
+        self.running = true;
+
+        // 将UI发来的消息发往目标对端 (未指定目标时发往所有已知对端)
+        {
+            let socket = Arc::clone(&socket);
+            let peers = Arc::clone(&self.peers);
+            let options = self.options.clone();
+            let ui_tx = self.ui_tx.clone();
+            let self_info = ConnectionInfo::non_ip(self.path.to_string());
+            tokio::spawn(async move {
+                while let Some(msg) = message_rx.recv().await {
+                    let targets: Vec<(String, UnixPath)> = {
+                        let peers = peers.read().await;
+                        match &msg.connection_info {
+                            Some(info) => peers
+                                .get(&info.connection_id)
+                                .map(|peer| (info.connection_id.clone(), peer.clone()))
+                                .into_iter()
+                                .collect(),
+                            None => peers.iter().map(|(id, peer)| (id.clone(), peer.clone())).collect(),
+                        }
+                    };
+
+                    match msg.content {
+                        // 无连接套接字的关闭只是将对端移出列表，半关闭无意义
+                        MessageType::Control(ConnectionControl::Close | ConnectionControl::Abort) => {
+                            for (id, _) in targets {
+                                if peers.write().await.remove(&id).is_some() {
+                                    let info = ConnectionInfo::non_ip(id);
+                                    notify_link(ui_tx.as_ref(), &options, &info, false, None).await;
+                                }
+                            }
+                            continue;
+                        }
+                        // 所有对端共用一个套接字
+                        MessageType::Control(control) => {
+                            apply_datagram_option(&socket, control, &self_info, ui_tx.as_ref()).await;
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    let Some(data) = frame_outgoing(ui_tx.as_ref(), &options, &msg.content).await else {
+                        continue;
+                    };
+                    for (id, peer) in targets {
+                        let info = ConnectionInfo::non_ip(id);
+                        send_datagram(&socket, Some(&peer), &data, &msg.ack, &options, &info, ui_tx.as_ref()).await;
+                    }
+                }
+            });
+        }
+
+        // 接收任务: 每个数据报作为一帧上报，首次收到某个对端的数据时视为新连接
+        let peers = Arc::clone(&self.peers);
+        let options = self.options.clone();
+        let ui_tx = self.ui_tx.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_DATAGRAM];
+            loop {
+                tokio::select! {
+                    result = socket.recv_from(&mut buf) => {
+                        let Ok((len, addr)) = result else {
+                            continue;
+                        };
+                        // 未绑定地址的对端无法回复，只上报数据
+                        let peer = UnixPath::from_peer(&addr);
+                        let id = peer.as_ref().map_or_else(|| "(unnamed)".to_string(), UnixPath::to_string);
+                        let info = ConnectionInfo::non_ip(id.clone());
+                        if let Some(peer) = peer {
+                            let is_new = peers.write().await.insert(id, peer).is_none();
+                            if is_new {
+                                notify_link(ui_tx.as_ref(), &options, &info, true, None).await;
+                            }
+                        }
+
+                        let frame = options.framing.undecoded(Bytes::copy_from_slice(&buf[..len]));
+                        let hits = forward_frame(ui_tx.as_ref(), &options, frame, &info).await;
+                        spawn_auto_replies(hits, &message_tx, |reply| {
+                            Message::new_sent(MessageType::Binary(reply), Some(info.clone()))
+                        });
+                    }
+                    _ = control_rx.recv() => break,
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if self.running {
+            if let Some(ref control_tx) = self.control_tx {
+                let _ = control_tx.send(()).await;
+            }
+            self.running = false;
+            self.control_tx = None;
+            self.path.remove();
+        }
+        Ok(())
+    }
+
+    async fn send_message(&mut self, message: MessageType, target: Option<String>) -> Result<()> {
+        if let Some(ref tx) = self.message_tx {
+            let connection_info = target.map(ConnectionInfo::non_ip);
+            let _ = tx.send(Message::new_sent(message, connection_info)).await;
+        }
+        Ok(())
+    }
+
+    fn get_ui_to_server_sender(&self) -> Option<Sender<Message>> {
+        self.message_tx.clone()
+    }
+
+    fn set_server_to_ui_sender(&mut self, sender: Sender<Message>) {
+        self.ui_tx = Some(sender);
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn get_connections(&self) -> Vec<ConnectionInfo> {
+        self.peers
+            .try_read()
+            .map(|peers| peers.keys().cloned().map(ConnectionInfo::non_ip).collect())
+            .unwrap_or_default()
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "Unix Datagram Server"
+    }
+}
+
+/// Unix 数据报套接字客户端处理器
+pub struct UnixDatagramClientHandler {
+    /// 服务器路径
+    path: UnixPath,
+    /// 本端绑定的地址，服务器据此回复
+    local_path: UnixPath,
+    /// 控制通道 (用于停止客户端)
+    control_tx: Option<Sender<()>>,
+    /// 消息发送通道
+    message_tx: Option<Sender<Message>>,
+    /// UI消息发送通道
+    ui_tx: Option<Sender<Message>>,
+    /// 会话选项
+    options: SessionOptions,
+    /// 运行状态
+    running: bool,
+}
+
+impl UnixDatagramClientHandler {
+    /// 创建新的 Unix 数据报套接字客户端处理器
+    pub fn new(path: UnixPath, options: SessionOptions) -> Self {
+        Self {
+            path,
+            local_path: client_dgram_path(),
+            control_tx: None,
+            message_tx: None,
+            ui_tx: None,
+            options,
+            running: false,
+        }
+    }
+
+    fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo::non_ip(self.path.to_string())
+    }
+}
+
+#[async_trait]
+impl ProtocolHandler for UnixDatagramClientHandler {
+    async fn start(&mut self) -> Result<()> {
+        let socket = self.local_path.bind(Type::DGRAM)?;
+        socket
+            .connect(&self.path.sock_addr()?)
+            .with_context(|| format!("Cannot connect to {}", self.path))?;
+        socket.set_nonblocking(true)?;
+        let socket = Arc::new(UnixDatagram::from_std(socket.into())?);
+        let info = self.connection_info();
+        if let Err(e) = self.options.socket.apply(&SockRef::from(&*socket), false) {
+            report_option_error(self.ui_tx.as_ref(), &info, None, e).await;
+        }
+
+        let (message_tx, mut message_rx) = channel::<Message>(100);
+        let (control_tx, mut control_rx) = channel::<()>(1);
+        self.message_tx = Some(message_tx.clone());
+        self.control_tx = Some(control_tx);
+        self.running = true;
+
+        notify_link(self.ui_tx.as_ref(), &self.options, &info, true, None).await;
+
+        // 发送任务
+        {
+            let socket = Arc::clone(&socket);
+            let options = self.options.clone();
+            let ui_tx = self.ui_tx.clone();
+            let info = info.clone();
+            tokio::spawn(async move {
+                while let Some(msg) = message_rx.recv().await {
+                    if let MessageType::Control(control) = msg.content {
+                        apply_datagram_option(&socket, control, &info, ui_tx.as_ref()).await;
+                        continue;
+                    }
+                    if let Some(data) = frame_outgoing(ui_tx.as_ref(), &options, &msg.content).await {
+                        send_datagram(&socket, None, &data, &msg.ack, &options, &info, ui_tx.as_ref()).await;
+                    }
+                }
+            });
+        }
+
+        // 接收任务
+        let options = self.options.clone();
+        let ui_tx = self.ui_tx.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_DATAGRAM];
+            loop {
+                tokio::select! {
+                    result = socket.recv(&mut buf) => {
+                        let len = match result {
+                            Ok(len) => len,
+                            // 服务器尚未绑定或已退出时发送会失败，不影响继续接收
+                            Err(_) => continue,
+                        };
+                        let frame = options.framing.undecoded(Bytes::copy_from_slice(&buf[..len]));
+                        let hits = forward_frame(ui_tx.as_ref(), &options, frame, &info).await;
+                        spawn_auto_replies(hits, &message_tx, |reply| Message::new_sent(MessageType::Binary(reply), None));
+                    }
+                    _ = control_rx.recv() => break,
+                }
+            }
+            notify_link(ui_tx.as_ref(), &options, &info, false, None).await;
+        });
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if self.running {
+            if let Some(ref control_tx) = self.control_tx {
+                let _ = control_tx.send(()).await;
+            }
+            self.running = false;
+            self.control_tx = None;
+            self.local_path.remove();
+        }
+        Ok(())
+    }
+
+    async fn send_message(&mut self, message: MessageType, _target: Option<String>) -> Result<()> {
+        if let Some(ref tx) = self.message_tx {
+            let _ = tx.send(Message::new_sent(message, None)).await;
+        }
+        Ok(())
+    }
+
+    fn get_ui_to_server_sender(&self) -> Option<Sender<Message>> {
+        self.message_tx.clone()
+    }
+
+    fn set_server_to_ui_sender(&mut self, sender: Sender<Message>) {
+        self.ui_tx = Some(sender);
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn get_connections(&self) -> Vec<ConnectionInfo> {
+        if self.running {
+            vec![self.connection_info()]
+        } else {
+            vec![]
+        }
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "Unix Datagram Client"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unix_path() {
+        assert_eq!(UnixPath::parse("/tmp/x.sock"), UnixPath::File(PathBuf::from("/tmp/x.sock")));
+        assert_eq!(UnixPath::parse("@nt"), UnixPath::Abstract("nt".to_string()));
+        assert_eq!(UnixPath::parse("@nt").to_string(), "@nt");
+    }
+
+    #[test]
+    fn test_bind_keeps_regular_file() {
+        let file = std::env::temp_dir().join(format!("nt-test-{}.txt", std::process::id()));
+        std::fs::write(&file, b"data").unwrap();
+        let error = UnixPath::File(file.clone()).bind(Type::STREAM).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(std::fs::read(&file).unwrap(), b"data");
+        std::fs::remove_file(&file).unwrap();
+
+        // 无进程使用的残留套接字文件被替换
+        let path = UnixPath::File(std::env::temp_dir().join(format!("nt-test-{}-stale.sock", std::process::id())));
+        drop(path.bind(Type::STREAM).unwrap());
+        assert!(path.bind(Type::STREAM).is_ok());
+        path.remove();
+    }
+
+    #[tokio::test]
+    async fn test_stream_peer_credentials() {
+        let path = UnixPath::File(std::env::temp_dir().join(format!("nt-test-{}.sock", std::process::id())));
+        let socket = path.bind(Type::STREAM).unwrap();
+        socket.listen(1).unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let listener = UnixListener::from_std(socket.into()).unwrap();
+
+        let client = path.connect(Type::STREAM).unwrap();
+        client.set_nonblocking(true).unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let credentials = peer_credentials(&stream).unwrap();
+        path.remove();
+
+        assert_eq!(credentials.pid, Some(std::process::id() as i32));
+        drop(client);
+    }
+}
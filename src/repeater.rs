@@ -0,0 +1,202 @@
+use std::time::{Duration, Instant};
+
+use crate::protocols::MessageType;
+
+/// 单次 tick 内单个重复发送任务最多补发的次数，避免间隔过小时阻塞界面
+const MAX_CATCH_UP_PER_TICK: usize = 100;
+
+/// 周期发送任务
+pub struct Repeater {
+    /// 任务 ID
+    pub id: usize,
+    /// 发送的消息
+    pub message: MessageType,
+    /// 发送间隔
+    pub interval: Duration,
+    /// 剩余发送次数，None 表示一直发送
+    pub remaining: Option<u64>,
+    /// 已发送次数
+    pub sent: u64,
+    /// 是否暂停
+    pub paused: bool,
+    /// 下一次发送时间
+    next_due: Instant,
+}
+
+impl Repeater {
+    /// 列表中显示的摘要
+    pub fn summary(&self) -> String {
+        let content = match &self.message {
+            MessageType::Text(text) => text.clone(),
+            MessageType::Hex(hex_str) => format!("[Hex] {}", hex_str),
+            other => format!("{:?}", other),
+        };
+        let remaining = match self.remaining {
+            Some(n) => format!("{} left", n),
+            None => "forever".to_string(),
+        };
+        format!(
+            "#{} {}every {}ms, sent {}, {}: {}",
+            self.id,
+            if self.paused { "[paused] " } else { "" },
+            self.interval.as_millis(),
+            self.sent,
+            remaining,
+            content
+        )
+    }
+}
+
+/// 周期发送任务集合
+#[derive(Default)]
+pub struct RepeaterSet {
+    /// 当前的任务
+    pub items: Vec<Repeater>,
+    /// 选中的任务索引
+    pub selected: usize,
+    /// 下一个任务 ID
+    next_id: usize,
+}
+
+impl RepeaterSet {
+    /// 添加周期发送任务，第一次发送立即进行，返回任务 ID
+    pub fn add(&mut self, message: MessageType, interval: Duration, count: Option<u64>) -> usize {
+        self.next_id += 1;
+        self.items.push(Repeater {
+            id: self.next_id,
+            message,
+            interval: interval.max(Duration::from_millis(1)),
+            remaining: count,
+            sent: 0,
+            paused: false,
+            next_due: Instant::now(),
+        });
+        self.next_id
+    }
+
+    /// 取出到期需要发送的消息 (任务 ID, 消息)，并移除已完成的任务
+    pub fn take_due(&mut self, now: Instant) -> Vec<(usize, MessageType)> {
+        let mut due = Vec::new();
+        for repeater in self.items.iter_mut().filter(|r| !r.paused) {
+            let mut sent_this_tick = 0;
+            while repeater.next_due <= now && repeater.remaining != Some(0) && sent_this_tick < MAX_CATCH_UP_PER_TICK {
+                due.push((repeater.id, repeater.message.clone()));
+                repeater.sent += 1;
+                repeater.remaining = repeater.remaining.map(|n| n - 1);
+                repeater.next_due += repeater.interval;
+                sent_this_tick += 1;
+            }
+            // 落后太多时不再补发，从当前时间重新计时
+            if repeater.next_due <= now {
+                repeater.next_due = now + repeater.interval;
+            }
+        }
+
+        self.items.retain(|r| r.remaining != Some(0));
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+        due
+    }
+
+    /// 暂停或恢复选中的任务
+    pub fn toggle_pause_selected(&mut self) {
+        if let Some(repeater) = self.items.get_mut(self.selected) {
+            repeater.paused = !repeater.paused;
+            if !repeater.paused {
+                repeater.next_due = Instant::now();
+            }
+        }
+    }
+
+    /// 取消选中的任务
+    pub fn cancel_selected(&mut self) {
+        if self.selected < self.items.len() {
+            self.items.remove(self.selected);
+            self.selected = self.selected.min(self.items.len().saturating_sub(1));
+        }
+    }
+
+    /// 选中下一个任务
+    pub fn select_next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> MessageType {
+        MessageType::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_take_due_schedule() {
+        let mut set = RepeaterSet::default();
+        let id = set.add(text("ping"), Duration::from_millis(100), Some(3));
+        let start = Instant::now();
+
+        // 第一次立即发送，间隔未到不再发送
+        let due = set.take_due(start);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, id);
+        assert!(set.take_due(start + Duration::from_millis(50)).is_empty());
+
+        // 间隔到达后发送，次数用完后任务被移除
+        assert_eq!(set.take_due(start + Duration::from_millis(100)).len(), 1);
+        assert_eq!(set.items[0].sent, 2);
+        assert_eq!(set.items[0].remaining, Some(1));
+        assert_eq!(set.take_due(start + Duration::from_millis(200)).len(), 1);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_take_due_catch_up_limit() {
+        let mut set = RepeaterSet::default();
+        set.add(text("ping"), Duration::from_millis(1), None);
+        let start = Instant::now();
+
+        // 落后太多时单次最多补发 MAX_CATCH_UP_PER_TICK 次，之后从当前时间重新计时
+        let late = start + Duration::from_secs(10);
+        assert_eq!(set.take_due(late).len(), MAX_CATCH_UP_PER_TICK);
+        assert!(set.take_due(late).is_empty());
+        assert_eq!(set.take_due(late + Duration::from_millis(1)).len(), 1);
+        assert_eq!(set.items[0].remaining, None);
+    }
+
+    #[test]
+    fn test_pause_and_cancel() {
+        let mut set = RepeaterSet::default();
+        let first = set.add(text("a"), Duration::from_millis(100), None);
+        let second = set.add(text("b"), Duration::from_millis(100), None);
+
+        // 暂停的任务不发送
+        set.toggle_pause_selected();
+        assert!(set.items[0].paused);
+        let due = set.take_due(Instant::now());
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, second);
+
+        // 恢复后立即发送
+        set.toggle_pause_selected();
+        let due = set.take_due(Instant::now());
+        assert_eq!(due.len(), 1);
+        assert!(matches!(&due[0], (id, MessageType::Text(t)) if *id == first && t == "a"));
+
+        // 取消选中的任务后选中项保持有效
+        set.select_next();
+        set.cancel_selected();
+        assert_eq!(set.items.len(), 1);
+        assert_eq!(set.items[0].id, first);
+        assert_eq!(set.selected, 0);
+        set.cancel_selected();
+        assert!(set.is_empty());
+        set.cancel_selected();
+        assert_eq!(set.selected, 0);
+    }
+}
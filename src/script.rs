@@ -0,0 +1,421 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use rhai::{Blob, CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{Receiver, Sender};
+
+use crate::protocols::{ConnectionControl, ConnectionInfo, Message, MessageDirection, MessageType, SessionEvent};
+use crate::utils::data_format::hex_to_bytes;
+
+/// `on_tick` 钩子及定时器的检查周期
+const SCRIPT_TICK: Duration = Duration::from_millis(100);
+
+/// 单次钩子调用允许执行的最大操作数，避免脚本死循环卡住会话
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// 脚本通过 API 产生的动作，在钩子返回后统一执行
+enum ScriptAction {
+    /// 向连接发送数据，连接为空字符串时广播
+    Send { conn: String, data: Vec<u8> },
+    /// 关闭、半关闭或中止连接
+    Control(String, ConnectionControl),
+    /// 日志输出
+    Log(String),
+    /// 设置定时器
+    SetTimer {
+        id: i64,
+        delay: Duration,
+        repeat: bool,
+        callback: String,
+    },
+    /// 取消定时器
+    ClearTimer(i64),
+}
+
+/// 脚本定时器
+struct Timer {
+    id: i64,
+    due: Instant,
+    /// 重复间隔，None 表示只触发一次
+    interval: Option<Duration>,
+    callback: String,
+}
+
+/// 脚本宿主: 加载 Rhai 脚本并在连接事件、收到数据和定时器到期时调用脚本中的钩子
+///
+/// 支持的钩子 (均为可选): `on_connect(conn)`、`on_data(conn, data)`、`on_disconnect(conn)`、`on_tick()`。
+/// `on_data` 只对收到的数据调用，脚本自己发送的数据不会再触发钩子。
+/// 脚本可用的函数: `send(conn, blob|string)`、`send_hex(conn, hex)`、`close(conn)`、`log(msg)`、
+/// `set_timeout(ms, "fn")`、`set_interval(ms, "fn")`、`clear_timer(id)`、`now_ms()`。
+/// 钩子函数中可通过 `this` 访问在各次调用之间保留的状态对象。
+pub struct ScriptHost {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    /// 绑定为 `this` 的持久状态
+    state: Dynamic,
+    /// 脚本本次调用产生的动作
+    actions: Arc<Mutex<Vec<ScriptAction>>>,
+    timers: Vec<Timer>,
+    /// 已连接的连接 (连接 ID -> 连接信息)
+    connections: HashMap<String, ConnectionInfo>,
+}
+
+impl ScriptHost {
+    /// 加载并编译脚本文件，执行脚本的顶层语句
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read script {}", path.display()))?;
+        Self::from_source(&source).with_context(|| format!("Failed to load script {}", path.display()))
+    }
+
+    /// 从源码创建脚本宿主
+    pub fn from_source(source: &str) -> Result<Self> {
+        let actions: Arc<Mutex<Vec<ScriptAction>>> = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        register_api(&mut engine, &actions);
+
+        let ast = engine.compile(source).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut host = Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            state: Dynamic::from_map(Map::new()),
+            actions,
+            timers: Vec::new(),
+            connections: HashMap::new(),
+        };
+        host.engine
+            .run_ast_with_scope(&mut host.scope, &host.ast)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(host)
+    }
+
+    /// 脚本是否定义了指定名称和参数个数的函数
+    fn has_fn(&self, name: &str, arity: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|f| f.name == name && f.params.len() == arity)
+    }
+
+    /// 调用脚本钩子，未定义时忽略，出错时记录为日志
+    fn call(&mut self, name: &str, arity: usize, args: impl FuncArgs) {
+        if !self.has_fn(name, arity) {
+            return;
+        }
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+        let result = self
+            .engine
+            .call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, name, args);
+        if let Err(e) = result {
+            self.push(ScriptAction::Log(format!("error in {}: {}", name, e)));
+        }
+    }
+
+    fn push(&self, action: ScriptAction) {
+        if let Ok(mut actions) = self.actions.lock() {
+            actions.push(action);
+        }
+    }
+
+    /// 处理协议处理器发来的消息
+    pub fn on_message(&mut self, message: &Message) {
+        let Some(info) = message.connection_info.clone() else {
+            return;
+        };
+        let conn = info.connection_id.clone();
+        match &message.content {
+            MessageType::ClientConnected => {
+                self.connections.insert(conn.clone(), info);
+                self.call("on_connect", 1, (conn,));
+            }
+            MessageType::ClientDisconnected => {
+                self.connections.remove(&conn);
+                self.call("on_disconnect", 1, (conn,));
+            }
+            // 发送的消息也会经过脚本宿主，不调用钩子，避免脚本回复自己的输出
+            content if message.direction == MessageDirection::Received => {
+                if let Some(data) = content.payload() {
+                    self.call("on_data", 2, (conn, data.to_vec() as Blob));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 周期调用: 执行 `on_tick` 并触发到期的定时器
+    pub fn on_tick(&mut self, now: Instant) {
+        self.call("on_tick", 0, ());
+
+        let mut due = Vec::new();
+        for timer in self.timers.iter_mut().filter(|t| t.due <= now) {
+            due.push(timer.callback.clone());
+            if let Some(interval) = timer.interval {
+                timer.due = now + interval;
+            }
+        }
+        self.timers.retain(|t| t.interval.is_some() || t.due > now);
+
+        for callback in due {
+            if self.has_fn(&callback, 0) {
+                self.call(&callback, 0, ());
+            } else {
+                self.push(ScriptAction::Log(format!("timer callback {}() is not defined", callback)));
+            }
+        }
+    }
+
+    /// 取出脚本产生的动作，更新定时器，返回需要发往协议处理器和UI的消息
+    fn drain(&mut self) -> (Vec<Message>, Vec<Message>) {
+        let actions = match self.actions.lock() {
+            Ok(mut actions) => std::mem::take(&mut *actions),
+            Err(_) => return (Vec::new(), Vec::new()),
+        };
+
+        let mut to_handler = Vec::new();
+        let mut to_ui = Vec::new();
+        for action in actions {
+            match action {
+                ScriptAction::Send { conn, data } => match self.resolve(&conn) {
+                    Ok(info) => {
+                        let message = Message::new_sent(MessageType::Binary(Bytes::from(data)), info);
+                        to_ui.push(message.clone());
+                        to_handler.push(message);
+                    }
+                    Err(e) => to_ui.push(log_message(e)),
+                },
+                ScriptAction::Control(conn, control) => match self.resolve(&conn) {
+                    Ok(info) => to_handler.push(Message::new_sent(MessageType::Control(control), info)),
+                    Err(e) => to_ui.push(log_message(e)),
+                },
+                ScriptAction::Log(line) => to_ui.push(log_message(line)),
+                ScriptAction::SetTimer {
+                    id,
+                    delay,
+                    repeat,
+                    callback,
+                } => self.timers.push(Timer {
+                    id,
+                    due: Instant::now() + delay,
+                    interval: repeat.then_some(delay.max(SCRIPT_TICK)),
+                    callback,
+                }),
+                ScriptAction::ClearTimer(id) => self.timers.retain(|t| t.id != id),
+            }
+        }
+        (to_handler, to_ui)
+    }
+
+    /// 将脚本中的连接 ID 转换为连接信息，空字符串表示全部连接
+    fn resolve(&self, conn: &str) -> Result<Option<ConnectionInfo>, String> {
+        if conn.is_empty() {
+            return Ok(None);
+        }
+        self.connections
+            .get(conn)
+            .cloned()
+            .map(Some)
+            .ok_or_else(|| format!("unknown connection: {}", conn))
+    }
+}
+
+fn log_message(line: String) -> Message {
+    Message::new_received(MessageType::Event(SessionEvent::ScriptLog(line)), None)
+}
+
+/// 注册脚本可调用的 API
+fn register_api(engine: &mut Engine, actions: &Arc<Mutex<Vec<ScriptAction>>>) {
+    let push = {
+        let actions = Arc::clone(actions);
+        move |action: ScriptAction| {
+            if let Ok(mut actions) = actions.lock() {
+                actions.push(action);
+            }
+        }
+    };
+    let started = Instant::now();
+    let next_timer_id = Arc::new(AtomicI64::new(0));
+
+    let p = push.clone();
+    engine.register_fn("send", move |conn: &str, data: Blob| {
+        p(ScriptAction::Send { conn: conn.to_string(), data });
+    });
+    let p = push.clone();
+    engine.register_fn("send", move |conn: &str, text: &str| {
+        p(ScriptAction::Send {
+            conn: conn.to_string(),
+            data: text.as_bytes().to_vec(),
+        });
+    });
+    let p = push.clone();
+    engine.register_fn("send_hex", move |conn: &str, hex: &str| -> Result<(), Box<EvalAltResult>> {
+        let data = hex_to_bytes(hex).map_err(|e| e.to_string())?;
+        p(ScriptAction::Send { conn: conn.to_string(), data });
+        Ok(())
+    });
+    for (name, control) in [
+        ("close", ConnectionControl::Close),
+        ("shutdown", ConnectionControl::Shutdown),
+        ("abort", ConnectionControl::Abort),
+    ] {
+        let p = push.clone();
+        engine.register_fn(name, move |conn: &str| p(ScriptAction::Control(conn.to_string(), control)));
+    }
+    let p = push.clone();
+    engine.register_fn("log", move |line: &str| p(ScriptAction::Log(line.to_string())));
+    let p = push.clone();
+    engine.on_print(move |line| p(ScriptAction::Log(line.to_string())));
+    engine.register_fn("now_ms", move || started.elapsed().as_millis() as i64);
+
+    for (name, repeat) in [("set_timeout", false), ("set_interval", true)] {
+        let p = push.clone();
+        let next_timer_id = Arc::clone(&next_timer_id);
+        engine.register_fn(name, move |ms: i64, callback: &str| -> i64 {
+            let id = next_timer_id.fetch_add(1, Ordering::Relaxed) + 1;
+            p(ScriptAction::SetTimer {
+                id,
+                delay: Duration::from_millis(ms.max(0) as u64),
+                repeat,
+                callback: callback.to_string(),
+            });
+            id
+        });
+    }
+    engine.register_fn("clear_timer", move |id: i64| push(ScriptAction::ClearTimer(id)));
+}
+
+/// 在后台运行脚本宿主: 转发协议处理器发往UI的消息并调用钩子，将脚本发送的数据交给协议处理器
+pub fn spawn_script_host(
+    mut host: ScriptHost,
+    mut from_handler: Receiver<Message>,
+    to_ui: Sender<Message>,
+    to_handler: Sender<Message>,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SCRIPT_TICK);
+        loop {
+            tokio::select! {
+                message = from_handler.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    host.on_message(&message);
+                    if to_ui.send(message).await.is_err() {
+                        break;
+                    }
+                }
+                _ = ticker.tick() => host.on_tick(Instant::now()),
+            }
+
+            let (handler_messages, ui_messages) = host.drain();
+            for message in handler_messages {
+                let _ = to_handler.send(message).await;
+            }
+            for message in ui_messages {
+                let _ = to_ui.send(message).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(id: &str) -> Message {
+        Message::new_received(
+            MessageType::ClientConnected,
+            Some(ConnectionInfo {
+                remote_addr: "127.0.0.1:9000".parse().unwrap(),
+                connection_id: id.to_string(),
+                local_addr: None,
+            }),
+        )
+    }
+
+    #[test]
+    fn test_hooks_and_state() {
+        let mut host = ScriptHost::from_source(
+            r#"
+            fn on_connect(conn) { this.count = 0; send(conn, "hello"); }
+            fn on_data(conn, data) {
+                this.count += 1;
+                switch data.as_string() {
+                    "bye" => close(conn),
+                    "half" => shutdown(conn),
+                    "reset" => abort(conn),
+                    _ => send_hex(conn, "AA 0" + this.count),
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let connected = connection("c1");
+        host.on_message(&connected);
+        let mut data = connected.clone();
+        data.content = MessageType::Text("ping".to_string());
+        host.on_message(&data);
+
+        let (to_handler, to_ui) = host.drain();
+        assert_eq!(to_handler.len(), 2);
+        assert_eq!(to_ui.len(), 2);
+        assert_eq!(to_handler[1].content.payload(), Some(Bytes::from_static(&[0xAA, 0x01])));
+
+        // 脚本发出的数据回到宿主时不再调用 on_data
+        for message in &to_handler {
+            host.on_message(message);
+        }
+        let (to_handler, _) = host.drain();
+        assert!(to_handler.is_empty());
+
+        data.content = MessageType::Text("bye".to_string());
+        host.on_message(&data);
+        let (to_handler, _) = host.drain();
+        assert!(matches!(to_handler[0].content, MessageType::Control(ConnectionControl::Close)));
+
+        for (text, control) in [("half", ConnectionControl::Shutdown), ("reset", ConnectionControl::Abort)] {
+            data.content = MessageType::Text(text.to_string());
+            host.on_message(&data);
+            let (to_handler, _) = host.drain();
+            assert!(matches!(to_handler[0].content, MessageType::Control(c) if c == control));
+        }
+    }
+
+    #[test]
+    fn test_timers_and_errors() {
+        let mut host = ScriptHost::from_source(
+            r#"
+            let id = set_interval(0, "beat");
+            set_timeout(0, "missing");
+            fn beat() { log("beat"); }
+            fn on_data(conn, data) { send("nobody", data); }
+            "#,
+        )
+        .unwrap();
+        host.drain();
+
+        host.on_tick(Instant::now());
+        let (_, to_ui) = host.drain();
+        assert_eq!(to_ui.len(), 2);
+        assert_eq!(host.timers.len(), 1);
+
+        let mut data = connection("c1");
+        data.content = MessageType::Text("x".to_string());
+        host.on_message(&data);
+        let (to_handler, to_ui) = host.drain();
+        assert!(to_handler.is_empty());
+        assert_eq!(to_ui.len(), 1);
+
+        assert!(ScriptHost::from_source("fn broken(").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use bytes::Bytes;
+use std::{path::PathBuf, time::Duration};
+use tokio::sync::mpsc::Sender;
+
+use crate::protocols::{common::WriteAck, Message, MessageType, SessionOptions};
+
+/// 文件分块发送选项
+#[derive(Debug, Clone, Default)]
+pub struct ChunkOptions {
+    /// 每块字节数，None 表示整个文件作为一条消息发送
+    pub chunk_size: Option<usize>,
+    /// 块之间的间隔
+    pub delay: Duration,
+}
+
+/// 文件发送进度
+#[derive(Debug, Clone)]
+pub struct TransferProgress {
+    /// 文件路径
+    pub path: String,
+    /// 已写出的字节数
+    pub sent: usize,
+    /// 本次更新新写出的字节数
+    pub chunk: usize,
+    /// 文件分帧后的总字节数
+    pub total: usize,
+    /// 是否已结束 (成功或失败)
+    pub done: bool,
+    /// 失败原因
+    pub error: Option<String>,
+}
+
+impl TransferProgress {
+    /// 完成百分比
+    pub fn percent(&self) -> usize {
+        (self.sent * 100).checked_div(self.total).unwrap_or(100)
+    }
+}
+
+/// 解析输入框中的文件引用 (`@path/to/file`)
+pub fn parse_file_reference(input: &str) -> Option<PathBuf> {
+    input
+        .strip_prefix('@')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// 在后台读取文件并按块通过协议处理器发送，进度通过 `progress_tx` 上报
+///
+/// 分帧和校验值按整个文件处理一次，各块是线路数据的切片，原样发送: TCP 为一次写入，
+/// UDP 为一个数据报。每块写出后才计入进度，块被丢弃 (未连接、写入失败等) 时发送失败。
+pub fn spawn_file_transfer(
+    path: PathBuf,
+    chunking: ChunkOptions,
+    options: SessionOptions,
+    ui_to_server_tx: Sender<Message>,
+    progress_tx: Sender<TransferProgress>,
+) {
+    tokio::spawn(async move {
+        let display_path = path.display().to_string();
+        let mut progress = TransferProgress {
+            path: display_path,
+            sent: 0,
+            chunk: 0,
+            total: 0,
+            done: false,
+            error: None,
+        };
+
+        let data = tokio::fs::read(&path).await.map_err(|e| e.to_string()).and_then(|data| {
+            let message = MessageType::Binary(Bytes::from(data));
+            options.outgoing_frame(&message).map_err(|e| e.to_string())
+        });
+        let data = match data {
+            Ok(data) => data.unwrap_or_default(),
+            Err(e) => {
+                progress.done = true;
+                progress.error = Some(e);
+                let _ = progress_tx.send(progress).await;
+                return;
+            }
+        };
+        progress.total = data.len();
+
+        let chunk_size = chunking.chunk_size.filter(|size| *size > 0).unwrap_or(data.len().max(1));
+        let mut offset = 0;
+        while offset < data.len() {
+            let end = (offset + chunk_size).min(data.len());
+            let (ack, mut written) = WriteAck::new();
+            let message = Message {
+                ack,
+                ..Message::new_sent(MessageType::Raw(data.slice(offset..end)), None)
+            };
+            if ui_to_server_tx.send(message).await.is_err() {
+                progress.error = Some("Connection closed".to_string());
+                break;
+            }
+            if written.recv().await.is_none() {
+                progress.error = Some("Not sent (not connected or write failed)".to_string());
+                break;
+            }
+
+            progress.chunk = end - offset;
+            progress.sent = end;
+            offset = end;
+            let _ = progress_tx.send(progress.clone()).await;
+
+            if offset < data.len() && !chunking.delay.is_zero() {
+                tokio::time::sleep(chunking.delay).await;
+            }
+        }
+
+        progress.done = true;
+        progress.chunk = 0;
+        let _ = progress_tx.send(progress).await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocols::framing::FramingConfig;
+    use crate::protocols::ConnectionInfo;
+    use tokio::sync::mpsc::{channel, Receiver};
+
+    #[test]
+    fn test_parse_file_reference() {
+        assert_eq!(parse_file_reference("@ data/fw.bin "), Some(PathBuf::from("data/fw.bin")));
+        assert_eq!(parse_file_reference("@"), None);
+        assert_eq!(parse_file_reference("data/fw.bin"), None);
+    }
+
+    /// 写入临时文件并启动发送，返回文件路径、处理器收到的消息和进度
+    fn start(
+        name: &str,
+        content: &[u8],
+        chunk_size: usize,
+        options: SessionOptions,
+    ) -> (PathBuf, Receiver<Message>, Receiver<TransferProgress>) {
+        let path = std::env::temp_dir().join(format!("nt-transfer-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        let (tx, rx) = channel(16);
+        let (progress_tx, progress_rx) = channel(16);
+        let chunking = ChunkOptions { chunk_size: Some(chunk_size), delay: Duration::ZERO };
+        spawn_file_transfer(path.clone(), chunking, options, tx, progress_tx);
+        (path, rx, progress_rx)
+    }
+
+    #[tokio::test]
+    async fn test_chunks_frame_file_once() {
+        let options = SessionOptions {
+            framing: FramingConfig {
+                mode: "len:2".parse().unwrap(),
+                idle_gap: None,
+            },
+            ..Default::default()
+        };
+        let (path, mut rx, mut progress_rx) = start("framed", b"hello", 3, options);
+
+        // 长度前缀只出现在整个文件之前，每块原样发送，写出后才计入进度
+        let mut wire = Vec::new();
+        for expected in [&[0x00, 0x05, b'h'][..], b"ell", b"o"] {
+            let message = rx.recv().await.unwrap();
+            let MessageType::Raw(chunk) = &message.content else {
+                panic!("chunk should be sent raw: {:?}", message.content);
+            };
+            assert_eq!(&chunk[..], expected);
+            wire.extend_from_slice(chunk);
+            assert!(progress_rx.try_recv().is_err());
+            message.ack.written(&ConnectionInfo::non_ip("test".to_string()), chunk);
+            let progress = progress_rx.recv().await.unwrap();
+            assert_eq!((progress.sent, progress.total), (wire.len(), 7));
+        }
+        assert!(progress_rx.recv().await.unwrap().done);
+        assert_eq!(wire, b"\x00\x05hello");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropped_chunk_fails() {
+        let (path, mut rx, mut progress_rx) = start("dropped", b"hello", 2, SessionOptions::default());
+        // 处理器丢弃消息 (如未连接) 时发送失败，不计入进度
+        drop(rx.recv().await.unwrap());
+        let progress = progress_rx.recv().await.unwrap();
+        assert!(progress.done);
+        assert_eq!(progress.sent, 0);
+        assert!(progress.error.is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+}
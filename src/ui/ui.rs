@@ -21,10 +21,48 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
     // 绘制顶部状态栏 (统计信息)
     app.status_bar.draw_top_bar(frame, vertical_chunks[0], app);
 
+    // 有周期发送任务或打开了自动回复、套接字选项、故障注入面板时在右侧显示侧栏
+    let show_repeaters = !app.repeaters.is_empty();
+    let show_rules = app.rule_panel.visible;
+    let show_socket = app.socket_panel.visible;
+    let show_faults = app.fault_panel.visible;
+    let panels = [show_repeaters, show_rules, show_socket, show_faults].iter().filter(|shown| **shown).count() as u32;
+    let content_area = if panels == 0 {
+        vertical_chunks[1]
+    } else {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(1),     // 发送和接收区
+                Constraint::Length(40), // 侧栏
+            ])
+            .split(vertical_chunks[1]);
+        let side = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Ratio(1, panels); panels as usize])
+            .split(chunks[1]);
+        let mut areas = side.iter();
+        if show_repeaters {
+            app.repeater_panel.draw(frame, *areas.next().unwrap(), &app.repeaters);
+        }
+        if show_rules {
+            app.rule_panel.draw(frame, *areas.next().unwrap(), &app.args.options.auto_reply);
+        }
+        if show_socket {
+            app.socket_panel.draw(frame, *areas.next().unwrap(), &app.args.options.socket);
+        }
+        if show_faults {
+            let target = app.receive_view.selected_connection();
+            let config = app.args.options.faults.get(target);
+            app.fault_panel.draw(frame, *areas.next().unwrap(), &config, target);
+        }
+        chunks[0]
+    };
+
     // 根据布局类型绘制中间的发送和接收区
     match app.layout.layout_type {
-        LayoutType::HorizontalSplit => draw_horizontal(frame, app, vertical_chunks[1]),
-        LayoutType::VerticalSplit => draw_vertical(frame, app, vertical_chunks[1]),
+        LayoutType::HorizontalSplit => draw_horizontal(frame, app, content_area),
+        LayoutType::VerticalSplit => draw_vertical(frame, app, content_area),
     };
 
     // 绘制底部状态栏 (快捷键提示)
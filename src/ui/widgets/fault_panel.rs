@@ -0,0 +1,137 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+use std::time::Duration;
+
+use crate::protocols::fault::FaultConfig;
+
+/// 面板中的故障项
+const ITEMS: [&str; 8] = [
+    "Latency",
+    "Jitter",
+    "Bandwidth",
+    "Drop (UDP)",
+    "Corrupt",
+    "Segment (TCP)",
+    "Close after",
+    "RST on close",
+];
+
+/// 延迟的调整步长 (毫秒)
+const LATENCY_STEP: u64 = 50;
+/// 抖动的调整步长 (毫秒)
+const JITTER_STEP: u64 = 10;
+/// 丢包概率的调整步长
+const DROP_STEP: f64 = 0.05;
+/// 篡改概率的最小非零值，之后按倍数调整
+const CORRUPT_MIN: f64 = 0.001;
+/// 自动关闭时间的调整步长 (秒)
+const CLOSE_STEP: u64 = 5;
+
+/// 故障注入面板
+#[derive(Default)]
+pub struct FaultPanel {
+    /// 是否显示
+    pub visible: bool,
+    /// 选中的故障项索引
+    pub selected: usize,
+}
+
+/// 按倍数调整的上限类设置: 关闭时减小从 `start` 开始，增大超过 `start` 时关闭
+fn scale_limit(value: Option<u64>, up: bool, start: u64, min: u64) -> Option<u64> {
+    match (value, up) {
+        (None, true) => None,
+        (None, false) => Some(start),
+        (Some(value), true) if value >= start => None,
+        (Some(value), true) => Some(value.saturating_mul(2)),
+        (Some(value), false) => Some((value / 2).max(min)),
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{} ms", duration.as_millis())
+}
+
+fn percent(value: f64) -> String {
+    format!("{:.1}%", value * 100.0)
+}
+
+impl FaultPanel {
+    /// 选中下一个故障项
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % ITEMS.len();
+    }
+
+    /// 按 `up` 增大或减小选中的故障项 (开关类切换)
+    pub fn adjust(&self, config: &mut FaultConfig, up: bool) {
+        let step = |value: Duration, step: u64| {
+            let value = value.as_millis() as u64;
+            Duration::from_millis(if up { value + step } else { value.saturating_sub(step) })
+        };
+        match self.selected {
+            0 => config.latency = step(config.latency, LATENCY_STEP),
+            1 => config.jitter = step(config.jitter, JITTER_STEP),
+            // 关闭 -> 1 MiB/s -> 512 KiB/s ... -> 64 B/s
+            2 => config.bandwidth = scale_limit(config.bandwidth, up, 1024 * 1024, 64),
+            3 => config.drop = (config.drop + if up { DROP_STEP } else { -DROP_STEP }).clamp(0.0, 1.0),
+            4 => {
+                config.corrupt = match (config.corrupt, up) {
+                    (corrupt, true) if corrupt < CORRUPT_MIN => CORRUPT_MIN,
+                    (corrupt, true) => (corrupt * 2.0).min(1.0),
+                    (corrupt, false) if corrupt <= CORRUPT_MIN => 0.0,
+                    (corrupt, false) => corrupt / 2.0,
+                }
+            }
+            // 关闭 -> 1024 -> 512 ... -> 1 字节
+            5 => {
+                let segment = config.segment.map(|segment| segment as u64);
+                config.segment = scale_limit(segment, up, 1024, 1).map(|segment| segment as usize);
+            }
+            6 => {
+                let secs = config.close_after.map(|after| after.as_secs()).unwrap_or(0);
+                let secs = if up { secs + CLOSE_STEP } else { secs.saturating_sub(CLOSE_STEP) };
+                config.close_after = (secs > 0).then(|| Duration::from_secs(secs));
+            }
+            _ => config.abrupt_close = !config.abrupt_close,
+        }
+    }
+
+    /// 绘制故障项列表，`scope` 为设置作用的连接，None 表示整个会话
+    pub fn draw(&self, frame: &mut Frame, area: Rect, config: &FaultConfig, scope: Option<&str>) {
+        let values = [
+            millis(config.latency),
+            millis(config.jitter),
+            config.bandwidth.map(|bandwidth| format!("{} B/s", bandwidth)).unwrap_or_else(|| "off".to_string()),
+            percent(config.drop),
+            percent(config.corrupt),
+            config.segment.map(|segment| format!("{} B", segment)).unwrap_or_else(|| "off".to_string()),
+            config.close_after.map(|after| format!("{} s", after.as_secs())).unwrap_or_else(|| "off".to_string()),
+            if config.abrupt_close { "on" } else { "off" }.to_string(),
+        ];
+
+        let items: Vec<ListItem> = ITEMS
+            .iter()
+            .zip(values)
+            .enumerate()
+            .map(|(index, (name, value))| {
+                let style = if index == self.selected {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::from(format!("{:<13} {}", name, value))).style(style)
+            })
+            .collect();
+
+        let list = List::new(items).block(
+            Block::default()
+                .title(format!("Faults [{}] (j: next, +/-)", scope.unwrap_or("session")))
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(list, area);
+    }
+}
@@ -6,6 +6,9 @@ use ratatui::{
     Frame,
 };
 
+use crate::protocols::proxy::ProxyDirection;
+use crate::utils::template::Template;
+
 /// 输入对话框组件
 pub struct InputDialog {
     /// 用户输入的文本
@@ -16,12 +19,51 @@ pub struct InputDialog {
     pub selected_client: Option<usize>,
     /// 可用的客户端列表
     pub clients: Vec<String>,
+    /// 可用的二进制结构模板
+    pub templates: Vec<Template>,
+    /// 当前选择的模板索引
+    pub template_index: usize,
+    /// 模板各输入字段的内容
+    pub field_inputs: Vec<String>,
+    /// 当前编辑的模板字段索引
+    pub field_cursor: usize,
+    /// 重复发送间隔 (毫秒)，为空表示只发送一次
+    pub repeat_interval: String,
+    /// 重复发送次数，为空表示一直发送
+    pub repeat_count: String,
+    /// 当前输入焦点
+    pub focus: DialogFocus,
+    /// 代理模式下数据注入的方向
+    pub proxy_direction: Option<ProxyDirection>,
+    /// 是否在编辑代理扣留的数据
+    pub held_edit: bool,
+}
+
+/// 输入焦点
+#[derive(Clone, Copy, PartialEq)]
+pub enum DialogFocus {
+    /// 消息内容 (或模板字段)
+    Message,
+    /// 重复间隔
+    RepeatInterval,
+    /// 重复次数
+    RepeatCount,
+}
+
+/// 重复发送设置
+pub struct RepeatSpec {
+    /// 发送间隔
+    pub interval: std::time::Duration,
+    /// 发送次数，None 表示一直发送
+    pub count: Option<u64>,
 }
 
 /// 数据发送格式
 pub enum FormatType {
     String,
     Hex,
+    /// 按二进制结构模板填写字段
+    Template,
 }
 
 impl InputDialog {
@@ -31,9 +73,137 @@ impl InputDialog {
             format_type: FormatType::String,
             selected_client: None,
             clients: Vec::new(),
+            templates: Vec::new(),
+            template_index: 0,
+            field_inputs: Vec::new(),
+            field_cursor: 0,
+            repeat_interval: String::new(),
+            repeat_count: String::new(),
+            focus: DialogFocus::Message,
+            proxy_direction: None,
+            held_edit: false,
+        }
+    }
+
+    /// 编辑代理扣留的数据: 以十六进制填入原数据，方向固定
+    pub fn for_held(direction: ProxyDirection, hex: String) -> Self {
+        Self {
+            input: hex,
+            format_type: FormatType::Hex,
+            proxy_direction: Some(direction),
+            held_edit: true,
+            ..Self::new()
+        }
+    }
+
+    /// 切换代理模式下数据注入的方向
+    pub fn toggle_direction(&mut self) {
+        if !self.held_edit {
+            self.proxy_direction = self.proxy_direction.map(ProxyDirection::reverse);
         }
     }
 
+    /// 在消息内容、重复间隔、重复次数之间切换焦点
+    pub fn cycle_focus(&mut self) {
+        self.focus = match self.focus {
+            DialogFocus::Message => DialogFocus::RepeatInterval,
+            DialogFocus::RepeatInterval => DialogFocus::RepeatCount,
+            DialogFocus::RepeatCount => DialogFocus::Message,
+        };
+    }
+
+    /// 解析重复发送设置，未填写间隔时返回 Ok(None)
+    pub fn repeat_spec(&self) -> Result<Option<RepeatSpec>, String> {
+        let interval = self.repeat_interval.trim();
+        if interval.is_empty() {
+            return Ok(None);
+        }
+        let interval: u64 = interval
+            .parse()
+            .map_err(|_| format!("Invalid repeat interval: {}", interval))?;
+        if interval == 0 {
+            return Err("Repeat interval must be greater than 0".to_string());
+        }
+
+        let count = self.repeat_count.trim();
+        let count = if count.is_empty() {
+            None
+        } else {
+            Some(count.parse::<u64>().map_err(|_| format!("Invalid repeat count: {}", count))?)
+        };
+
+        Ok(Some(RepeatSpec {
+            interval: std::time::Duration::from_millis(interval),
+            count,
+        }))
+    }
+
+    /// 设置可用的模板
+    pub fn set_templates(&mut self, templates: Vec<Template>) {
+        self.templates = templates;
+        self.select_template(0);
+    }
+
+    /// 当前选择的模板
+    pub fn current_template(&self) -> Option<&Template> {
+        self.templates.get(self.template_index)
+    }
+
+    /// 选择模板并清空字段输入
+    fn select_template(&mut self, index: usize) {
+        self.template_index = index;
+        self.field_cursor = 0;
+        self.field_inputs = self
+            .current_template()
+            .map(|t| vec![String::new(); t.input_fields().count()])
+            .unwrap_or_default();
+    }
+
+    /// 切换到下一个模板
+    pub fn next_template(&mut self) {
+        if !self.templates.is_empty() {
+            self.select_template((self.template_index + 1) % self.templates.len());
+        }
+    }
+
+    /// 切换到上一个模板
+    pub fn prev_template(&mut self) {
+        if !self.templates.is_empty() {
+            self.select_template((self.template_index + self.templates.len() - 1) % self.templates.len());
+        }
+    }
+
+    /// 移动到下一个模板字段
+    pub fn next_field(&mut self) {
+        if !self.field_inputs.is_empty() {
+            self.field_cursor = (self.field_cursor + 1) % self.field_inputs.len();
+        }
+    }
+
+    /// 移动到上一个模板字段
+    pub fn prev_field(&mut self) {
+        if !self.field_inputs.is_empty() {
+            self.field_cursor = (self.field_cursor + self.field_inputs.len() - 1) % self.field_inputs.len();
+        }
+    }
+
+    /// 当前正在编辑的输入内容 (模板模式下为当前字段)
+    pub fn active_input_mut(&mut self) -> &mut String {
+        match (self.focus, &self.format_type) {
+            (DialogFocus::RepeatInterval, _) => &mut self.repeat_interval,
+            (DialogFocus::RepeatCount, _) => &mut self.repeat_count,
+            (DialogFocus::Message, FormatType::Template) if !self.field_inputs.is_empty() => {
+                &mut self.field_inputs[self.field_cursor]
+            }
+            _ => &mut self.input,
+        }
+    }
+
+    /// 按当前模板序列化字段输入
+    pub fn encode_template(&self) -> Option<Result<Vec<u8>, String>> {
+        self.current_template().map(|t| t.encode(&self.field_inputs))
+    }
+
     /// 添加客户端
     pub fn add_client(&mut self, client: String) {
         self.clients.push(client);
@@ -46,7 +216,8 @@ impl InputDialog {
     pub fn toggle_format(&mut self) {
         self.format_type = match self.format_type {
             FormatType::String => FormatType::Hex,
-            FormatType::Hex => FormatType::String,
+            FormatType::Hex if !self.templates.is_empty() => FormatType::Template,
+            FormatType::Hex | FormatType::Template => FormatType::String,
         };
     }
 
@@ -64,7 +235,10 @@ impl InputDialog {
         // 计算对话框的尺寸和位置
         let area = frame.size();
         let width = area.width.min(60);
-        let height = 10;
+        let height = match (&self.format_type, self.current_template()) {
+            (FormatType::Template, Some(template)) => (template.fields.len() as u16 + 7).min(area.height),
+            _ => 10,
+        };
         let x = (area.width - width) / 2;
         let y = (area.height - height) / 2;
         let dialog_area = Rect::new(x, y, width, height);
@@ -74,7 +248,7 @@ impl InputDialog {
 
         // 创建对话框边框
         let block = Block::default()
-            .title("Send Message")
+            .title(if self.held_edit { "Edit Held Data (empty: drop)" } else { "Send Message" })
             .borders(Borders::ALL)
             .style(Style::default().bg(Color::DarkGray));
 
@@ -85,7 +259,7 @@ impl InputDialog {
             .constraints([
                 Constraint::Length(1),  // 格式选择
                 Constraint::Length(1),  // 客户端选择
-                Constraint::Length(1),  // 间距
+                Constraint::Length(1),  // 重复发送设置
                 Constraint::Min(3),     // 输入区域
             ])
             .split(dialog_area);
@@ -94,13 +268,15 @@ impl InputDialog {
         frame.render_widget(block, dialog_area);
 
         // 绘制格式选择标签
-        let format_tabs = Tabs::new(vec![
-            Line::from("String"),
-            Line::from("Hex"),
-        ])
+        let mut formats = vec![Line::from("String"), Line::from("Hex")];
+        if let Some(template) = self.current_template() {
+            formats.push(Line::from(format!("Template: {}", template.name)));
+        }
+        let format_tabs = Tabs::new(formats)
         .select(match self.format_type {
             FormatType::String => 0,
             FormatType::Hex => 1,
+            FormatType::Template => 2,
         })
         .style(Style::default().fg(Color::White))
         .highlight_style(Style::default().fg(Color::Yellow));
@@ -117,6 +293,32 @@ impl InputDialog {
             
             frame.render_widget(Paragraph::new("Client:"), chunks[1]);
             frame.render_widget(client_tabs, chunks[1]);
+        } else if let Some(direction) = self.proxy_direction {
+            let hint = if self.held_edit { "" } else { " (Ctrl+D)" };
+            frame.render_widget(Paragraph::new(format!("Direction: {}{}", direction, hint)), chunks[1]);
+        }
+
+        // 绘制重复发送设置
+        let focused = |focus: DialogFocus| {
+            if self.focus == focus {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            }
+        };
+        let repeat_line = Line::from(vec![
+            Span::raw("Repeat every "),
+            Span::styled(format!("[{:>5}]", self.repeat_interval), focused(DialogFocus::RepeatInterval)),
+            Span::raw(" ms, "),
+            Span::styled(format!("[{:>4}]", self.repeat_count), focused(DialogFocus::RepeatCount)),
+            Span::raw(" times (Ctrl+R)"),
+        ]);
+        frame.render_widget(Paragraph::new(repeat_line), chunks[2]);
+
+        // 模板模式下绘制字段表单
+        if let (FormatType::Template, Some(template)) = (&self.format_type, self.current_template()) {
+            self.draw_template_form(frame, template, chunks[3]);
+            return;
         }
 
         // 绘制输入区域
@@ -131,9 +333,55 @@ impl InputDialog {
         frame.render_widget(input_paragraph, chunks[3]);
 
         // 显示光标
-        frame.set_cursor_position((
-            chunks[3].x + 1 + self.input.len() as u16,
-            chunks[3].y + 1,
-        ));
+        if self.focus == DialogFocus::Message {
+            frame.set_cursor_position((
+                chunks[3].x + 1 + self.input.len() as u16,
+                chunks[3].y + 1,
+            ));
+        }
+    }
+
+    /// 绘制模板字段表单: 输入字段可编辑，计算字段和校验字段只显示说明
+    fn draw_template_form(&self, frame: &mut Frame, template: &Template, area: Rect) {
+        let mut lines = Vec::with_capacity(template.fields.len());
+        let mut input_index = 0;
+        let mut cursor = None;
+
+        for field in &template.fields {
+            if field.is_input() {
+                let selected = input_index == self.field_cursor;
+                let label = format!("{} {}: ", if selected { ">" } else { " " }, field.name);
+                let value = self.field_inputs.get(input_index).cloned().unwrap_or_default();
+                if selected {
+                    cursor = Some((
+                        area.x + 1 + (label.chars().count() + value.chars().count()) as u16,
+                        area.y + 1 + lines.len() as u16,
+                    ));
+                }
+                let style = if selected {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                lines.push(Line::from(vec![Span::styled(label, style), Span::raw(value)]));
+                input_index += 1;
+            } else {
+                lines.push(Line::from(Span::styled(
+                    format!("  {}: (auto)", field.name),
+                    Style::default().fg(Color::Gray),
+                )));
+            }
+        }
+
+        let form = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Up/Down: field | Left/Right: template"),
+        );
+        frame.render_widget(form, area);
+
+        if let (Some(position), DialogFocus::Message) = (cursor, self.focus) {
+            frame.set_cursor_position(position);
+        }
     }
 }
\ No newline at end of file
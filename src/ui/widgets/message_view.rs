@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -6,8 +8,17 @@ use ratatui::{
     Frame,
 };
 
+use crate::protocols::resolve::IpFamily;
 use crate::ui::widgets::tabs::TabsState;
 
+/// 连接标签页的显示名称，标题为地址时附带地址族
+fn connection_label(title: &str) -> String {
+    match title.parse::<SocketAddr>() {
+        Ok(addr) => format!("{} ({})", title, IpFamily::of(&addr)),
+        Err(_) => title.to_string(),
+    }
+}
+
 /// 消息视图组件
 pub struct MessageView {
     /// 标题
@@ -155,6 +166,12 @@ impl MessageView {
             tabs.previous();
         }
     }
+
+    /// 当前选中的连接标签页标题 (连接 ID)，选中默认标签页时返回 None
+    pub fn selected_connection(&self) -> Option<&str> {
+        let tabs = self.tabs.as_ref()?;
+        (tabs.index > 0).then(|| tabs.titles.get(tabs.index).map(String::as_str)).flatten()
+    }
     /// 绘制视图
     pub fn draw(&self, frame: &mut Frame, area: Rect) {
         // 创建一个带边框的块
@@ -180,7 +197,7 @@ impl MessageView {
             // 绘制标签页
             if let Some(tabs) = &self.tabs {
                 // 渲染标签页标题
-                let titles: Vec<Line> = tabs.titles.iter().map(|t| Line::from(t.as_str())).collect();
+                let titles: Vec<Line> = tabs.titles.iter().map(|t| Line::from(connection_label(t))).collect();
 
                 let tabs_widget = ratatui::widgets::Tabs::new(titles)
                     .block(Block::default().borders(Borders::BOTTOM))
@@ -1,4 +1,8 @@
 pub mod status_bar;
 pub mod message_view;
 pub mod input_dialog;
-pub mod tabs;
\ No newline at end of file
+pub mod tabs;
+pub mod repeater_panel;
+pub mod rule_panel;
+pub mod socket_panel;
+pub mod fault_panel;
@@ -0,0 +1,41 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+
+use crate::repeater::RepeaterSet;
+
+/// 周期发送任务面板
+#[derive(Default)]
+pub struct RepeaterPanel {}
+
+impl RepeaterPanel {
+    /// 绘制周期发送任务列表，选中项高亮
+    pub fn draw(&self, frame: &mut Frame, area: Rect, repeaters: &RepeaterSet) {
+        let items: Vec<ListItem> = repeaters
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, repeater)| {
+                let style = if index == repeaters.selected {
+                    Style::default().fg(Color::Yellow)
+                } else if repeater.paused {
+                    Style::default().fg(Color::Gray)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::from(repeater.summary())).style(style)
+            })
+            .collect();
+
+        let list = List::new(items).block(
+            Block::default()
+                .title("Repeaters (n: next, p: pause, x: cancel)")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(list, area);
+    }
+}
@@ -0,0 +1,55 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+
+use crate::protocols::autoreply::AutoReplyRules;
+
+/// 自动回复规则面板
+#[derive(Default)]
+pub struct RulePanel {
+    /// 是否显示
+    pub visible: bool,
+    /// 选中的规则索引
+    pub selected: usize,
+}
+
+impl RulePanel {
+    /// 选中下一条规则
+    pub fn select_next(&mut self, rules: &AutoReplyRules) {
+        let count = rules.summaries().len();
+        if count > 0 {
+            self.selected = (self.selected + 1) % count;
+        }
+    }
+
+    /// 绘制规则列表，已禁用的规则灰色显示，选中项高亮
+    pub fn draw(&self, frame: &mut Frame, area: Rect, rules: &AutoReplyRules) {
+        let items: Vec<ListItem> = rules
+            .summaries()
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, enabled))| {
+                let style = if index == self.selected {
+                    Style::default().fg(Color::Yellow)
+                } else if !enabled {
+                    Style::default().fg(Color::Gray)
+                } else {
+                    Style::default()
+                };
+                let mark = if enabled { "[x]" } else { "[ ]" };
+                ListItem::new(Line::from(format!("{} {}", mark, name))).style(style)
+            })
+            .collect();
+
+        let list = List::new(items).block(
+            Block::default()
+                .title("Auto-reply (r: next, space: toggle)")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(list, area);
+    }
+}
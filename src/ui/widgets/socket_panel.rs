@@ -0,0 +1,132 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+use std::time::Duration;
+
+use crate::protocols::sockopt::{Keepalive, SocketOption, SocketOptions};
+
+/// 面板中的选项，后三项只能在绑定前设置，运行时只读
+const ITEMS: [&str; 9] = [
+    "TCP_NODELAY",
+    "SO_KEEPALIVE",
+    "SO_RCVBUF",
+    "SO_SNDBUF",
+    "SO_LINGER",
+    "IP_TTL",
+    "SO_REUSEADDR",
+    "SO_REUSEPORT",
+    "IPV6_V6ONLY",
+];
+
+/// 保活空闲时间的调整步长 (秒)
+const KEEPALIVE_STEP: u64 = 10;
+
+/// 套接字选项面板
+#[derive(Default)]
+pub struct SocketPanel {
+    /// 是否显示
+    pub visible: bool,
+    /// 选中的选项索引
+    pub selected: usize,
+}
+
+fn on_off(value: Option<bool>) -> String {
+    match value {
+        Some(true) => "on".to_string(),
+        Some(false) => "off".to_string(),
+        None => "default".to_string(),
+    }
+}
+
+fn or_default<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_else(|| "default".to_string())
+}
+
+impl SocketPanel {
+    /// 选中下一个选项
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % ITEMS.len();
+    }
+
+    /// 按 `up` 增大或减小选中的选项 (开关类选项切换)，返回修改后的选项；只读选项返回 None
+    pub fn adjust(&self, options: &SocketOptions, up: bool) -> Option<SocketOption> {
+        let buffer = |size: Option<usize>| {
+            let size = size.unwrap_or(64 * 1024);
+            if up { size.saturating_mul(2) } else { (size / 2).max(1024) }
+        };
+        match self.selected {
+            0 => Some(SocketOption::NoDelay(!options.nodelay.unwrap_or(false))),
+            1 => {
+                let current = options.keepalive.map(|keepalive| keepalive.idle.as_secs()).unwrap_or(0);
+                let idle = if up { current + KEEPALIVE_STEP } else { current.saturating_sub(KEEPALIVE_STEP) };
+                let keepalive = (idle > 0).then(|| Keepalive {
+                    idle: Duration::from_secs(idle),
+                    interval: options.keepalive.and_then(|keepalive| keepalive.interval),
+                    count: options.keepalive.and_then(|keepalive| keepalive.count),
+                });
+                Some(SocketOption::Keepalive(keepalive))
+            }
+            2 => Some(SocketOption::RecvBuffer(buffer(options.recv_buffer))),
+            3 => Some(SocketOption::SendBuffer(buffer(options.send_buffer))),
+            // 关闭 -> 0 (RST) -> 1s -> 2s ...
+            4 => Some(SocketOption::Linger(match (options.linger, up) {
+                (None, true) => Some(Duration::ZERO),
+                (None, false) => None,
+                (Some(linger), true) => Some(linger + Duration::from_secs(1)),
+                (Some(linger), false) if linger.is_zero() => None,
+                (Some(linger), false) => Some(linger - Duration::from_secs(1)),
+            })),
+            5 => {
+                let ttl = options.ttl.unwrap_or(64);
+                Some(SocketOption::Ttl(if up { (ttl + 1).min(255) } else { ttl.saturating_sub(1).max(1) }))
+            }
+            _ => None,
+        }
+    }
+
+    /// 绘制选项列表，只读选项灰色显示，选中项高亮
+    pub fn draw(&self, frame: &mut Frame, area: Rect, options: &SocketOptions) {
+        let values = [
+            on_off(options.nodelay),
+            or_default(options.keepalive),
+            or_default(options.recv_buffer),
+            or_default(options.send_buffer),
+            match options.linger {
+                Some(linger) if linger.is_zero() => "0 (RST)".to_string(),
+                Some(linger) => format!("{}s", linger.as_secs()),
+                None => "off".to_string(),
+            },
+            or_default(options.ttl),
+            on_off(options.reuse_addr),
+            on_off(options.reuse_port),
+            on_off(options.only_v6),
+        ];
+
+        let items: Vec<ListItem> = ITEMS
+            .iter()
+            .zip(values)
+            .enumerate()
+            .map(|(index, (name, value))| {
+                let style = if index == self.selected {
+                    Style::default().fg(Color::Yellow)
+                } else if index >= 6 {
+                    Style::default().fg(Color::Gray)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::from(format!("{:<13} {}", name, value))).style(style)
+            })
+            .collect();
+
+        let list = List::new(items).block(
+            Block::default()
+                .title("Socket (j: next, +/-: change)")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(list, area);
+    }
+}
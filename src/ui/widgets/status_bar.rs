@@ -22,17 +22,38 @@ impl Default for StatusBar {
 impl StatusBar {
     /// 绘制顶部状态栏
     pub fn draw_top_bar(&self, frame: &mut Frame, area: Rect, app: &App) {
-        let status_text = format!(
+        let mut status_text = format!(
             " Sent: {} bytes | Received: {} bytes | Status: {} ",
             app.stats.sent_bytes,
             app.stats.received_bytes,
-            if app.stats.connected {
-                "Connected"
-            } else {
-                "Disconnected"
+            match app.stats.link {
+                Some(state) => state.to_string(),
+                None if app.stats.connected => "Connected".to_string(),
+                None => "Disconnected".to_string(),
             }
         );
 
+        if app.stats.repeated_sends > 0 {
+            status_text.push_str(&format!("| Repeated: {} ", app.stats.repeated_sends));
+        }
+
+        // 文件发送进度
+        if let Some(transfer) = &app.stats.transfer {
+            let state = match (&transfer.error, transfer.done) {
+                (Some(_), _) => "failed".to_string(),
+                (None, true) => "done".to_string(),
+                (None, false) => format!("{}%", transfer.percent()),
+            };
+            status_text.push_str(&format!(
+                "| File: {} {}/{} bytes ({}) ",
+                transfer.path, transfer.sent, transfer.total, state
+            ));
+        }
+
+        if let Some(path) = app.args.options.capture.path() {
+            status_text.push_str(&format!("| Log: {} ", path.display()));
+        }
+
         let status_widget = Paragraph::new(Span::styled(
             status_text,
             Style::default().fg(Color::Black).bg(Color::LightCyan),
@@ -43,7 +64,7 @@ impl StatusBar {
 
     /// 绘制底部状态栏 (快捷键提示)
     pub fn draw_bottom_bar(&self, frame: &mut Frame, area: Rect) {
-        let help_text = " Ctrl+C: Quit | I: Input Message ";
+        let help_text = " Ctrl+C: Quit | I: Input Message | Tab: String/Hex | @path: Send File | N/P/X: Repeaters | A: Auto-reply | O: Socket | M: Faults | Tab: Conn | F/C/K: FIN/Close/RST | L: Log | W: Export pcap | H: Export HAR ";
 
         let help_widget = Paragraph::new(Span::styled(
             help_text,
@@ -0,0 +1,327 @@
+use std::{fmt, str::FromStr};
+
+use crate::utils::data_format::{read_uint, write_uint, Endian};
+
+/// 校验算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    /// 逐字节累加取低 8 位
+    Sum8,
+    /// 逐字节异或
+    Xor8,
+    /// CRC-8 (多项式 0x07，初值 0x00)
+    Crc8,
+    /// CRC-16/MODBUS (多项式 0x8005 反射，初值 0xFFFF)
+    Crc16Modbus,
+    /// CRC-16/CCITT-FALSE (多项式 0x1021，初值 0xFFFF)
+    Crc16Ccitt,
+    /// CRC-32 (IEEE 802.3)
+    Crc32,
+}
+
+impl ChecksumAlgorithm {
+    /// 校验值字节数
+    pub fn width(&self) -> usize {
+        match self {
+            ChecksumAlgorithm::Sum8 | ChecksumAlgorithm::Xor8 | ChecksumAlgorithm::Crc8 => 1,
+            ChecksumAlgorithm::Crc16Modbus | ChecksumAlgorithm::Crc16Ccitt => 2,
+            ChecksumAlgorithm::Crc32 => 4,
+        }
+    }
+
+    /// 协议惯用的字节序 (Modbus 低字节在前)
+    pub fn default_endian(&self) -> Endian {
+        match self {
+            ChecksumAlgorithm::Crc16Modbus => Endian::Little,
+            _ => Endian::Big,
+        }
+    }
+
+    /// 计算数据的校验值
+    pub fn compute(&self, data: &[u8]) -> u64 {
+        match self {
+            ChecksumAlgorithm::Sum8 => data.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) as u64,
+            ChecksumAlgorithm::Xor8 => data.iter().fold(0u8, |acc, b| acc ^ b) as u64,
+            ChecksumAlgorithm::Crc8 => {
+                let mut crc = 0u8;
+                for byte in data {
+                    crc ^= byte;
+                    for _ in 0..8 {
+                        crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+                    }
+                }
+                crc as u64
+            }
+            ChecksumAlgorithm::Crc16Modbus => {
+                let mut crc = 0xFFFFu16;
+                for byte in data {
+                    crc ^= *byte as u16;
+                    for _ in 0..8 {
+                        crc = if crc & 0x0001 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+                    }
+                }
+                crc as u64
+            }
+            ChecksumAlgorithm::Crc16Ccitt => {
+                let mut crc = 0xFFFFu16;
+                for byte in data {
+                    crc ^= (*byte as u16) << 8;
+                    for _ in 0..8 {
+                        crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+                    }
+                }
+                crc as u64
+            }
+            ChecksumAlgorithm::Crc32 => {
+                let mut crc = 0xFFFF_FFFFu32;
+                for byte in data {
+                    crc ^= *byte as u32;
+                    for _ in 0..8 {
+                        crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+                    }
+                }
+                (!crc) as u64
+            }
+        }
+    }
+}
+
+impl FromStr for ChecksumAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "sum8" | "sum" => Ok(ChecksumAlgorithm::Sum8),
+            "xor" | "xor8" => Ok(ChecksumAlgorithm::Xor8),
+            "crc8" => Ok(ChecksumAlgorithm::Crc8),
+            "crc16-modbus" | "modbus" => Ok(ChecksumAlgorithm::Crc16Modbus),
+            "crc16-ccitt" | "ccitt" => Ok(ChecksumAlgorithm::Crc16Ccitt),
+            "crc32" => Ok(ChecksumAlgorithm::Crc32),
+            other => Err(format!("Unknown checksum algorithm: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ChecksumAlgorithm::Sum8 => "SUM8",
+            ChecksumAlgorithm::Xor8 => "XOR8",
+            ChecksumAlgorithm::Crc8 => "CRC-8",
+            ChecksumAlgorithm::Crc16Modbus => "CRC-16/MODBUS",
+            ChecksumAlgorithm::Crc16Ccitt => "CRC-16/CCITT",
+            ChecksumAlgorithm::Crc32 => "CRC-32",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 参与校验计算的字节范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChecksumRange {
+    /// 起始偏移
+    pub start: usize,
+    /// 末尾排除的字节数 (不含校验字段本身)
+    pub skip_tail: usize,
+}
+
+impl FromStr for ChecksumRange {
+    type Err = String;
+
+    /// 解析 `START[:-N]`，如 `1` 表示跳过首字节，`2:-1` 表示跳过前 2 字节和末尾 1 字节
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once(':').unwrap_or((s, ""));
+        let start = if start.is_empty() {
+            0
+        } else {
+            start.parse().map_err(|_| format!("Invalid range start: {}", start))?
+        };
+        let skip_tail = if end.is_empty() || end == "0" {
+            0
+        } else {
+            end.strip_prefix('-')
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| format!("Range end must be a negative offset like -1, got {}", end))?
+        };
+        Ok(ChecksumRange { start, skip_tail })
+    }
+}
+
+/// 校验值的使用方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumMode {
+    /// 仅追加到发送数据
+    Append,
+    /// 仅校验收到的帧
+    Verify,
+    /// 追加并校验
+    #[default]
+    Both,
+}
+
+impl ChecksumMode {
+    /// 是否在发送数据末尾追加校验值
+    pub fn appends(&self) -> bool {
+        *self != ChecksumMode::Verify
+    }
+
+    /// 是否校验收到的帧
+    pub fn verifies(&self) -> bool {
+        *self != ChecksumMode::Append
+    }
+}
+
+impl FromStr for ChecksumMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "append" => Ok(ChecksumMode::Append),
+            "verify" => Ok(ChecksumMode::Verify),
+            "both" => Ok(ChecksumMode::Both),
+            other => Err(format!("Unknown checksum mode: {} (expected append, verify or both)", other)),
+        }
+    }
+}
+
+/// 校验不匹配的详情
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    /// 使用的算法
+    pub algorithm: ChecksumAlgorithm,
+    /// 根据数据计算得到的值
+    pub expected: u64,
+    /// 帧中携带的值 (帧过短时为 None)
+    pub actual: Option<u64>,
+}
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.algorithm.width() * 2;
+        match self.actual {
+            Some(actual) => write!(
+                f,
+                "{} mismatch: expected {:0width$X}, got {:0width$X}",
+                self.algorithm,
+                self.expected,
+                actual,
+                width = digits
+            ),
+            None => write!(f, "{} mismatch: frame too short", self.algorithm),
+        }
+    }
+}
+
+/// 校验配置
+#[derive(Debug, Clone)]
+pub struct ChecksumConfig {
+    /// 校验算法
+    pub algorithm: ChecksumAlgorithm,
+    /// 校验字段字节序
+    pub endian: Endian,
+    /// 参与计算的字节范围
+    pub range: ChecksumRange,
+    /// 是否在发送的十六进制/二进制消息末尾追加校验值
+    pub append: bool,
+    /// 是否校验收到的帧
+    pub verify: bool,
+}
+
+impl ChecksumConfig {
+    pub fn new(algorithm: ChecksumAlgorithm) -> Self {
+        Self {
+            algorithm,
+            endian: algorithm.default_endian(),
+            range: ChecksumRange::default(),
+            append: true,
+            verify: true,
+        }
+    }
+
+    /// 按配置的范围计算校验值
+    fn compute(&self, data: &[u8]) -> u64 {
+        let end = data.len().saturating_sub(self.range.skip_tail);
+        let start = self.range.start.min(end);
+        self.algorithm.compute(&data[start..end])
+    }
+
+    /// 在数据末尾追加校验值
+    pub fn append_to(&self, data: &[u8]) -> Vec<u8> {
+        let mut frame = data.to_vec();
+        frame.extend_from_slice(&write_uint(self.compute(data), self.algorithm.width(), self.endian));
+        frame
+    }
+
+    /// 校验以校验值结尾的帧
+    pub fn verify_frame(&self, frame: &[u8]) -> Result<(), ChecksumMismatch> {
+        let width = self.algorithm.width();
+        if frame.len() < width {
+            return Err(ChecksumMismatch {
+                algorithm: self.algorithm,
+                expected: self.algorithm.compute(&[]),
+                actual: None,
+            });
+        }
+
+        let (data, field) = frame.split_at(frame.len() - width);
+        let expected = self.compute(data);
+        let actual = read_uint(field, self.endian);
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(ChecksumMismatch {
+                algorithm: self.algorithm,
+                expected,
+                actual: Some(actual),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECK_INPUT: &[u8] = b"123456789";
+
+    #[test]
+    fn test_check_values() {
+        assert_eq!(ChecksumAlgorithm::Sum8.compute(CHECK_INPUT), 0xDD);
+        assert_eq!(ChecksumAlgorithm::Xor8.compute(CHECK_INPUT), 0x31);
+        assert_eq!(ChecksumAlgorithm::Crc8.compute(CHECK_INPUT), 0xF4);
+        assert_eq!(ChecksumAlgorithm::Crc16Modbus.compute(CHECK_INPUT), 0x4B37);
+        assert_eq!(ChecksumAlgorithm::Crc16Ccitt.compute(CHECK_INPUT), 0x29B1);
+        assert_eq!(ChecksumAlgorithm::Crc32.compute(CHECK_INPUT), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_modbus_append_and_verify() {
+        // Modbus RTU 读保持寄存器请求，CRC 低字节在前
+        let config = ChecksumConfig::new(ChecksumAlgorithm::Crc16Modbus);
+        let frame = config.append_to(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]);
+        assert_eq!(&frame[6..], &[0xC5, 0xCD]);
+        assert!(config.verify_frame(&frame).is_ok());
+
+        let mut corrupted = frame.clone();
+        corrupted[2] ^= 0xFF;
+        let mismatch = config.verify_frame(&corrupted).unwrap_err();
+        assert_eq!(mismatch.actual, Some(0xCDC5));
+    }
+
+    #[test]
+    fn test_range() {
+        let mut config = ChecksumConfig::new(ChecksumAlgorithm::Sum8);
+        config.range = "1:-1".parse().unwrap();
+        // 只计算中间两个字节 0x02 + 0x03
+        assert_eq!(config.append_to(&[0x01, 0x02, 0x03, 0x04]), vec![0x01, 0x02, 0x03, 0x04, 0x05]);
+        assert!("1:2".parse::<ChecksumRange>().is_err());
+    }
+
+    #[test]
+    fn test_mode() {
+        let mode: ChecksumMode = "Verify".parse().unwrap();
+        assert!(!mode.appends() && mode.verifies());
+        assert!(ChecksumMode::default().appends() && ChecksumMode::default().verifies());
+        assert!("check".parse::<ChecksumMode>().is_err());
+    }
+}
@@ -1,3 +1,48 @@
+use std::str::FromStr;
+
+/// 多字节整数的字节序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endian {
+    /// 大端 (网络字节序)
+    #[default]
+    Big,
+    /// 小端
+    Little,
+}
+
+impl FromStr for Endian {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "be" | "big" => Ok(Endian::Big),
+            "le" | "little" => Ok(Endian::Little),
+            other => Err(format!("Invalid endianness: {}", other)),
+        }
+    }
+}
+
+/// 按指定字节序将整数写为 `width` 字节 (width 取 1/2/4/8)
+pub fn write_uint(value: u64, width: usize, endian: Endian) -> Vec<u8> {
+    let bytes = match endian {
+        Endian::Big => value.to_be_bytes(),
+        Endian::Little => value.to_le_bytes(),
+    };
+    match endian {
+        Endian::Big => bytes[8 - width..].to_vec(),
+        Endian::Little => bytes[..width].to_vec(),
+    }
+}
+
+/// 按指定字节序读取 `data` 中的无符号整数 (最多 8 字节)
+pub fn read_uint(data: &[u8], endian: Endian) -> u64 {
+    let iter: Box<dyn Iterator<Item = &u8>> = match endian {
+        Endian::Big => Box::new(data.iter()),
+        Endian::Little => Box::new(data.iter().rev()),
+    };
+    iter.fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
+
 /// 将字节数据转换为十六进制字符串
 pub fn bytes_to_hex(bytes: &[u8]) -> String {
     bytes
@@ -9,9 +54,18 @@ pub fn bytes_to_hex(bytes: &[u8]) -> String {
 
 /// 将十六进制字符串转换为字节数据
 pub fn hex_to_bytes(hex_str: &str) -> Result<Vec<u8>, String> {
-    // 移除所有空格
-    let hex_str = hex_str.replace(' ', "");
+    // 移除可能的 0x 前缀和所有空白字符
+    let hex_str = hex_str.trim();
+    let hex_str = hex_str
+        .strip_prefix("0x")
+        .or_else(|| hex_str.strip_prefix("0X"))
+        .unwrap_or(hex_str);
+    let hex_str: String = hex_str.chars().filter(|c| !c.is_whitespace()).collect();
     
+    if !hex_str.is_ascii() {
+        return Err("Invalid hex characters".to_string());
+    }
+
     // 验证字符串长度是偶数
     if hex_str.len() % 2 != 0 {
         return Err("Invalid hex string length".to_string());
@@ -74,4 +128,12 @@ mod tests {
         assert!(hex_to_bytes("0102ABFG").is_err()); // 非法字符 'G'
         assert!(hex_to_bytes("0102ABF").is_err());  // 奇数长度
     }
+
+    #[test]
+    fn test_uint_endian() {
+        assert_eq!(write_uint(0x0102, 2, Endian::Big), vec![0x01, 0x02]);
+        assert_eq!(write_uint(0x0102, 4, Endian::Little), vec![0x02, 0x01, 0x00, 0x00]);
+        assert_eq!(read_uint(&[0x01, 0x02], Endian::Big), 0x0102);
+        assert_eq!(read_uint(&[0x02, 0x01, 0x00, 0x00], Endian::Little), 0x0102);
+    }
 }
\ No newline at end of file
@@ -1 +1,4 @@
-pub mod data_format;
\ No newline at end of file
+pub mod checksum;
+pub mod data_format;
+pub mod random;
+pub mod template;
\ No newline at end of file
@@ -0,0 +1,511 @@
+use std::fmt;
+
+use crate::utils::checksum::ChecksumAlgorithm;
+use crate::utils::data_format::{bytes_to_hex, hex_to_bytes, read_uint, write_uint, Endian};
+
+/// 字段类型
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    /// 无符号整数 (字节数, 字节序)
+    Uint(usize, Endian),
+    /// 有符号整数 (字节数, 字节序)
+    Int(usize, Endian),
+    /// 字节串，可指定固定长度
+    Bytes(Option<usize>),
+    /// UTF-8 字符串，可指定固定长度
+    Str(Option<usize>),
+    /// 校验值，覆盖之前的所有字节
+    Checksum(ChecksumAlgorithm, Endian),
+}
+
+impl FieldType {
+    /// 固定长度字段的字节数，变长字段返回 None
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self {
+            FieldType::Uint(width, _) | FieldType::Int(width, _) => Some(*width),
+            FieldType::Bytes(size) | FieldType::Str(size) => *size,
+            FieldType::Checksum(algorithm, _) => Some(algorithm.width()),
+        }
+    }
+
+    /// 解析整数类型名，如 `u8`、`u16be`、`i32le`
+    fn parse_int(name: &str) -> Option<Self> {
+        let (signed, rest) = match name.as_bytes().first()? {
+            b'u' => (false, &name[1..]),
+            b'i' => (true, &name[1..]),
+            _ => return None,
+        };
+        let (bits, endian) = if let Some(bits) = rest.strip_suffix("le") {
+            (bits, Endian::Little)
+        } else {
+            (rest.strip_suffix("be").unwrap_or(rest), Endian::Big)
+        };
+        let width = match bits {
+            "8" => 1,
+            "16" => 2,
+            "32" => 4,
+            "64" => 8,
+            _ => return None,
+        };
+        Some(if signed {
+            FieldType::Int(width, endian)
+        } else {
+            FieldType::Uint(width, endian)
+        })
+    }
+}
+
+/// 字段取值表达式
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldExpr {
+    /// 整数常量
+    Const(u64),
+    /// 字节常量 (用于 bytes/str 字段)
+    ConstBytes(Vec<u8>),
+    /// 另一个字段的字节长度
+    Len(String),
+}
+
+/// 模板字段
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    /// 字段名
+    pub name: String,
+    /// 字段类型
+    pub ty: FieldType,
+    /// 取值表达式，没有表达式的字段需要用户输入
+    pub expr: Option<FieldExpr>,
+}
+
+impl Field {
+    /// 是否需要用户输入
+    pub fn is_input(&self) -> bool {
+        self.expr.is_none() && !matches!(self.ty, FieldType::Checksum(..))
+    }
+}
+
+/// 二进制结构模板
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// 模板名
+    pub name: String,
+    /// 字段列表
+    pub fields: Vec<Field>,
+}
+
+/// 解析出的字段值
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedField {
+    /// 字段名
+    pub name: String,
+    /// 格式化后的值
+    pub value: String,
+}
+
+/// 模板匹配的解析结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedFrame {
+    /// 模板名
+    pub template: String,
+    /// 各字段的值
+    pub fields: Vec<ParsedField>,
+}
+
+impl fmt::Display for ParsedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.template)?;
+        for field in &self.fields {
+            write!(f, " {}={}", field.name, field.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// 解析模板文件内容
+///
+/// 文件由若干 `struct name { ... }` 块组成；没有 `struct` 块时整个文件作为一个名为
+/// `default_name` 的模板。字段以 `;` 或换行分隔，`#` 或 `//` 开始注释，例如:
+/// `u8 cmd = 0x03; u16be len = len(payload); bytes payload; crc16 modbus`
+pub fn parse_templates(source: &str, default_name: &str) -> Result<Vec<Template>, String> {
+    // 去掉注释
+    let source: String = source
+        .lines()
+        .map(|line| {
+            let end = [line.find('#'), line.find("//")].into_iter().flatten().min().unwrap_or(line.len());
+            &line[..end]
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if !source.lines().any(|line| line.trim_start().starts_with("struct ")) {
+        return Ok(vec![parse_template(default_name, &source)?]);
+    }
+
+    let mut templates = Vec::new();
+    let mut rest = source.trim();
+    while !rest.is_empty() {
+        let body_start = rest.find('{').ok_or("Expected '{' after struct name")?;
+        let header = rest[..body_start].trim();
+        let name = header
+            .strip_prefix("struct")
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| format!("Expected 'struct <name>', found '{}'", header))?;
+        let body_end = rest[body_start..].find('}').ok_or_else(|| format!("Missing '}}' in struct {}", name))?
+            + body_start;
+        templates.push(parse_template(name, &rest[body_start + 1..body_end])?);
+        rest = rest[body_end + 1..].trim_start_matches(';').trim();
+    }
+
+    Ok(templates)
+}
+
+/// 解析单个模板的字段定义
+fn parse_template(name: &str, body: &str) -> Result<Template, String> {
+    let mut fields: Vec<Field> = Vec::new();
+    for statement in body.split([';', '\n']).map(str::trim).filter(|s| !s.is_empty()) {
+        let field = parse_field(statement).map_err(|e| format!("{} in '{}'", e, statement))?;
+        if fields.iter().any(|f| f.name == field.name) {
+            return Err(format!("Duplicate field '{}' in template {}", field.name, name));
+        }
+        fields.push(field);
+    }
+
+    // 变长字段必须由长度字段确定，或者之后只有固定长度字段
+    for (index, field) in fields.iter().enumerate() {
+        if field.ty.fixed_size().is_none() && find_len_field(&fields, &field.name).is_none() {
+            if let Some(other) = fields[index + 1..].iter().find(|f| f.ty.fixed_size().is_none()) {
+                return Err(format!(
+                    "Variable-length field '{}' must have a length field when followed by '{}'",
+                    field.name, other.name
+                ));
+            }
+        }
+        if let Some(FieldExpr::Len(target)) = &field.expr {
+            if !fields.iter().any(|f| &f.name == target) {
+                return Err(format!("Unknown field '{}' in len()", target));
+            }
+        }
+    }
+
+    if fields.is_empty() {
+        return Err(format!("Template {} has no fields", name));
+    }
+
+    Ok(Template {
+        name: name.to_string(),
+        fields,
+    })
+}
+
+/// 解析单个字段语句
+fn parse_field(statement: &str) -> Result<Field, String> {
+    let (decl, expr) = match statement.split_once('=') {
+        Some((decl, expr)) => (decl.trim(), Some(expr.trim())),
+        None => (statement, None),
+    };
+    let tokens: Vec<&str> = decl.split_whitespace().collect();
+    if tokens.is_empty() {
+        return Err("Missing field type".to_string());
+    }
+    let type_name = tokens[0].to_ascii_lowercase();
+
+    // 校验字段: crc16 modbus [le|be] [name]
+    let checksum = match type_name.as_str() {
+        "crc16" => {
+            let variant = tokens.get(1).ok_or("crc16 requires a variant (modbus or ccitt)")?;
+            Some((format!("crc16-{}", variant).parse::<ChecksumAlgorithm>()?, 2))
+        }
+        "crc8" | "crc32" | "sum8" | "xor8" => Some((type_name.parse::<ChecksumAlgorithm>()?, 1)),
+        _ => None,
+    };
+    if let Some((algorithm, consumed)) = checksum {
+        let mut endian = algorithm.default_endian();
+        let mut name = type_name.clone();
+        for token in &tokens[consumed..] {
+            match token.parse::<Endian>() {
+                Ok(e) => endian = e,
+                Err(_) => name = token.to_string(),
+            }
+        }
+        if expr.is_some() {
+            return Err("Checksum fields cannot have a value".to_string());
+        }
+        return Ok(Field {
+            name,
+            ty: FieldType::Checksum(algorithm, endian),
+            expr: None,
+        });
+    }
+
+    if tokens.len() != 2 {
+        return Err("Expected '<type> <name>'".to_string());
+    }
+
+    let ty = if let Some(ty) = FieldType::parse_int(&type_name) {
+        ty
+    } else {
+        let (base, size) = match type_name.split_once('[') {
+            Some((base, size)) => {
+                let size = size
+                    .strip_suffix(']')
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .ok_or_else(|| format!("Invalid size in '{}'", type_name))?;
+                (base, Some(size))
+            }
+            None => (type_name.as_str(), None),
+        };
+        match base {
+            "bytes" => FieldType::Bytes(size),
+            "str" => FieldType::Str(size),
+            _ => return Err(format!("Unknown type '{}'", tokens[0])),
+        }
+    };
+
+    let expr = match expr {
+        None => None,
+        Some(expr) => Some(if let Some(target) = expr.strip_prefix("len(").and_then(|e| e.strip_suffix(')')) {
+            FieldExpr::Len(target.trim().to_string())
+        } else {
+            match &ty {
+                FieldType::Bytes(_) => FieldExpr::ConstBytes(hex_to_bytes(expr)?),
+                FieldType::Str(_) => FieldExpr::ConstBytes(expr.trim_matches('"').as_bytes().to_vec()),
+                _ => FieldExpr::Const(parse_number(expr)?),
+            }
+        }),
+    };
+
+    Ok(Field {
+        name: tokens[1].to_string(),
+        ty,
+        expr,
+    })
+}
+
+/// 解析十进制或 0x 前缀的十六进制整数，支持负数
+fn parse_number(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse::<u64>(),
+    }
+    .map_err(|_| format!("Invalid number '{}'", text))?;
+    Ok(if negative { (value as i64).wrapping_neg() as u64 } else { value })
+}
+
+/// 查找以 `len(target)` 作为取值的长度字段
+fn find_len_field<'a>(fields: &'a [Field], target: &str) -> Option<&'a Field> {
+    fields
+        .iter()
+        .find(|f| matches!(&f.expr, Some(FieldExpr::Len(name)) if name == target))
+}
+
+impl Template {
+    /// 需要用户输入的字段
+    pub fn input_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter(|f| f.is_input())
+    }
+
+    /// 按用户输入序列化为字节，`inputs` 与 `input_fields` 一一对应
+    pub fn encode(&self, inputs: &[String]) -> Result<Vec<u8>, String> {
+        let mut inputs = inputs.iter();
+
+        // 先确定所有字节串/字符串字段的内容，以便计算长度
+        let mut values: Vec<Option<Vec<u8>>> = Vec::with_capacity(self.fields.len());
+        for field in &self.fields {
+            let value = match (&field.ty, &field.expr) {
+                (FieldType::Checksum(..), _) | (_, Some(FieldExpr::Len(_))) => None,
+                (_, Some(FieldExpr::ConstBytes(bytes))) => Some(bytes.clone()),
+                (ty, Some(FieldExpr::Const(value))) => Some(encode_int(ty, *value)),
+                (ty, None) => {
+                    let input = inputs.next().map(String::as_str).unwrap_or("");
+                    let bytes = match ty {
+                        FieldType::Bytes(_) => hex_to_bytes(input)?,
+                        FieldType::Str(_) => input.as_bytes().to_vec(),
+                        _ => encode_int(ty, parse_number(input).map_err(|e| format!("{}: {}", field.name, e))?),
+                    };
+                    Some(bytes)
+                }
+            };
+
+            // 固定长度的字节串按长度截断或补零
+            let value = match (&field.ty, value) {
+                (FieldType::Bytes(Some(size)) | FieldType::Str(Some(size)), Some(mut bytes)) => {
+                    bytes.resize(*size, 0);
+                    Some(bytes)
+                }
+                (_, value) => value,
+            };
+            values.push(value);
+        }
+
+        let mut frame = Vec::new();
+        for (index, field) in self.fields.iter().enumerate() {
+            match (&field.ty, &field.expr) {
+                (FieldType::Checksum(algorithm, endian), _) => {
+                    let checksum = algorithm.compute(&frame);
+                    frame.extend_from_slice(&write_uint(checksum, algorithm.width(), *endian));
+                }
+                (ty, Some(FieldExpr::Len(target))) => {
+                    let target_index = self.fields.iter().position(|f| &f.name == target).unwrap_or(index);
+                    let len = values[target_index].as_ref().map(Vec::len).unwrap_or(0);
+                    frame.extend_from_slice(&encode_int(ty, len as u64));
+                }
+                _ => frame.extend_from_slice(values[index].as_deref().unwrap_or_default()),
+            }
+        }
+
+        Ok(frame)
+    }
+
+    /// 尝试按模板解析收到的帧，常量、长度或校验不匹配时返回 None
+    pub fn parse(&self, data: &[u8]) -> Option<ParsedFrame> {
+        let mut offset = 0;
+        let mut lengths: Vec<(&str, u64)> = Vec::new();
+        let mut fields = Vec::with_capacity(self.fields.len());
+
+        for (index, field) in self.fields.iter().enumerate() {
+            let size = match field.ty.fixed_size() {
+                Some(size) => size,
+                None => match find_len_field(&self.fields, &field.name) {
+                    Some(len_field) => lengths.iter().find(|(name, _)| *name == len_field.name)?.1 as usize,
+                    None => {
+                        let tail: usize = self.fields[index + 1..].iter().filter_map(|f| f.ty.fixed_size()).sum();
+                        data.len().checked_sub(offset + tail)?
+                    }
+                },
+            };
+            let bytes = data.get(offset..offset.checked_add(size)?)?;
+
+            let value = match &field.ty {
+                FieldType::Uint(_, endian) | FieldType::Int(_, endian) => {
+                    let value = read_uint(bytes, *endian);
+                    match &field.expr {
+                        Some(FieldExpr::Const(expected)) if encode_int(&field.ty, *expected) != encode_int(&field.ty, value) => {
+                            return None
+                        }
+                        Some(FieldExpr::Len(_)) => lengths.push((&field.name, value)),
+                        _ => {}
+                    }
+                    format_int(&field.ty, value)
+                }
+                FieldType::Bytes(_) | FieldType::Str(_) => {
+                    if let Some(FieldExpr::ConstBytes(expected)) = &field.expr {
+                        if expected.as_slice() != bytes {
+                            return None;
+                        }
+                    }
+                    match field.ty {
+                        FieldType::Str(_) => format!("\"{}\"", String::from_utf8_lossy(bytes)),
+                        _ => format!("[{}]", bytes_to_hex(bytes)),
+                    }
+                }
+                FieldType::Checksum(algorithm, endian) => {
+                    if algorithm.compute(&data[..offset]) != read_uint(bytes, *endian) {
+                        return None;
+                    }
+                    "OK".to_string()
+                }
+            };
+
+            fields.push(ParsedField {
+                name: field.name.clone(),
+                value,
+            });
+            offset += size;
+        }
+
+        // 必须恰好消耗整个帧
+        if offset != data.len() {
+            return None;
+        }
+
+        Some(ParsedFrame {
+            template: self.name.clone(),
+            fields,
+        })
+    }
+}
+
+/// 按整数字段类型编码
+fn encode_int(ty: &FieldType, value: u64) -> Vec<u8> {
+    match ty {
+        FieldType::Uint(width, endian) | FieldType::Int(width, endian) => write_uint(value, *width, *endian),
+        _ => Vec::new(),
+    }
+}
+
+/// 格式化整数字段: 无符号显示十进制和十六进制，有符号做符号扩展
+fn format_int(ty: &FieldType, value: u64) -> String {
+    match ty {
+        FieldType::Int(width, _) => {
+            let shift = 64 - width * 8;
+            (((value << shift) as i64) >> shift).to_string()
+        }
+        FieldType::Uint(width, _) => format!("{} (0x{:0w$X})", value, value, w = width * 2),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODBUS_TEMPLATE: &str = "u8 addr; u8 func = 0x03; u16be start; u16be count; crc16 modbus";
+
+    #[test]
+    fn test_encode_modbus_request() {
+        let templates = parse_templates(MODBUS_TEMPLATE, "read").unwrap();
+        let template = &templates[0];
+        assert_eq!(template.input_fields().count(), 3);
+
+        let frame = template
+            .encode(&["1".to_string(), "0".to_string(), "10".to_string()])
+            .unwrap();
+        assert_eq!(frame, vec![0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD]);
+
+        let parsed = template.parse(&frame).unwrap();
+        assert_eq!(parsed.fields[3].value, "10 (0x000A)");
+        assert_eq!(parsed.fields[4].value, "OK");
+
+        // 常量或校验不匹配的帧不应被识别
+        assert!(template.parse(&[0x01, 0x04, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD]).is_none());
+        assert!(template.parse(&frame[..7]).is_none());
+    }
+
+    #[test]
+    fn test_length_field_and_structs() {
+        let source = r#"
+            # 带长度的消息
+            struct msg {
+                u8 cmd
+                u16le len = len(payload)
+                bytes payload
+                str[2] tag = "OK"
+            }
+            struct ping { u8 cmd = 0x01 }
+        "#;
+        let templates = parse_templates(source, "unused").unwrap();
+        assert_eq!(templates.len(), 2);
+
+        let frame = templates[0].encode(&["0x10".to_string(), "AA BB CC".to_string()]).unwrap();
+        assert_eq!(frame, vec![0x10, 0x03, 0x00, 0xAA, 0xBB, 0xCC, b'O', b'K']);
+
+        let parsed = templates[0].parse(&frame).unwrap();
+        assert_eq!(parsed.to_string(), "msg: cmd=16 (0x10) len=3 (0x0003) payload=[AA BB CC] tag=\"OK\"");
+        assert!(templates[1].parse(&frame).is_none());
+        assert!(templates[1].parse(&[0x01]).is_some());
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert!(parse_templates("u24 x", "t").is_err());
+        assert!(parse_templates("bytes a; bytes b", "t").is_err());
+        assert!(parse_templates("u8 len = len(missing)", "t").is_err());
+        assert!(parse_templates("u8 a; u8 a", "t").is_err());
+    }
+}